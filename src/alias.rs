@@ -0,0 +1,45 @@
+//! Arbitrum L1 -> L2 address aliasing.
+//!
+//! Messages sent from an L1 contract through a retryable ticket arrive on L2
+//! with the sender shifted by a fixed offset, so an L1 contract can never
+//! impersonate an L2 account with the same address.
+
+use stylus_sdk::alloy_primitives::{address, Address, U160};
+
+/// Offset added to an L1 contract address to obtain its L2 alias.
+pub const L1_ALIAS_OFFSET: Address = address!("1111000000000000000000000000000000001111");
+
+/// Returns the address an L1 contract at `l1_address` appears as on L2.
+pub fn apply_l1_alias(l1_address: Address) -> Address {
+    Address::from(to_u160(l1_address).wrapping_add(to_u160(L1_ALIAS_OFFSET)))
+}
+
+/// Returns the L1 address behind the aliased L2 sender `l2_address`.
+pub fn undo_l1_alias(l2_address: Address) -> Address {
+    Address::from(to_u160(l2_address).wrapping_sub(to_u160(L1_ALIAS_OFFSET)))
+}
+
+fn to_u160(address: Address) -> U160 {
+    U160::from_be_bytes(address.into_array())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_alias_round_trip() {
+        let l1 = address!("0000000000000000000000000000000000000001");
+        let aliased = apply_l1_alias(l1);
+        assert_eq!(aliased, address!("1111000000000000000000000000000000001112"));
+        assert_eq!(undo_l1_alias(aliased), l1);
+    }
+
+    #[test]
+    fn test_alias_wraps_around() {
+        let l1 = address!("ffffffffffffffffffffffffffffffffffffffff");
+        let aliased = apply_l1_alias(l1);
+        assert_eq!(aliased, address!("1111000000000000000000000000000000001110"));
+        assert_eq!(undo_l1_alias(aliased), l1);
+    }
+}
@@ -0,0 +1,86 @@
+//! Packed boolean flags, 256 per storage word, keyed by a numeric index.
+//!
+//! Used instead of `mapping(address => bool)` for large cohorts: addresses are
+//! first assigned an index through the contract's member registry and their
+//! flags then share storage slots with 255 neighbours.
+
+use alloc::vec::Vec;
+
+use stylus_sdk::{alloy_primitives::U256, prelude::*};
+
+sol_storage! {
+    pub struct Bitmap {
+        mapping(uint256 => uint256) words;
+    }
+}
+
+impl Bitmap {
+    pub fn get(&self, index: U256) -> bool {
+        let (word, mask) = locate(index);
+        self.words.get(word) & mask != U256::ZERO
+    }
+
+    pub fn set(&mut self, index: U256) {
+        let (word, mask) = locate(index);
+        let current = self.words.get(word);
+        self.words.setter(word).set(current | mask);
+    }
+
+    pub fn unset(&mut self, index: U256) {
+        let (word, mask) = locate(index);
+        let current = self.words.get(word);
+        self.words.setter(word).set(current & !mask);
+    }
+
+    pub fn set_to(&mut self, index: U256, value: bool) {
+        if value {
+            self.set(index);
+        } else {
+            self.unset(index);
+        }
+    }
+}
+
+fn locate(index: U256) -> (U256, U256) {
+    let bit = (index & U256::from(0xff)).to::<usize>();
+    (index >> 8, U256::from(1) << bit)
+}
+
+#[cfg(test)]
+mod tests {
+    use stylus_sdk::testing::TestVM;
+
+    use super::*;
+
+    #[test]
+    fn test_set_get_unset() {
+        let vm = TestVM::default();
+        let mut bitmap = Bitmap::from(&vm);
+
+        assert!(!bitmap.get(U256::from(3)));
+        bitmap.set(U256::from(3));
+        assert!(bitmap.get(U256::from(3)));
+        assert!(!bitmap.get(U256::from(2)));
+        assert!(!bitmap.get(U256::from(4)));
+
+        bitmap.unset(U256::from(3));
+        assert!(!bitmap.get(U256::from(3)));
+    }
+
+    #[test]
+    fn test_word_boundaries_are_independent() {
+        let vm = TestVM::default();
+        let mut bitmap = Bitmap::from(&vm);
+
+        bitmap.set_to(U256::from(255), true);
+        bitmap.set_to(U256::from(256), true);
+        assert!(bitmap.get(U256::from(255)));
+        assert!(bitmap.get(U256::from(256)));
+        assert_eq!(bitmap.words.get(U256::ZERO), U256::from(1) << 255);
+        assert_eq!(bitmap.words.get(U256::from(1)), U256::from(1));
+
+        bitmap.set_to(U256::from(255), false);
+        assert!(!bitmap.get(U256::from(255)));
+        assert!(bitmap.get(U256::from(256)));
+    }
+}
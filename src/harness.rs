@@ -0,0 +1,349 @@
+//! Randomized state-machine test harness.
+//!
+//! Drives `RewardProcessor` through long random sequences of admin actions,
+//! accruals, pauses, shutdowns and refund transfers while a plain-Rust model
+//! tracks what the contract should look like. After every step the contract
+//! is checked against the model and against invariants that must hold in any
+//! state: solvency, role consistency, monotonic counters and pause semantics.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use alloy_primitives::{Address, U256};
+use stylus_sdk::{
+    stylus_core::host::AccountAccess,
+    testing::{TestVM, TestVMBuilder},
+};
+
+use crate::{RewardProcessor, ACTION_EMERGENCY_SHUTDOWN, REFUND_WINDOW, SHUTDOWN_TIMELOCK};
+
+const OWNER: Address = Address::new([0x01; 20]);
+const ACTORS: [Address; 5] = [
+    OWNER,
+    Address::new([0x02; 20]),
+    Address::new([0x03; 20]),
+    Address::new([0x04; 20]),
+    Address::new([0x05; 20]),
+];
+
+/// xorshift64*, so runs are reproducible from the seed alone.
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 >> 12;
+        self.0 ^= self.0 << 25;
+        self.0 ^= self.0 >> 27;
+        self.0.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    fn below(&mut self, bound: u64) -> u64 {
+        self.next() % bound
+    }
+
+    fn flip(&mut self) -> bool {
+        self.below(2) == 0
+    }
+
+    fn actor(&mut self) -> Address {
+        ACTORS[self.below(ACTORS.len() as u64) as usize]
+    }
+}
+
+/// What the contract state should be after the steps so far.
+#[derive(Default)]
+struct Model {
+    now: u64,
+    guardian: Address,
+    operators: BTreeSet<Address>,
+    members: BTreeSet<Address>,
+    deposits: BTreeMap<Address, U256>,
+    contract_balance: U256,
+    paused_until: u64,
+    shutdown_scheduled_at: Option<u64>,
+    refund_deadline: Option<u64>,
+    last_seq: U256,
+    last_round: U256,
+    accrued: BTreeMap<Address, U256>,
+    refunds_paid: usize,
+}
+
+impl Model {
+    fn paused(&self) -> bool {
+        self.now < self.paused_until
+    }
+
+    fn shut_down(&self) -> bool {
+        self.refund_deadline.is_some()
+    }
+
+    fn can_pause(&self, sender: Address) -> bool {
+        sender == OWNER || (self.guardian != Address::ZERO && sender == self.guardian)
+    }
+}
+
+struct Harness {
+    vm: TestVM,
+    contract: RewardProcessor,
+    model: Model,
+    rng: Rng,
+}
+
+impl Harness {
+    fn new(seed: u64) -> Self {
+        let vm = TestVMBuilder::new().sender(OWNER).build();
+        vm.set_block_timestamp(1);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let model = Model {
+            now: 1,
+            ..Model::default()
+        };
+        Self {
+            vm,
+            contract,
+            model,
+            rng: Rng(seed.wrapping_mul(0x9e37_79b9_7f4a_7c15) | 1),
+        }
+    }
+
+    fn run(&mut self, steps: usize) {
+        for step in 0..steps {
+            let action = self.rng.below(11);
+            match action {
+                0 => self.fund(),
+                1 => self.set_operator(),
+                2 => self.set_guardian(),
+                3 => self.accrue(),
+                4 => self.pause(),
+                5 => self.try_unpause(),
+                6 => self.advance_time(),
+                7 => self.register_member(),
+                8 => self.schedule_shutdown(),
+                9 => self.activate_shutdown(),
+                _ => self.claim_refund(),
+            }
+            self.check_invariants(step, action);
+        }
+    }
+
+    fn fund(&mut self) {
+        let sponsor = self.rng.actor();
+        let value = U256::from(1 + self.rng.below(1_000));
+
+        self.vm.set_sender(sponsor);
+        self.vm.set_value(value);
+        let result = self.contract.fund();
+        self.vm.set_value(U256::ZERO);
+
+        let expected = !self.model.shut_down() && !self.model.paused();
+        assert_eq!(result.is_ok(), expected, "fund: {result:?}");
+        if result.is_ok() {
+            // The test VM does not move call value, so credit the contract like the chain would.
+            self.model.contract_balance += value;
+            self.vm.set_balance(self.vm.contract_address(), self.model.contract_balance);
+            *self.model.deposits.entry(sponsor).or_default() += value;
+        }
+    }
+
+    fn set_operator(&mut self) {
+        let sender = self.rng.actor();
+        let operator = self.rng.actor();
+        let enabled = self.rng.flip();
+
+        self.vm.set_sender(sender);
+        let result = self.contract.set_operator(operator, enabled);
+        assert_eq!(result.is_ok(), sender == OWNER, "set_operator: {result:?}");
+        if result.is_ok() {
+            if enabled {
+                self.model.operators.insert(operator);
+            } else {
+                self.model.operators.remove(&operator);
+            }
+        }
+    }
+
+    fn set_guardian(&mut self) {
+        let sender = if self.rng.below(4) == 0 { self.rng.actor() } else { OWNER };
+        let guardian = ACTORS[1 + self.rng.below(ACTORS.len() as u64 - 1) as usize];
+
+        self.vm.set_sender(sender);
+        let result = self.contract.set_guardian(guardian);
+        assert_eq!(result.is_ok(), sender == OWNER, "set_guardian: {result:?}");
+        if result.is_ok() {
+            self.model.guardian = guardian;
+        }
+    }
+
+    fn accrue(&mut self) {
+        let sender = self.rng.actor();
+        let user = self.rng.actor();
+        let amount = U256::from(1 + self.rng.below(1_000_000));
+        let start_time = U256::from(self.model.now);
+        let end_time = start_time + U256::from(1 + self.rng.below(10_000));
+
+        self.vm.set_sender(sender);
+        let result = self.contract.accrue(user, amount, start_time, end_time, self.rng.flip(), self.rng.flip());
+
+        let authorized = sender == OWNER || self.model.operators.contains(&sender);
+        let expected = authorized && !self.model.shut_down() && !self.model.paused();
+        assert_eq!(result.is_ok(), expected, "accrue: {result:?}");
+        if let Ok(reward) = result {
+            assert!(reward > U256::ZERO);
+            *self.model.accrued.entry(user).or_default() += reward;
+        }
+    }
+
+    fn pause(&mut self) {
+        let sender = self.rng.actor();
+        let duration = 1 + self.rng.below(2 * 24 * 60 * 60);
+
+        self.vm.set_sender(sender);
+        let result = self.contract.pause_for(U256::from(duration));
+        assert_eq!(result.is_ok(), self.model.can_pause(sender), "pause_for: {result:?}");
+        if result.is_ok() {
+            self.model.paused_until = self.model.now + duration;
+        }
+    }
+
+    fn try_unpause(&mut self) {
+        let sender = self.rng.actor();
+
+        self.vm.set_sender(sender);
+        let result = self.contract.try_unpause();
+        let expected = !self.model.paused() || self.model.can_pause(sender);
+        assert_eq!(result.is_ok(), expected, "try_unpause: {result:?}");
+        if result.is_ok() {
+            self.model.paused_until = 0;
+        }
+    }
+
+    fn advance_time(&mut self) {
+        self.model.now += self.rng.below(3 * 24 * 60 * 60);
+        self.vm.set_block_timestamp(self.model.now);
+    }
+
+    fn register_member(&mut self) {
+        let sender = if self.rng.flip() { OWNER } else { self.rng.actor() };
+        let member = self.rng.actor();
+
+        self.vm.set_sender(sender);
+        if self.contract.register_member(member).is_ok() {
+            self.model.members.insert(member);
+        }
+    }
+
+    fn schedule_shutdown(&mut self) {
+        // Rare, so most runs spend a while in the live state before shutting down.
+        if self.rng.below(4) != 0 {
+            return;
+        }
+
+        self.vm.set_sender(OWNER);
+        let result = self.contract.schedule_emergency_shutdown();
+        assert_eq!(result.is_ok(), !self.model.shut_down(), "schedule_emergency_shutdown: {result:?}");
+        if result.is_ok() {
+            self.model.shutdown_scheduled_at = Some(self.model.now);
+        }
+    }
+
+    fn activate_shutdown(&mut self) {
+        let guardian = self.model.guardian;
+        if guardian == Address::ZERO {
+            return;
+        }
+
+        self.vm.set_sender(OWNER);
+        self.contract.confirm_action(ACTION_EMERGENCY_SHUTDOWN).unwrap();
+        self.vm.set_sender(guardian);
+        self.contract.confirm_action(ACTION_EMERGENCY_SHUTDOWN).unwrap();
+
+        self.vm.set_sender(OWNER);
+        let result = self.contract.emergency_shutdown();
+        let timelock_elapsed = self
+            .model
+            .shutdown_scheduled_at
+            .is_some_and(|scheduled_at| self.model.now >= scheduled_at + SHUTDOWN_TIMELOCK);
+        let expected = !self.model.shut_down() && timelock_elapsed;
+        assert_eq!(result.is_ok(), expected, "emergency_shutdown: {result:?}");
+        if result.is_ok() {
+            self.model.refund_deadline = Some(self.model.now + REFUND_WINDOW);
+        }
+    }
+
+    fn claim_refund(&mut self) {
+        let sponsor = self.rng.actor();
+        let deposit = self.model.deposits.get(&sponsor).copied().unwrap_or_default();
+        let sponsor_balance = self.vm.balance(sponsor);
+
+        self.vm.set_sender(sponsor);
+        let result = self.contract.claim_refund();
+
+        let window_open = self.model.refund_deadline.is_some_and(|deadline| self.model.now <= deadline);
+        let expected = window_open && deposit > U256::ZERO;
+        assert_eq!(result.is_ok(), expected, "claim_refund: {result:?}");
+        if let Ok(amount) = result {
+            assert_eq!(amount, deposit);
+            assert_eq!(self.vm.balance(sponsor), sponsor_balance + amount);
+            self.model.contract_balance -= amount;
+            self.model.deposits.remove(&sponsor);
+            self.model.refunds_paid += 1;
+        }
+    }
+
+    fn check_invariants(&mut self, step: usize, action: u64) {
+        let context = format!("step {step}, action {action}");
+        let contract = &self.contract;
+        let model = &mut self.model;
+
+        // Solvency: every refundable wei is held by the contract.
+        let total_refundable = contract.total_refundable();
+        assert_eq!(self.vm.balance(self.vm.contract_address()), model.contract_balance, "{context}");
+        assert_eq!(contract.contract_balance(), model.contract_balance, "{context}");
+        assert!(contract.reserved_balance() >= total_refundable, "{context}");
+        assert!(model.contract_balance >= total_refundable, "{context}: insolvent");
+        assert_eq!(total_refundable, model.deposits.values().fold(U256::ZERO, |sum, deposit| sum + *deposit), "{context}");
+
+        // Issuance accounting.
+        let total_accrued = model.accrued.values().fold(U256::ZERO, |sum, reward| sum + *reward);
+        assert_eq!(contract.total_calculated(), total_accrued, "{context}");
+
+        // Role consistency.
+        assert_eq!(contract.guardian(), model.guardian, "{context}");
+        for actor in ACTORS {
+            assert_eq!(contract.is_operator(actor), model.operators.contains(&actor), "{context}: operator {actor}");
+            assert_eq!(contract.refundable_balance_of(actor), model.deposits.get(&actor).copied().unwrap_or_default(), "{context}");
+            assert_eq!(contract.compounded_balance_of(actor).unwrap(), model.accrued.get(&actor).copied().unwrap_or_default(), "{context}");
+        }
+        self.vm.set_sender(ACTORS[1]);
+        assert!(contract.assert_owner().is_err(), "{context}");
+        self.vm.set_sender(OWNER);
+        assert!(contract.assert_owner().is_ok(), "{context}");
+
+        // Monotonic counters.
+        let seq = contract.last_event_seq();
+        assert!(seq >= model.last_seq, "{context}: event_seq went backwards");
+        model.last_seq = seq;
+        let round = contract.lucky_draw_round();
+        assert!(round >= model.last_round, "{context}: draw round went backwards");
+        model.last_round = round;
+        assert_eq!(contract.member_count(), U256::from(model.members.len()), "{context}");
+
+        // Pause and shutdown semantics.
+        assert_eq!(contract.is_paused(), model.paused(), "{context}");
+        assert_eq!(contract.is_shut_down(), model.shut_down(), "{context}");
+    }
+}
+
+#[test]
+fn test_random_action_sequences_preserve_invariants() {
+    let mut refunds_paid = 0;
+    for seed in 1..=24 {
+        let mut harness = Harness::new(seed);
+        harness.run(200);
+        refunds_paid += harness.model.refunds_paid;
+    }
+
+    // Guards against the harness silently never reaching the shutdown and refund states.
+    assert!(refunds_paid > 0);
+}
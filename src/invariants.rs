@@ -0,0 +1,154 @@
+//! Property tests for the reward math.
+//!
+//! Each case configures a fresh `RewardProcessor` with random settings and
+//! checks invariants that must hold for any of them: decay never pays more
+//! later in the window, the reward never exceeds the amount plus its bonuses,
+//! and the rounding modes stay within one unit of each other per rounded term.
+
+use alloy_primitives::{Address, U256};
+use proptest::prelude::*;
+use stylus_sdk::testing::{TestVM, TestVMBuilder};
+
+use crate::{RewardProcessor, DEFAULT_MAX_MULTIPLY_FACTOR};
+
+const OWNER: Address = Address::new([0x01; 20]);
+
+/// Decayed amount, percentage bonus and strict bonus are each rounded once.
+const ROUNDED_TERMS: u64 = 3;
+
+#[derive(Clone, Debug)]
+struct Settings {
+    decay_mode: u8,
+    rounding_mode: u8,
+    multiply_factor: u64,
+    percentage_bonus: u64,
+    step_interval: u64,
+    midpoint: (u64, u64),
+    grace_period: u64,
+}
+
+fn settings() -> impl Strategy<Value = Settings> {
+    (
+        0u8..4,
+        0u8..3,
+        1..=DEFAULT_MAX_MULTIPLY_FACTOR,
+        1u64..=20_000,
+        1u64..=1_000_000,
+        (1u64..10_000, 5_000u64..=10_000),
+        0u64..=100_000,
+    )
+        .prop_map(|(decay_mode, rounding_mode, multiply_factor, percentage_bonus, step_interval, midpoint, grace_period)| Settings {
+            decay_mode,
+            rounding_mode,
+            multiply_factor,
+            percentage_bonus,
+            step_interval,
+            midpoint,
+            grace_period,
+        })
+}
+
+/// A window plus two observation times, the second no earlier than the first.
+fn window() -> impl Strategy<Value = (u64, u64, u64, u64)> {
+    (1_000_000u64..1_000_000_000, 1u64..100_000_000).prop_flat_map(|(start_time, duration)| {
+        let end_time = start_time + duration;
+        let times = (start_time - 1_000_000)..(end_time + 1_000_000);
+        (Just(start_time), Just(end_time), times.clone(), times).prop_map(|(start_time, end_time, a, b)| (start_time, end_time, a.min(b), a.max(b)))
+    })
+}
+
+fn amount() -> impl Strategy<Value = U256> {
+    (0u128..=u128::MAX).prop_map(U256::from)
+}
+
+fn deploy(settings: &Settings) -> (TestVM, RewardProcessor) {
+    let vm = TestVMBuilder::new().sender(OWNER).build();
+    let mut contract = RewardProcessor::from(&vm);
+    contract.constructor(U256::from(settings.multiply_factor)).unwrap();
+
+    contract.update_percentage_bonus(U256::from(settings.percentage_bonus)).unwrap();
+    contract.update_step_interval(U256::from(settings.step_interval)).unwrap();
+    contract.update_decay_midpoint(U256::from(settings.midpoint.0), U256::from(settings.midpoint.1)).unwrap();
+    contract.update_grace_period(U256::from(settings.grace_period)).unwrap();
+    contract.update_decay_mode(settings.decay_mode).unwrap();
+    contract.update_rounding_mode(settings.rounding_mode).unwrap();
+
+    (vm, contract)
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(128))]
+
+    #[test]
+    fn reward_never_increases_over_time(
+        settings in settings(),
+        (start_time, end_time, earlier, later) in window(),
+        amount in amount(),
+        has_bonus: bool,
+        has_strict_bonus: bool,
+    ) {
+        let (_vm, contract) = deploy(&settings);
+        let (start_time, end_time) = (U256::from(start_time), U256::from(end_time));
+
+        let first = contract.calculate_reward_at_time(amount, U256::from(earlier), start_time, end_time, has_bonus, has_strict_bonus).unwrap();
+        let second = contract.calculate_reward_at_time(amount, U256::from(later), start_time, end_time, has_bonus, has_strict_bonus).unwrap();
+        prop_assert!(second <= first, "reward grew from {first} at {earlier} to {second} at {later}");
+    }
+
+    #[test]
+    fn reward_is_capped_by_amount_plus_bonuses(
+        settings in settings(),
+        (start_time, end_time, current_time, _) in window(),
+        amount in amount(),
+        has_bonus: bool,
+        has_strict_bonus: bool,
+    ) {
+        let (_vm, contract) = deploy(&settings);
+
+        let reward = contract
+            .calculate_reward_at_time(amount, U256::from(current_time), U256::from(start_time), U256::from(end_time), has_bonus, has_strict_bonus)
+            .unwrap();
+
+        let denominator = contract.percentage_denominator();
+        let percentage_part = amount * (denominator + U256::from(settings.percentage_bonus)) / denominator;
+        let strict_part = amount * U256::from(settings.multiply_factor) / contract.strict_bonus_denominator();
+        let mut cap = percentage_part + strict_part + U256::from(ROUNDED_TERMS);
+        if contract.get_pseudo_random() {
+            cap *= U256::from(2);
+        }
+        prop_assert!(reward <= cap, "reward {reward} above cap {cap}");
+    }
+
+    #[test]
+    fn rounding_modes_stay_within_one_unit_per_term(
+        settings in settings(),
+        (start_time, end_time, current_time, _) in window(),
+        amount in amount(),
+        has_bonus: bool,
+        has_strict_bonus: bool,
+    ) {
+        let (_vm, mut contract) = deploy(&settings);
+        let mut breakdown_with = |mode: u8| {
+            contract.update_rounding_mode(mode).unwrap();
+            contract
+                .calculate_reward_detailed(amount, U256::from(current_time), U256::from(start_time), U256::from(end_time), has_bonus, has_strict_bonus)
+                .unwrap()
+        };
+        let (floor, ceil, half_up) = (breakdown_with(0), breakdown_with(1), breakdown_with(2));
+
+        // Bonuses are rounded directly, so the modes are ordered and at most one unit apart.
+        let one = U256::from(1);
+        for (floor, half_up, ceil) in [(floor.bonus, half_up.bonus, ceil.bonus), (floor.strict_bonus, half_up.strict_bonus, ceil.strict_bonus)] {
+            prop_assert!(floor <= half_up && half_up <= ceil && ceil - floor <= one, "floor {floor}, half-up {half_up}, ceil {ceil}");
+        }
+
+        // Rounding the decay up lowers the multiplier, so the decayed amount is not ordered by mode, but
+        // the multiplier moves by at most one unit and the amount is rounded once more on top.
+        let denominator = contract.percentage_denominator();
+        let multipliers = [floor.time_multiplier, half_up.time_multiplier, ceil.time_multiplier];
+        let decayed = [floor.decayed, half_up.decayed, ceil.decayed];
+        let spread = |values: [U256; 3]| values.iter().max().unwrap() - values.iter().min().unwrap();
+        prop_assert!(spread(multipliers) <= one, "multipliers {multipliers:?}");
+        prop_assert!(spread(decayed) <= amount / denominator + one, "decayed {decayed:?}");
+    }
+}
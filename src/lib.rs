@@ -1,574 +1,10494 @@
 #![cfg_attr(not(any(test, feature = "export-abi")), no_main)]
 #![cfg_attr(not(any(test, feature = "export-abi")), no_std)]
+#![recursion_limit = "512"]
 
 #[macro_use]
 extern crate alloc;
 
-use alloc::vec::Vec;
+pub mod alias;
+pub mod bitmap;
+#[cfg(test)]
+mod harness;
+#[cfg(test)]
+mod invariants;
+pub mod math;
+pub mod merkle;
+pub mod mul_div;
+pub mod reward_math;
+#[cfg(any(test, feature = "export-abi"))]
+pub mod schema;
+
+use alloc::{format, string::String, vec::Vec};
+
+use alloy_sol_types::{eip712_domain, sol, Eip712Domain, SolCall, SolEvent, SolStruct, SolValue};
+
+use crate::alias::undo_l1_alias;
+use crate::bitmap::Bitmap;
+use crate::math::{isqrt, WAD};
+use crate::mul_div::{mul_div, mul_div_rounding, Rounding};
+use crate::reward_math::DecayCurve;
+pub use crate::reward_math::{BoundaryPolicy, DecayMode};
+
+use stylus_sdk::{abi::Bytes, alloy_primitives::U256, alloy_primitives::U8, alloy_primitives::U64, alloy_primitives::B256, alloy_primitives::Address, prelude::*, crypto::keccak, stylus_core::calls::context::Call};
+
+/// Delay between scheduling and executing an emergency shutdown.
+pub const SHUTDOWN_TIMELOCK: u64 = 2 * 24 * 60 * 60;
+
+/// Initial upper bound for `multiply_factor`, 1000x in `strict_bonus_denominator` units.
+pub const DEFAULT_MAX_MULTIPLY_FACTOR: u64 = 10_000_000;
+
+/// Initial plateau length for `DecayMode::Step`, one week.
+pub const DEFAULT_STEP_INTERVAL: u64 = 7 * 24 * 60 * 60;
+
+/// Initial length of a participation epoch, one week.
+pub const DEFAULT_EPOCH_LENGTH: u64 = 7 * 24 * 60 * 60;
+
+/// How long owner and guardian confirmations of a destructive action stay valid.
+pub const CONFIRMATION_WINDOW: u64 = 24 * 60 * 60;
+
+/// Action id for `emergency_shutdown` in the two-person confirmation flow.
+pub const ACTION_EMERGENCY_SHUTDOWN: u8 = 1;
+
+/// How long sponsors can reclaim their deposits once the contract is shut down.
+pub const REFUND_WINDOW: u64 = 30 * 24 * 60 * 60;
+
+/// Denominator of user-chosen basis point settings such as the donation share.
+pub const BPS_DENOMINATOR: u64 = 10_000;
+
+/// Length of the year used to annualize rates.
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// Most points `sample_reward_curve` returns in one call.
+pub const MAX_CURVE_POINTS: u64 = 256;
+
+/// Latest timestamp accepted by the `u64` entry points, 3000-01-01T00:00:00Z.
+pub const MAX_TIMESTAMP: u64 = 32_503_680_000;
+
+/// Decimals amounts are normalized to before the reward math runs.
+pub const INTERNAL_DECIMALS: u8 = 18;
+
+/// Largest accepted `reward_decimals`, and the most decimals `approve_token` accepts from a token.
+pub const MAX_REWARD_DECIMALS: u8 = 36;
+
+/// Most campaigns `get_campaigns` returns in one call.
+pub const MAX_CAMPAIGN_PAGE: u64 = 100;
+
+/// Most times `extend_campaign` may push back one campaign's end.
+pub const MAX_CAMPAIGN_EXTENSIONS: u8 = 3;
+
+/// Largest `s` accepted in a claim signature, half the secp256k1 group order, so signatures are not malleable.
+const MAX_SIGNATURE_S: U256 = U256::from_be_bytes(alloy_primitives::hex!("7fffffffffffffffffffffffffffffff5d576e7357a4501ddfe92f46681b20a0"));
+
+/// Highest claim fee `set_claim_fee_bps` accepts, in basis points.
+pub const MAX_CLAIM_FEE_BPS: u64 = 1_000;
+
+/// Storage layout version new campaigns are created with; campaigns stored without one are version 1.
+pub const CAMPAIGN_VERSION: u8 = 2;
+
+sol! {
+    interface IERC20Metadata {
+        function decimals() external view returns (uint8);
+        function symbol() external view returns (string);
+    }
+
+    interface IERC20 {
+        function transfer(address to, uint256 amount) external returns (bool);
+        function transferFrom(address from, address to, uint256 amount) external returns (bool);
+        function balanceOf(address account) external view returns (uint256);
+        function allowance(address owner, address spender) external view returns (uint256);
+    }
+
+    interface IERC20Permit {
+        function permit(address owner, address spender, uint256 value, uint256 deadline, uint8 v, bytes32 r, bytes32 s) external;
+    }
+
+    interface IWETH {
+        function deposit() external payable;
+    }
+
+    interface ISybilScorer {
+        function score(address account) external view returns (uint256);
+    }
+
+    interface IValueOracle {
+        function latestValue() external view returns (uint256);
+    }
+}
+
+sol! {
+    // In events carrying a `campaign_id`, zero stands for the global settings and for rewards outside campaigns.
+    event MultiplyFactorUpdated(uint256 indexed campaign_id, address indexed sender, uint256 multiply_factor, uint256 seq);
+    event PercentageBonusUpdated(uint256 indexed campaign_id, address indexed sender, uint256 percentage_bonus, uint256 seq);
+    event OwnershipTransferred(address indexed previous_owner, address indexed new_owner, uint256 seq);
+    event SponsorFunded(address indexed sponsor, uint256 amount, uint256 seq);
+    event EmergencyShutdownScheduled(address indexed sender, uint256 eta, uint256 seq);
+    event EmergencyShutdownCancelled(address indexed sender, uint256 seq);
+    event EmergencyShutdownActivated(address indexed sender, uint256 refund_deadline, uint256 seq);
+    event RefundClaimed(address indexed sponsor, uint256 amount, uint256 seq);
+    event MemberRegistered(address indexed member, uint256 index, uint256 seq);
+    event OperatorUpdated(address indexed operator, bool enabled, uint256 seq);
+    event EpochLengthUpdated(address indexed sender, uint256 epoch_length, uint256 seq);
+    event StreakBonusUpdated(address indexed sender, uint256 bonus_per_epoch, uint256 max_bonus, uint256 seq);
+    event ParticipationRecorded(address indexed user, uint256 participation_count, uint256 streak, uint256 seq);
+    event CompoundRateUpdated(address indexed sender, uint256 compound_rate, uint256 seq);
+    event RewardAccrued(uint256 indexed campaign_id, address indexed user, uint256 reward, uint256 balance, uint256 seq);
+    event BoundaryPolicyUpdated(address indexed sender, uint8 boundary_policy, uint256 seq);
+    event RoundingModeUpdated(address indexed sender, uint8 rounding_mode, uint256 seq);
+    event GuardianUpdated(address indexed previous_guardian, address indexed new_guardian, uint256 seq);
+    event ActionConfirmed(address indexed confirmer, uint8 action, uint256 seq);
+    event MaxMultiplyFactorUpdated(address indexed sender, uint256 max_multiply_factor, uint256 seq);
+    event DecayModeUpdated(address indexed sender, uint8 decay_mode, uint256 seq);
+    event StepIntervalUpdated(address indexed sender, uint256 step_interval, uint256 seq);
+    event PercentageDenominatorUpdated(uint256 indexed campaign_id, address indexed sender, uint256 percentage_denominator, uint256 seq);
+    event TokenApproved(address indexed token, uint8 decimals, string symbol, uint256 seq);
+    event TokenRevoked(address indexed token, uint256 seq);
+    event DonationBeneficiaryUpdated(address indexed beneficiary, bool approved, uint256 seq);
+    event DonationPreferenceUpdated(address indexed user, address indexed beneficiary, uint256 bps, uint256 seq);
+    event AliasedCallersUpdated(address indexed sender, bool enabled, uint256 seq);
+    event PrecisionModeUpdated(address indexed sender, bool wad_precision, uint256 seq);
+    event EarlyBirdUpdated(address indexed sender, uint256 window, uint256 bonus, uint256 seq);
+    event Paused(address indexed sender, uint256 until, uint256 seq);
+    event Unpaused(address indexed sender, uint256 seq);
+    event GracePeriodUpdated(address indexed sender, uint256 grace_period, uint256 seq);
+    event BonusPolicyUpdated(address indexed sender, uint8 bonus_policy, uint256 seq);
+    event DecayMidpointUpdated(address indexed sender, uint256 fraction, uint256 multiplier, uint256 seq);
+    event GrowthModeUpdated(address indexed sender, bool growth_mode, uint256 seq);
+    event SybilScorerUpdated(address indexed scorer, uint256 threshold, uint8 policy, uint64 gas_cap, bool fail_open, uint256 seq);
+    event CampaignFactorUpdated(uint256 indexed campaign_id, uint256 multiply_factor, uint256 seq);
+    event BonusExpiryUpdated(address indexed sender, uint256 bonus_valid_until, uint256 strict_bonus_valid_until, uint256 seq);
+    /// Packed form of `RewardAccrued`: `packed_amounts` is `reward << 128 | balance`.
+    event RewardAccruedCompact(uint256 indexed campaign_id, address indexed user, uint256 packed_amounts, uint256 seq);
+    event CompactEventsUpdated(address indexed sender, bool compact_events, uint256 seq);
+    event PenaltyUpdated(address indexed sender, uint256 penalty_bps, uint256 seq);
+    event LuckyDrawCommitted(uint256 indexed round, bytes32 commitment, uint256 bonus_factor, uint256 win_chance, uint256 seq);
+    event LuckyDrawRevealed(uint256 indexed round, bytes32 seed, uint256 seq);
+    event LuckyDrawSettled(uint256 indexed round, address indexed user, uint256 bonus, uint256 seq);
+    event SqrtBonusScalingUpdated(address indexed sender, uint256 reference_amount, uint256 seq);
+    event RewardDecimalsUpdated(address indexed sender, uint8 decimals, uint256 seq);
+    event DustThresholdUpdated(address indexed sender, uint256 threshold, bool revert_on_dust, uint256 seq);
+    event BonusCooldownUpdated(address indexed sender, uint256 cooldown, uint256 seq);
+    event RewardRecorded(uint256 indexed campaign_id, address indexed user, uint256 reward, bool bonus_applied, uint256 seq);
+    event StrictBonusDenominatorUpdated(uint256 indexed campaign_id, address indexed sender, uint256 strict_bonus_denominator, uint256 seq);
+    event MaxTotalBonusUpdated(address indexed sender, uint256 max_total_bonus_bps, uint256 seq);
+    event HalvingScheduleUpdated(address indexed sender, uint256 genesis, uint256 interval, uint256 seq);
+    event CampaignCreated(uint256 indexed campaign_id, uint256 multiply_factor, uint256 percentage_bonus, uint256 denominator, uint256 start_time, uint256 end_time, uint256 seq);
+    event CampaignActivated(uint256 indexed campaign_id, uint256 seq);
+    event CampaignCancelled(uint256 indexed campaign_id, uint256 seq);
+    event CampaignFinalized(uint256 indexed campaign_id, uint256 seq);
+    event CampaignReport(uint256 indexed campaign_id, uint256 claim_count, uint256 claimant_count, uint256 total_paid, uint256 average_multiplier, uint256 budget_utilization_bps, uint256 seq);
+    event CampaignBudgetUpdated(uint256 indexed campaign_id, uint256 budget, uint256 seq);
+    event CampaignOperatorUpdated(uint256 indexed campaign_id, address indexed operator, bool enabled, uint256 seq);
+    event CampaignBonusUpdated(uint256 indexed campaign_id, uint256 percentage_bonus, uint256 seq);
+    event CampaignMetadataUpdated(uint256 indexed campaign_id, bytes32 name, bytes32 metadata_uri_hash, uint256 seq);
+    event EpochRolled(uint256 indexed epoch_id, uint256 percentage_bonus, uint256 percentage_denominator, uint256 multiply_factor, uint256 strict_bonus_denominator, uint256 seq);
+    event RewardClaimed(uint256 indexed campaign_id, address indexed user, uint256 amount, uint256 donation, uint256 seq);
+    /// Packed form of `RewardClaimed`: `packed_amounts` is `amount << 128 | donation`.
+    event RewardClaimedCompact(uint256 indexed campaign_id, address indexed user, uint256 packed_amounts, uint256 seq);
+    event DonationRouted(uint256 indexed campaign_id, address indexed user, address indexed beneficiary, uint256 amount, uint256 seq);
+    event CampaignVestingUpdated(uint256 indexed campaign_id, uint256 cliff, uint256 duration, uint256 seq);
+    event VestingGranted(uint256 indexed campaign_id, address indexed user, uint256 amount, uint256 seq);
+    event CampaignClaimWindowUpdated(uint256 indexed campaign_id, uint256 claim_start, uint256 claim_deadline, uint256 seq);
+    event ClaimDeadlineExtended(uint256 indexed campaign_id, uint256 claim_deadline, uint256 seq);
+    event RewardsExpired(uint256 indexed campaign_id, uint256 amount, uint256 seq);
+    event CampaignCloned(uint256 indexed source_id, uint256 indexed campaign_id, uint256 seq);
+    event CampaignPauseUpdated(uint256 indexed campaign_id, bool paused, uint256 seq);
+    event CampaignFunded(uint256 indexed campaign_id, address indexed sponsor, address token, uint256 amount, uint256 seq);
+    event CampaignRewardClaimed(uint256 indexed campaign_id, address indexed user, address token, uint256 amount, uint256 seq);
+    event CampaignExtended(uint256 indexed campaign_id, uint256 old_end_time, uint256 new_end_time, uint256 seq);
+    event CampaignRestrictionUpdated(uint256 indexed campaign_id, bool restricted, uint256 seq);
+    event CampaignParticipantsUpdated(uint256 indexed campaign_id, uint256 count, bool allowed, uint256 seq);
+    event CampaignArchived(uint256 indexed campaign_id, bytes32 params_hash, uint256 spent, uint256 claimed, uint256 expired, uint256 seq);
+    event OperatorCampaignLimitUpdated(address indexed sender, uint256 max_open_campaigns, uint256 seq);
+    event CampaignTemplateCreated(uint256 indexed template_id, bytes32 name, uint256 multiply_factor, uint256 percentage_bonus, uint256 denominator, uint256 budget, uint256 max_duration, uint256 seq);
+    event CampaignCreatedFromTemplate(uint256 indexed template_id, uint256 indexed campaign_id, uint256 seq);
+    event CampaignMigrated(uint256 indexed campaign_id, uint8 from_version, uint8 to_version, uint256 seq);
+    event CampaignReconciled(uint256 indexed from_id, uint256 indexed to_id, uint256 budget, uint256 pool, uint256 seq);
+    event CampaignBonusOracleUpdated(uint256 indexed campaign_id, address indexed oracle, uint256 scale, uint256 min_bonus, uint256 max_bonus, uint256 seq);
+    event ReferralRegistered(address indexed referee, address indexed referrer, uint256 seq);
+    event CampaignReferralRateUpdated(uint256 indexed campaign_id, uint256 referral_bps, uint256 seq);
+    event ReferralCredited(uint256 indexed campaign_id, address indexed referrer, address indexed referee, uint256 amount, uint256 seq);
+    event SurplusWithdrawn(address indexed recipient, uint256 amount, uint256 seq);
+    event PayoutTokenUpdated(address indexed sender, address indexed token, uint256 seq);
+    event CampaignMerkleRootUpdated(uint256 indexed campaign_id, bytes32 merkle_root, uint256 seq);
+    event MerkleClaimed(uint256 indexed campaign_id, uint256 indexed index, address indexed user, uint256 amount, uint256 reward, uint256 seq);
+    event ClaimSignerUpdated(address indexed sender, address indexed signer, uint256 seq);
+    event ClaimerUpdated(address indexed account, address indexed claimer, bool approved, uint256 seq);
+    event BatchRewardClaimed(address indexed user, address indexed token, uint256 amount, uint256 campaign_count, uint256 seq);
+    event ClaimReceipt(address indexed user, uint256 indexed campaign_id, uint256 base, uint256 bonus, uint256 strict_bonus, uint256 total, uint256 seq);
+    event TreasuryDeposited(address indexed depositor, address indexed token, uint256 amount, uint256 seq);
+    event TreasuryWithdrawn(address indexed recipient, uint256 amount, uint256 seq);
+    event ClaimFeeUpdated(address indexed sender, uint256 fee_bps, address recipient, uint256 seq);
+    event FeeCollected(uint256 indexed campaign_id, address indexed user, address indexed token, address recipient, uint256 amount, uint256 seq);
+    event AssetsRescued(address indexed token, address indexed recipient, uint256 amount, uint256 seq);
+    event CampaignPayoutLegsUpdated(uint256 indexed campaign_id, address[] tokens, uint256[] weights, uint256 seq);
+    event WethUpdated(address indexed sender, address weth, uint256 seq);
+    event WethPreferenceUpdated(address indexed user, bool enabled, uint256 seq);
+    event WithdrawalDelayUpdated(address indexed sender, uint256 delay, uint256 seq);
+    event WithdrawalQueued(uint256 indexed withdrawal_id, uint256 indexed campaign_id, address indexed user, uint256 amount, uint256 ready_at, uint256 seq);
+    event WithdrawalFrozen(uint256 indexed withdrawal_id, bool frozen, uint256 seq);
+    event WithdrawalExecuted(uint256 indexed withdrawal_id, address indexed user, uint256 amount, uint256 donation, uint256 seq);
+    event BudgetRolledOver(uint256 indexed from_id, uint256 indexed to_id, uint256 budget, uint256 pool, uint256 seq);
+    event FeeTiersUpdated(address indexed sender, uint256[] thresholds, uint256[] fee_bps, uint256 seq);
+    event IntegratorTierChanged(address indexed integrator, uint256 previous_tier, uint256 tier, uint256 fee_bps, uint256 volume, uint256 seq);
+    event IntegratorUpdated(address indexed integrator, bool enabled, uint256 seq);
+    event CampaignRefundClaimed(uint256 indexed campaign_id, address indexed sponsor, address token, uint256 amount, uint256 seq);
+}
+
+sol! {
+    /// Every component of a reward, as returned by `calculate_reward_detailed`.
+    #[derive(Debug, AbiType)]
+    struct RewardBreakdown {
+        uint256 base;
+        uint256 decayed;
+        uint256 time_multiplier;
+        uint256 bonus;
+        uint256 strict_bonus;
+        uint256 penalty;
+        uint256 total;
+    }
+
+    /// Headline figures of one campaign, as returned by `get_campaign_stats`.
+    #[derive(Debug, AbiType)]
+    struct CampaignStats {
+        uint256 participants;
+        uint256 total_accrued;
+        uint256 total_claimed;
+        uint256 remaining_budget;
+        uint8 state;
+    }
+}
+
+sol! {
+    /// EIP-712 message the claim signer signs for `claim_with_signature`; bit 0 of `flags` is
+    /// `has_bonus`, bit 1 `has_strict_bonus`, and `nonce` is the account's `nonce_of` when claiming.
+    struct RewardClaim {
+        address account;
+        uint256 amount;
+        uint256 start_time;
+        uint256 end_time;
+        uint8 flags;
+        uint256 deadline;
+        uint256 nonce;
+    }
+}
+
+#[cfg(feature = "export-abi")]
+impl stylus_sdk::abi::export::internal::InnerTypes for RewardBreakdown {
+    fn inner_types() -> Vec<stylus_sdk::abi::export::internal::InnerType> {
+        alloc::vec![stylus_sdk::abi::export::internal::InnerType {
+            name: String::from("struct RewardBreakdown { uint256 base; uint256 decayed; uint256 time_multiplier; uint256 bonus; uint256 strict_bonus; uint256 penalty; uint256 total; }"),
+            id: core::any::TypeId::of::<Self>(),
+        }]
+    }
+}
+
+#[cfg(feature = "export-abi")]
+impl stylus_sdk::abi::export::internal::InnerTypes for CampaignStats {
+    fn inner_types() -> Vec<stylus_sdk::abi::export::internal::InnerType> {
+        alloc::vec![stylus_sdk::abi::export::internal::InnerType {
+            name: String::from("struct CampaignStats { uint256 participants; uint256 total_accrued; uint256 total_claimed; uint256 remaining_budget; uint8 state; }"),
+            id: core::any::TypeId::of::<Self>(),
+        }]
+    }
+}
+
+sol! {
+    #[derive(Debug)]
+    error InvalidMultiplyFactor();
+
+    #[derive(Debug)]
+    error Unauthorized();
+
+    #[derive(Debug)]
+    error ZeroValue();
+
+    #[derive(Debug)]
+    error ShutdownNotScheduled();
+
+    #[derive(Debug)]
+    error TimelockNotElapsed();
+
+    #[derive(Debug)]
+    error ContractShutDown();
+
+    #[derive(Debug)]
+    error NotShutDown();
+
+    #[derive(Debug)]
+    error RefundWindowClosed();
+
+    #[derive(Debug)]
+    error TransferFailed();
+
+    #[derive(Debug)]
+    error InvalidBoundaryPolicy();
+
+    #[derive(Debug)]
+    error InvalidRoundingMode();
+
+    #[derive(Debug)]
+    error MathOverflow();
+
+    #[derive(Debug)]
+    error ActionNotConfirmed();
+
+    #[derive(Debug)]
+    error InvalidTimeWindow();
+
+    #[derive(Debug)]
+    error InvalidDecayMode();
+
+    #[derive(Debug)]
+    error InvalidDenominator();
+
+    #[derive(Debug)]
+    error TokenNotApproved();
+
+    #[derive(Debug)]
+    error TokenMetadataUnavailable();
+
+    #[derive(Debug)]
+    error LengthMismatch();
+
+    #[derive(Debug)]
+    error InvalidDonation();
+
+    #[derive(Debug)]
+    error ContractPaused();
+
+    #[derive(Debug)]
+    error PauseNotExpired();
+
+    #[derive(Debug)]
+    error InvalidBonusPolicy();
+
+    #[derive(Debug)]
+    error InvalidDecayMidpoint();
+
+    #[derive(Debug)]
+    error InvalidSybilPolicy();
+
+    #[derive(Debug)]
+    error SybilScoreTooLow();
+
+    #[derive(Debug)]
+    error ScorerUnavailable();
+
+    #[derive(Debug)]
+    error DrawInProgress();
+
+    #[derive(Debug)]
+    error DrawNotOpen();
+
+    #[derive(Debug)]
+    error DrawNotRevealed();
+
+    #[derive(Debug)]
+    error InvalidReveal();
+
+    #[derive(Debug)]
+    error NotDrawWinner();
+
+    #[derive(Debug)]
+    error DrawAlreadySettled();
+
+    #[derive(Debug)]
+    error InvalidDecimals();
+
+    #[derive(Debug)]
+    error InvalidTimestamp();
+
+    #[derive(Debug)]
+    error InvalidPointCount();
+
+    #[derive(Debug)]
+    error CampaignNotFound();
+
+    #[derive(Debug)]
+    error InvalidCampaignState();
+
+    #[derive(Debug)]
+    error CampaignNotEnded();
+
+    #[derive(Debug)]
+    error BudgetExceeded();
+
+    #[derive(Debug)]
+    error EpochNotFound();
+
+    #[derive(Debug)]
+    error ClaimWindowClosed();
+
+    #[derive(Debug)]
+    error DeadlineAlreadyExtended();
+
+    #[derive(Debug)]
+    error ClaimWindowOpen();
+
+    #[derive(Debug)]
+    error CampaignPaused();
+
+    #[derive(Debug)]
+    error TokenMismatch();
+
+    #[derive(Debug)]
+    error BudgetNotFunded();
+
+    #[derive(Debug)]
+    error ExtensionLimitReached();
+
+    #[derive(Debug)]
+    error NotEligible();
+
+    #[derive(Debug)]
+    error OutstandingRewards();
+
+    #[derive(Debug)]
+    error CampaignLimitReached();
+
+    #[derive(Debug)]
+    error TemplateNotFound();
+
+    #[derive(Debug)]
+    error OracleUnavailable();
+
+    #[derive(Debug)]
+    error InvalidReferral();
+
+    #[derive(Debug)]
+    error InvalidProof();
+
+    #[derive(Debug)]
+    error AlreadyClaimed();
+
+    #[derive(Debug)]
+    error InvalidSignature();
+
+    #[derive(Debug)]
+    error SignatureExpired();
+
+    #[derive(Debug)]
+    error AmountExceedsBalance();
+
+    #[derive(Debug)]
+    error ReentrantCall();
+
+    #[derive(Debug)]
+    error FeeTooHigh();
+
+    #[derive(Debug)]
+    error InvalidPayoutLegs();
+
+    #[derive(Debug)]
+    error WithdrawalNotFound();
+
+    #[derive(Debug)]
+    error WithdrawalNotReady();
+
+    #[derive(Debug)]
+    error WithdrawalLocked();
+
+    #[derive(Debug)]
+    error InvalidFeeTiers();
+
+    #[derive(Debug)]
+    error OutsideCampaignWindow();
+}
+
+sol_storage! {
+    #[entrypoint]
+    pub struct RewardProcessor {
+        uint256 multiply_factor;
+        address owner;
+        uint256 percentage_denominator;
+        uint256 percentage_bonus;
+        uint256 shutdown_eta;
+        uint256 shutdown_at;
+        uint256 refund_deadline;
+        mapping(address => uint256) sponsor_deposits;
+        uint256 total_sponsor_deposits;
+        mapping(address => uint256) member_indices;
+        uint256 member_count;
+        mapping(address => bool) operators;
+        uint256 epoch_length;
+        uint256 streak_bonus_per_epoch;
+        uint256 max_streak_bonus;
+        mapping(address => uint256) participation_counts;
+        mapping(address => uint256) participation_streaks;
+        mapping(address => uint256) last_participation_epochs;
+        uint256 compound_rate;
+        mapping(address => UserLedger) ledgers;
+        uint8 boundary_policy;
+        uint8 rounding_mode;
+        address guardian;
+        mapping(uint256 => uint256) owner_confirmations;
+        mapping(uint256 => uint256) guardian_confirmations;
+        uint256 max_multiply_factor;
+        uint8 decay_mode;
+        uint256 step_interval;
+        mapping(address => RewardToken) reward_tokens;
+        uint256 event_seq;
+        mapping(address => bool) donation_beneficiaries;
+        mapping(address => address) donation_targets;
+        mapping(address => uint256) donation_bps;
+        mapping(address => uint256) donated_totals;
+        bool accept_aliased_callers;
+        bool wad_precision;
+        uint256 early_bird_window;
+        uint256 early_bird_bonus;
+        uint256 paused_until;
+        uint256 grace_period;
+        uint8 bonus_policy;
+        uint256 decay_midpoint_fraction;
+        uint256 decay_midpoint_multiplier;
+        bool growth_mode;
+        address sybil_scorer;
+        uint256 sybil_threshold;
+        uint8 sybil_policy;
+        uint64 sybil_gas_cap;
+        bool sybil_fail_open;
+        uint256 campaign_count;
+        mapping(uint256 => Campaign) campaigns;
+        uint256 bonus_valid_until;
+        uint256 strict_bonus_valid_until;
+        bool compact_events;
+        uint256 penalty_bps;
+        uint256 draw_round;
+        mapping(uint256 => LuckyDraw) lucky_draws;
+        uint256 sqrt_bonus_reference;
+        uint8 reward_decimals;
+        uint256 dust_threshold;
+        bool dust_reverts;
+        uint256 bonus_cooldown;
+        mapping(address => uint256) last_bonus_at;
+        uint256 strict_bonus_denominator;
+        uint256 max_total_bonus_bps;
+        uint256 halving_genesis;
+        uint256 halving_interval;
+        uint256 epoch_checkpoint_count;
+        mapping(uint256 => BonusSnapshot) epoch_checkpoints;
+        uint256 param_history_count;
+        mapping(uint256 => BonusSnapshot) param_history;
+        uint256 total_calculated;
+        uint256 total_claimed;
+        mapping(address => uint256[]) vesting_campaigns;
+        uint256 max_operator_campaigns;
+        mapping(address => uint256) open_campaigns;
+        uint256 template_count;
+        mapping(uint256 => CampaignTemplate) templates;
+        mapping(address => address) referrers;
+        mapping(address => uint256) referral_earnings;
+        address payout_token;
+        address claim_signer;
+        mapping(address => uint256) nonces;
+        mapping(address => mapping(address => bool)) claimers;
+        bool entered;
+        uint256 treasury;
+        mapping(address => uint256) treasury_tokens;
+        uint256 claim_fee_bps;
+        address fee_recipient;
+        address weth;
+        mapping(address => bool) wants_weth;
+        uint256 withdrawal_delay;
+        uint256 withdrawal_count;
+        mapping(uint256 => QueuedWithdrawal) withdrawals;
+        uint256 queued_total;
+        uint256[] fee_tier_thresholds;
+        uint256[] fee_tier_bps;
+        mapping(address => uint256) integrator_volume;
+        mapping(address => uint256) pooled_tokens;
+        mapping(address => bool) integrators;
+    }
+
+    pub struct UserLedger {
+        uint256 accrued;
+        uint256 claimed;
+        uint256 last_update;
+        uint256 epoch;
+        uint256 bonus;
+        uint256 strict_bonus;
+    }
+
+    pub struct BonusSnapshot {
+        uint256 percentage_bonus;
+        uint256 percentage_denominator;
+        uint256 multiply_factor;
+        uint256 strict_bonus_denominator;
+        uint256 taken_at;
+    }
+
+    pub struct RewardToken {
+        bool approved;
+        uint8 decimals;
+        string symbol;
+    }
+
+    pub struct Campaign {
+        bool exists;
+        uint256 multiply_factor;
+        uint256 percentage_bonus;
+        uint256 denominator;
+        uint256 start_time;
+        uint256 end_time;
+        uint8 state;
+        uint256 budget;
+        uint256 spent;
+        Bitmap operators;
+        bytes32 name;
+        bytes32 metadata_uri_hash;
+        uint256 claimed;
+        uint256 vesting_cliff;
+        uint256 vesting_duration;
+        mapping(address => uint256) vesting_granted;
+        mapping(address => uint256) vesting_released;
+        uint256 claim_start;
+        uint256 claim_deadline;
+        bool deadline_extended;
+        bool swept;
+        uint256 expired;
+        bool paused;
+        address reward_token;
+        uint256 pool;
+        uint8 extensions;
+        bool restricted;
+        Bitmap participants;
+        bool archived;
+        bytes32 params_hash;
+        uint256 participant_count;
+        Bitmap participated;
+        address creator;
+        uint8 version;
+        address bonus_oracle;
+        uint256 oracle_scale;
+        uint256 oracle_min_bonus;
+        uint256 oracle_max_bonus;
+        uint256 referral_bps;
+        bytes32 merkle_root;
+        Bitmap merkle_claimed;
+        mapping(address => uint256) granted_bonus;
+        mapping(address => uint256) granted_strict_bonus;
+        address[] leg_tokens;
+        uint256[] leg_weights;
+        uint256 claim_count;
+        uint256 claimant_count;
+        Bitmap claimed_by;
+        uint256 multiplier_sum;
+        uint256 priced_count;
+        mapping(address => uint256) sponsored;
+        uint256 sponsored_total;
+        uint256 credited;
+    }
+
+    pub struct QueuedWithdrawal {
+        address user;
+        uint256 campaign_id;
+        uint256 amount;
+        uint256 ready_at;
+        bool frozen;
+        bool executed;
+    }
+
+    pub struct CampaignTemplate {
+        bool exists;
+        bytes32 name;
+        uint256 multiply_factor;
+        uint256 percentage_bonus;
+        uint256 denominator;
+        uint256 budget;
+        uint256 max_duration;
+    }
+
+    pub struct LuckyDraw {
+        bytes32 commitment;
+        bytes32 entropy;
+        bytes32 seed;
+        bool revealed;
+        uint256 bonus_factor;
+        uint256 win_chance;
+        mapping(address => uint256) rewards;
+        mapping(address => bool) settled;
+    }
+}
+
+/// Bonus settings a reward is priced with: the global ones or a campaign's.
+#[derive(Clone, Copy)]
+struct BonusParams {
+    /// Percentage bonus, in `bonus_denominator` units.
+    percentage_bonus: U256,
+    bonus_denominator: U256,
+    /// Factor applied for the strict bonus, in `strict_bonus_denominator` units.
+    multiply_factor: U256,
+    strict_bonus_denominator: U256,
+}
+
+/// Bonus components of an issued or paid reward; the rest of it is base.
+#[derive(Clone, Copy, Default)]
+struct BonusShares {
+    bonus: U256,
+    strict_bonus: U256,
+}
+
+impl BonusShares {
+    fn of(breakdown: &RewardBreakdown) -> Self {
+        Self { bonus: breakdown.bonus, strict_bonus: breakdown.strict_bonus }
+    }
+
+    fn checked_add(self, other: Self) -> Result<Self, CommonError> {
+        Ok(Self { bonus: checked_add(self.bonus, other.bonus)?, strict_bonus: checked_add(self.strict_bonus, other.strict_bonus)? })
+    }
+}
+
+/// Inputs of a single reward computation.
+#[derive(Clone, Copy)]
+struct RewardRequest {
+    amount: U256,
+    current_time: U256,
+    start_time: U256,
+    end_time: U256,
+    has_bonus: bool,
+    has_strict_bonus: bool,
+    /// Additional bonus on top of the configured ones, in `percentage_denominator` units.
+    extra_bonus: U256,
+    /// Global or campaign bonus settings.
+    bonus_params: BonusParams,
+    /// Subtracts `penalty_bps` of the amount from the reward.
+    has_penalty: bool,
+}
+
+/// `value * numerator / denominator` at full precision, rounded down.
+fn scale(value: U256, numerator: U256, denominator: U256) -> Result<U256, CommonError> {
+    mul_div(value, numerator, denominator).ok_or(CommonError::MathOverflow(MathOverflow {}))
+}
+
+/// `value * numerator / denominator` at full precision with explicit rounding.
+fn scale_rounded(value: U256, numerator: U256, denominator: U256, rounding: Rounding) -> Result<U256, CommonError> {
+    mul_div_rounding(value, numerator, denominator, rounding).ok_or(CommonError::MathOverflow(MathOverflow {}))
+}
+
+fn checked_add(a: U256, b: U256) -> Result<U256, CommonError> {
+    a.checked_add(b).ok_or(CommonError::MathOverflow(MathOverflow {}))
+}
+
+fn checked_sub(a: U256, b: U256) -> Result<U256, CommonError> {
+    a.checked_sub(b).ok_or(CommonError::MathOverflow(MathOverflow {}))
+}
+
+/// Packs `high` and `low` into one word as `high << 128 | low`, if both fit in 128 bits.
+fn pack_u128_pair(high: U256, low: U256) -> Option<U256> {
+    let limit = U256::from(1) << 128;
+    if high >= limit || low >= limit {
+        return None;
+    }
+    Some(high << 128 | low)
+}
+
+/// Widens a `u64` timestamp, rejecting values past `MAX_TIMESTAMP`.
+fn checked_timestamp(timestamp: u64) -> Result<U256, CommonError> {
+    if timestamp > MAX_TIMESTAMP {
+        return Err(CommonError::InvalidTimestamp(InvalidTimestamp {}));
+    }
+    Ok(U256::from(timestamp))
+}
+
+fn decimals_factor(decimals: u8) -> U256 {
+    U256::from(10).pow(U256::from(decimals))
+}
+
+fn checked_mul(a: U256, b: U256) -> Result<U256, CommonError> {
+    a.checked_mul(b).ok_or(CommonError::MathOverflow(MathOverflow {}))
+}
+
+/// `amount` of a token with `decimals` as a decimal string, truncated to four fractional digits.
+fn format_units(amount: U256, decimals: u8) -> String {
+    let (whole, fraction) = (amount / decimals_factor(decimals), amount % decimals_factor(decimals));
+    let digits = decimals.min(4);
+    let fraction = format!("{:0>width$}", fraction / decimals_factor(decimals - digits), width = digits as usize);
+    match fraction.trim_end_matches('0') {
+        "" => format!("{whole}"),
+        fraction => format!("{whole}.{fraction}"),
+    }
+}
+
+/// How the percentage bonus and the strict bonus combine when both flags are set.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BonusPolicy {
+    /// Both bonuses are paid.
+    Additive = 0,
+    /// The strict bonus replaces the percentage bonus.
+    StrictOverridesPercentage = 1,
+}
+
+impl BonusPolicy {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Additive),
+            1 => Some(Self::StrictOverridesPercentage),
+            _ => None,
+        }
+    }
+
+    /// Whether the percentage bonus applies given both request flags.
+    pub fn pays_percentage_bonus(self, has_bonus: bool, has_strict_bonus: bool) -> bool {
+        match self {
+            Self::Additive => has_bonus,
+            Self::StrictOverridesPercentage => has_bonus && !has_strict_bonus,
+        }
+    }
+}
+
+/// What `accrue` does for users scoring below `sybil_threshold`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SybilPolicy {
+    /// The accrual reverts.
+    Reject = 0,
+    /// The accrual goes through without the percentage, strict and streak bonuses.
+    StripBonuses = 1,
+}
+
+impl SybilPolicy {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Reject),
+            1 => Some(Self::StripBonuses),
+            _ => None,
+        }
+    }
+}
+
+/// Where the current block lies relative to a reward window, as reported by `window_status`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WindowPhase {
+    PreStart = 0,
+    Active = 1,
+    Ended = 2,
+}
+
+/// Lifecycle of a campaign; only `Active` campaigns price rewards.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CampaignState {
+    Draft = 0,
+    Active = 1,
+    Ended = 2,
+    Cancelled = 3,
+}
+
+impl CampaignState {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Draft),
+            1 => Some(Self::Active),
+            2 => Some(Self::Ended),
+            3 => Some(Self::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+#[derive(SolidityError, Debug)]
+pub enum ConstructorError {
+    InvalidMultiplyFactor(InvalidMultiplyFactor),
+}
+
+#[derive(SolidityError, Debug)]
+pub enum CommonError {
+    Unauthorized(Unauthorized),
+    ZeroValue(ZeroValue),
+    InvalidMultiplyFactor(InvalidMultiplyFactor),
+    ShutdownNotScheduled(ShutdownNotScheduled),
+    TimelockNotElapsed(TimelockNotElapsed),
+    ContractShutDown(ContractShutDown),
+    NotShutDown(NotShutDown),
+    RefundWindowClosed(RefundWindowClosed),
+    TransferFailed(TransferFailed),
+    InvalidBoundaryPolicy(InvalidBoundaryPolicy),
+    InvalidRoundingMode(InvalidRoundingMode),
+    MathOverflow(MathOverflow),
+    ActionNotConfirmed(ActionNotConfirmed),
+    InvalidTimeWindow(InvalidTimeWindow),
+    InvalidDecayMode(InvalidDecayMode),
+    InvalidDenominator(InvalidDenominator),
+    TokenNotApproved(TokenNotApproved),
+    TokenMetadataUnavailable(TokenMetadataUnavailable),
+    LengthMismatch(LengthMismatch),
+    InvalidDonation(InvalidDonation),
+    ContractPaused(ContractPaused),
+    PauseNotExpired(PauseNotExpired),
+    InvalidBonusPolicy(InvalidBonusPolicy),
+    InvalidDecayMidpoint(InvalidDecayMidpoint),
+    InvalidSybilPolicy(InvalidSybilPolicy),
+    SybilScoreTooLow(SybilScoreTooLow),
+    ScorerUnavailable(ScorerUnavailable),
+    DrawInProgress(DrawInProgress),
+    DrawNotOpen(DrawNotOpen),
+    DrawNotRevealed(DrawNotRevealed),
+    InvalidReveal(InvalidReveal),
+    NotDrawWinner(NotDrawWinner),
+    DrawAlreadySettled(DrawAlreadySettled),
+    InvalidDecimals(InvalidDecimals),
+    InvalidTimestamp(InvalidTimestamp),
+    InvalidPointCount(InvalidPointCount),
+    CampaignNotFound(CampaignNotFound),
+    InvalidCampaignState(InvalidCampaignState),
+    CampaignNotEnded(CampaignNotEnded),
+    BudgetExceeded(BudgetExceeded),
+    EpochNotFound(EpochNotFound),
+    ClaimWindowClosed(ClaimWindowClosed),
+    DeadlineAlreadyExtended(DeadlineAlreadyExtended),
+    ClaimWindowOpen(ClaimWindowOpen),
+    CampaignPaused(CampaignPaused),
+    TokenMismatch(TokenMismatch),
+    BudgetNotFunded(BudgetNotFunded),
+    ExtensionLimitReached(ExtensionLimitReached),
+    NotEligible(NotEligible),
+    OutstandingRewards(OutstandingRewards),
+    CampaignLimitReached(CampaignLimitReached),
+    TemplateNotFound(TemplateNotFound),
+    OracleUnavailable(OracleUnavailable),
+    InvalidReferral(InvalidReferral),
+    InvalidProof(InvalidProof),
+    AlreadyClaimed(AlreadyClaimed),
+    InvalidSignature(InvalidSignature),
+    SignatureExpired(SignatureExpired),
+    AmountExceedsBalance(AmountExceedsBalance),
+    ReentrantCall(ReentrantCall),
+    FeeTooHigh(FeeTooHigh),
+    InvalidPayoutLegs(InvalidPayoutLegs),
+    WithdrawalNotFound(WithdrawalNotFound),
+    WithdrawalNotReady(WithdrawalNotReady),
+    WithdrawalLocked(WithdrawalLocked),
+    InvalidFeeTiers(InvalidFeeTiers),
+    OutsideCampaignWindow(OutsideCampaignWindow),
+}
+
+#[public]
+impl RewardProcessor {
+    #[constructor]
+    #[payable]
+    pub fn constructor(
+        &mut self,
+        multiply_factor_: U256,
+    ) -> Result<(), ConstructorError> {
+        if multiply_factor_ == U256::ZERO || multiply_factor_ > U256::from(DEFAULT_MAX_MULTIPLY_FACTOR) {
+            return Err(ConstructorError::InvalidMultiplyFactor(InvalidMultiplyFactor {}));
+        }
+
+        self.multiply_factor.set(multiply_factor_);
+        self.max_multiply_factor.set(U256::from(DEFAULT_MAX_MULTIPLY_FACTOR));
+        self.owner.set(self.vm().tx_origin());
+        self.percentage_denominator.set(U256::from(10000));
+        self.strict_bonus_denominator.set(U256::from(10000));
+        self.percentage_bonus.set(U256::from(1000));
+        self.epoch_length.set(U256::from(DEFAULT_EPOCH_LENGTH));
+        self.step_interval.set(U256::from(DEFAULT_STEP_INTERVAL));
+        self.reward_decimals.set(U8::from(INTERNAL_DECIMALS));
+        self.record_bonus_params();
+
+        let value = self.vm().msg_value();
+        if value > U256::ZERO {
+            self.record_sponsor_deposit(self.vm().tx_origin(), value);
+        }
+
+        Ok(())
+    }
+
+    pub fn calculate_reward(&self, amount: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        let current_time = U256::from(self.vm().block_timestamp());
+        self.calculate_reward_at_time(amount, current_time, start_time, end_time, has_bonus, has_strict_bonus)
+    }
+
+    /// Prices the bonuses with the settings that were in effect at `current_time`, so later changes
+    /// do not rewrite past rewards.
+    pub fn calculate_reward_at_time(&self, amount: U256, current_time: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.quote_reward(RewardRequest {
+            amount,
+            current_time,
+            start_time,
+            end_time,
+            has_bonus,
+            has_strict_bonus,
+            extra_bonus: U256::ZERO,
+            bonus_params: self.bonus_params_at(current_time),
+            has_penalty: false,
+        })
+    }
+
+    /// `calculate_reward` taking timestamps in seconds as `u64`, like `block_timestamp()`.
+    pub fn calculate_reward_u64(&self, amount: U256, start_time: u64, end_time: u64, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.calculate_reward_at_time_u64(amount, self.vm().block_timestamp(), start_time, end_time, has_bonus, has_strict_bonus)
+    }
+
+    /// `calculate_reward_at_time` taking `u64` timestamps; any of them past `MAX_TIMESTAMP` is rejected.
+    pub fn calculate_reward_at_time_u64(&self, amount: U256, current_time: u64, start_time: u64, end_time: u64, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.calculate_reward_at_time(amount, checked_timestamp(current_time)?, checked_timestamp(start_time)?, checked_timestamp(end_time)?, has_bonus, has_strict_bonus)
+    }
+
+    /// Rewards for `amount` without bonuses at `n_points` evenly spaced timestamps from
+    /// `start_time` to `end_time` inclusive, for charting the decay curve.
+    pub fn sample_reward_curve(&self, amount: U256, start_time: U256, end_time: U256, n_points: U256) -> Result<Vec<U256>, CommonError> {
+        if n_points == U256::ZERO || n_points > U256::from(MAX_CURVE_POINTS) {
+            return Err(CommonError::InvalidPointCount(InvalidPointCount {}));
+        }
+        if end_time <= start_time {
+            return Err(CommonError::InvalidTimeWindow(InvalidTimeWindow {}));
+        }
+
+        let n_points = n_points.to::<u64>();
+        let duration = end_time - start_time;
+        let intervals = U256::from(n_points.saturating_sub(1).max(1));
+        let mut rewards = Vec::with_capacity(n_points as usize);
+        for i in 0..n_points {
+            let current_time = start_time + scale(duration, U256::from(i), intervals)?;
+            rewards.push(self.calculate_reward_at_time(amount, current_time, start_time, end_time, false, false)?);
+        }
+
+        Ok(rewards)
+    }
+
+    /// Same as `calculate_reward_at_time`, split into the pieces that make up the total.
+    ///
+    /// `total` is `decayed + bonus + strict_bonus - penalty` floored at zero, doubled when the lucky draw hits.
+    /// With `reward_decimals` below 18 each piece is rounded down separately, so they can sum to less than `total`.
+    pub fn calculate_reward_detailed(&self, amount: U256, current_time: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<RewardBreakdown, CommonError> {
+        self.quote_breakdown(RewardRequest {
+            amount,
+            current_time,
+            start_time,
+            end_time,
+            has_bonus,
+            has_strict_bonus,
+            extra_bonus: U256::ZERO,
+            bonus_params: self.bonus_params_at(current_time),
+            has_penalty: false,
+        })
+    }
+
+    /// Reward for `total_amount` deposited at a constant rate over the whole window.
+    ///
+    /// Uses the average multiplier across the window, i.e. the integral of the
+    /// multiplier over `[start_time, end_time]` divided by its length.
+    pub fn calculate_reward_streamed(&self, total_amount: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        if end_time <= start_time {
+            return Err(CommonError::InvalidTimeWindow(InvalidTimeWindow {}));
+        }
+
+        let total_amount = self.to_internal(total_amount)?;
+        let (numerator, denominator) = self.streamed_multiplier(checked_sub(end_time, start_time)?)?;
+        let percentage_denominator = self.percentage_denominator.get();
+        let time_multiplier = numerator / denominator;
+        let decayed = scale_rounded(total_amount, numerator, checked_mul(denominator, percentage_denominator)?, self.active_rounding())?;
+
+        let request = RewardRequest {
+            amount: total_amount,
+            current_time: start_time,
+            start_time,
+            end_time,
+            has_bonus,
+            has_strict_bonus,
+            extra_bonus: U256::ZERO,
+            bonus_params: self.global_bonus_params(),
+            has_penalty: false,
+        };
+        self.lucky_draw(self.finish_breakdown(request, time_multiplier, decayed)?.total)
+    }
+
+    /// Reward rate of a position opened now in the given window, annualized without compounding.
+    ///
+    /// In `percentage_denominator` units; the lucky draw is not included.
+    pub fn effective_apr(&self, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        let window_rate = self.window_rate(start_time, end_time, has_bonus, has_strict_bonus)?;
+        scale(window_rate, U256::from(SECONDS_PER_YEAR), end_time - start_time)
+    }
+
+    /// Like `effective_apr`, but compounding the window rate once per whole window that fits in a year.
+    ///
+    /// Windows of a year or longer do not compound and return the APR.
+    pub fn effective_apy(&self, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        let window_rate = self.window_rate(start_time, end_time, has_bonus, has_strict_bonus)?;
+        let windows_per_year = U256::from(SECONDS_PER_YEAR) / (end_time - start_time);
+        if windows_per_year <= U256::from(1) {
+            return self.effective_apr(start_time, end_time, has_bonus, has_strict_bonus);
+        }
+
+        let denominator = self.percentage_denominator.get();
+        checked_sub(self.compound(denominator, window_rate, windows_per_year)?, denominator)
+    }
+
+    /// Prices many positions at the current block timestamp; all arrays must have the same length.
+    pub fn calculate_rewards_batch(&self, amounts: Vec<U256>, start_times: Vec<U256>, end_times: Vec<U256>, has_bonus: Vec<bool>, has_strict_bonus: Vec<bool>) -> Result<Vec<U256>, CommonError> {
+        let len = amounts.len();
+        if start_times.len() != len || end_times.len() != len || has_bonus.len() != len || has_strict_bonus.len() != len {
+            return Err(CommonError::LengthMismatch(LengthMismatch {}));
+        }
+
+        let current_time = U256::from(self.vm().block_timestamp());
+        let mut rewards = Vec::with_capacity(len);
+        for i in 0..len {
+            rewards.push(self.calculate_reward_at_time(amounts[i], current_time, start_times[i], end_times[i], has_bonus[i], has_strict_bonus[i])?);
+        }
+
+        Ok(rewards)
+    }
+
+    /// Like `calculate_reward`, plus the loyalty streak bonus earned by `user`.
+    pub fn calculate_reward_for_user(&self, user: Address, amount: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.quote_reward(RewardRequest {
+            amount,
+            current_time: U256::from(self.vm().block_timestamp()),
+            start_time,
+            end_time,
+            has_bonus,
+            has_strict_bonus,
+            extra_bonus: self.streak_bonus_of(user),
+            bonus_params: self.global_bonus_params(),
+            has_penalty: false,
+        })
+    }
+
+    pub fn get_pseudo_random(&self) -> bool {
+        let mut data = Vec::new();
+        data.extend_from_slice(&self.vm().block_timestamp().to_be_bytes());
+        data.extend_from_slice(&self.vm().block_number().to_be_bytes());
+        data.extend_from_slice(self.vm().tx_origin().as_slice());
+        data.extend_from_slice(&self.vm().tx_gas_price().to_be_bytes::<32>());
+        data.extend_from_slice(self.vm().msg_sender().as_slice());
+        
+        let random_hash = keccak(data);
+        
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&random_hash.as_slice()[0..8]);
+        let result = u64::from_be_bytes(bytes) % 100;
+
+        result == 0
+    }
+
+    pub fn update_multiply_factor(&mut self, new_factor: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        
+        if new_factor == U256::ZERO || new_factor > self.max_multiply_factor.get() {
+            return Err(CommonError::InvalidMultiplyFactor(InvalidMultiplyFactor {}));
+        }
+        
+        self.multiply_factor.set(new_factor);
+        self.record_bonus_params();
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| MultiplyFactorUpdated {
+            campaign_id: U256::ZERO,
+            sender,
+            multiply_factor: new_factor,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn update_percentage_bonus(&mut self, new_bonus: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        
+        if new_bonus == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+        
+        self.percentage_bonus.set(new_bonus);
+        self.record_bonus_params();
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| PercentageBonusUpdated {
+            campaign_id: U256::ZERO,
+            sender,
+            percentage_bonus: new_bonus,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    /// Changes the precision of every value expressed in `percentage_denominator` units.
+    ///
+    /// Stored values are not rescaled, so the bonus settings should be updated alongside.
+    pub fn update_percentage_denominator(&mut self, new_denominator: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        if new_denominator == U256::ZERO
+            || new_denominator < self.percentage_bonus.get()
+            || new_denominator < self.max_streak_bonus.get()
+        {
+            return Err(CommonError::InvalidDenominator(InvalidDenominator {}));
+        }
+
+        self.percentage_denominator.set(new_denominator);
+        self.record_bonus_params();
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| PercentageDenominatorUpdated {
+            campaign_id: U256::ZERO,
+            sender,
+            percentage_denominator: new_denominator,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn percentage_denominator(&self) -> U256 {
+        self.percentage_denominator.get()
+    }
+
+    /// Changes the precision of `multiply_factor`, independently of `percentage_denominator`.
+    ///
+    /// The stored factor is not rescaled, so it should be updated alongside.
+    pub fn update_strict_bonus_denominator(&mut self, new_denominator: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        if new_denominator == U256::ZERO {
+            return Err(CommonError::InvalidDenominator(InvalidDenominator {}));
+        }
+
+        self.strict_bonus_denominator.set(new_denominator);
+        self.record_bonus_params();
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| StrictBonusDenominatorUpdated {
+            campaign_id: U256::ZERO,
+            sender,
+            strict_bonus_denominator: new_denominator,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn strict_bonus_denominator(&self) -> U256 {
+        self.strict_bonus_denominator.get()
+    }
+
+    /// Sets the cap for `update_multiply_factor`; it may not drop below the current factor.
+    pub fn update_max_multiply_factor(&mut self, new_max: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        if new_max < self.multiply_factor.get() {
+            return Err(CommonError::InvalidMultiplyFactor(InvalidMultiplyFactor {}));
+        }
+
+        self.max_multiply_factor.set(new_max);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| MaxMultiplyFactorUpdated {
+            sender,
+            max_multiply_factor: new_max,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn max_multiply_factor(&self) -> U256 {
+        self.max_multiply_factor.get()
+    }
+
+    pub fn assert_owner(&self) -> Result<(), CommonError> {
+        let origin = self.vm().tx_origin();
+        let owner = self.owner.get();
+        if origin != owner && !(self.accept_aliased_callers.get() && undo_l1_alias(origin) == owner) {
+            return Err(CommonError::Unauthorized(Unauthorized {}));
+        }
+        Ok(())
+    }
+
+    pub fn transfer_ownership(&mut self, new_owner: Address) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.owner.set(new_owner);
+
+        let previous_owner = self.vm().tx_origin();
+        self.emit(|seq| OwnershipTransferred {
+            previous_owner,
+            new_owner,
+            seq,
+        });
+        
+        Ok(())
+    }
+
+    /// Adds the attached value to the caller's refundable sponsor deposit.
+    #[payable]
+    pub fn fund(&mut self) -> Result<(), CommonError> {
+        self.assert_not_shut_down()?;
+        self.assert_not_paused()?;
+
+        let value = self.vm().msg_value();
+        if value == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+
+        self.record_sponsor_deposit(self.vm().msg_sender(), value);
+        Ok(())
+    }
+
+    /// Starts the timelock after which `emergency_shutdown` may be executed.
+    pub fn schedule_emergency_shutdown(&mut self) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_not_shut_down()?;
+
+        let eta = U256::from(self.vm().block_timestamp() + SHUTDOWN_TIMELOCK);
+        self.shutdown_eta.set(eta);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| EmergencyShutdownScheduled {
+            sender,
+            eta,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn cancel_emergency_shutdown(&mut self) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_not_shut_down()?;
+
+        if self.shutdown_eta.get() == U256::ZERO {
+            return Err(CommonError::ShutdownNotScheduled(ShutdownNotScheduled {}));
+        }
+        self.shutdown_eta.set(U256::ZERO);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| EmergencyShutdownCancelled {
+            sender,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    /// Permanently halts funding and opens the sponsor refund window.
+    ///
+    /// Requires fresh confirmations of `ACTION_EMERGENCY_SHUTDOWN` from both the owner and the guardian,
+    /// and the owner as the direct caller, the same principal `confirm_action` records.
+    pub fn emergency_shutdown(&mut self) -> Result<(), CommonError> {
+        self.assert_owner_is_caller()?;
+        self.assert_not_shut_down()?;
+
+        let eta = self.shutdown_eta.get();
+        if eta == U256::ZERO {
+            return Err(CommonError::ShutdownNotScheduled(ShutdownNotScheduled {}));
+        }
+
+        let now = self.vm().block_timestamp();
+        if U256::from(now) < eta {
+            return Err(CommonError::TimelockNotElapsed(TimelockNotElapsed {}));
+        }
+
+        self.consume_confirmations(ACTION_EMERGENCY_SHUTDOWN)?;
+
+        let refund_deadline = U256::from(now + REFUND_WINDOW);
+        self.shutdown_at.set(U256::from(now));
+        self.refund_deadline.set(refund_deadline);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| EmergencyShutdownActivated {
+            sender,
+            refund_deadline,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the caller's share of the native balance left for sponsors, as `refund_due` describes, while
+    /// the refund window is open.
+    pub fn claim_refund(&mut self) -> Result<U256, CommonError> {
+        self.assert_refund_window_open()?;
+
+        let sponsor = self.vm().msg_sender();
+        let deposit = self.sponsor_deposits.get(sponsor);
+        if deposit == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+        let amount = self.refund_due(sponsor)?;
+
+        self.sponsor_deposits.setter(sponsor).set(U256::ZERO);
+        self.total_sponsor_deposits.set(self.total_sponsor_deposits.get() - deposit);
+
+        self.transfer_native(sponsor, amount)?;
+
+        self.emit(|seq| RefundClaimed { sponsor, amount, seq });
+
+        Ok(amount)
+    }
+
+    /// Returns the caller's share of what is left in `campaign_id`'s pool, in proportion to what they funded,
+    /// while the refund window is open; claims are halted by then, so rewards issued but unclaimed are refunded too.
+    ///
+    /// Pool moved in by merges, rollovers and splits counts as funded by the campaign's creator, or the
+    /// owner for campaigns the owner created.
+    pub fn claim_campaign_refund(&mut self, campaign_id: U256) -> Result<U256, CommonError> {
+        self.assert_refund_window_open()?;
+        self.assert_campaign_exists(campaign_id)?;
+
+        let sponsor = self.vm().msg_sender();
+        let amount = self.campaign_refundable_of(campaign_id, sponsor)?;
+        let campaign = self.campaigns.get(campaign_id);
+        let (sponsored, sponsored_total, token) = (campaign.sponsored.get(sponsor), campaign.sponsored_total.get(), campaign.reward_token.get());
+        if sponsored == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+        let pool = checked_sub(campaign.pool.get(), amount)?;
+
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.sponsored.setter(sponsor).set(U256::ZERO);
+        campaign.sponsored_total.set(sponsored_total - sponsored);
+        campaign.pool.set(pool);
+        self.release_pooled(token, amount)?;
+
+        if amount > U256::ZERO {
+            self.call_token(token, IERC20::transferCall { to: sponsor, amount }.abi_encode())?;
+        }
+
+        self.emit(|seq| CampaignRefundClaimed { campaign_id, sponsor, token, amount, seq });
+
+        Ok(amount)
+    }
+
+    /// What `claim_campaign_refund` would currently pay `sponsor` from `campaign_id`'s pool.
+    pub fn campaign_refundable_of(&self, campaign_id: U256, sponsor: Address) -> Result<U256, CommonError> {
+        let campaign = self.campaigns.get(campaign_id);
+        let sponsored_total = campaign.sponsored_total.get();
+        if sponsored_total == U256::ZERO {
+            return Ok(U256::ZERO);
+        }
+        scale(campaign.pool.get(), campaign.sponsored.get(sponsor), sponsored_total)
+    }
+
+    pub fn is_shut_down(&self) -> bool {
+        self.shutdown_at.get() != U256::ZERO
+    }
+
+    pub fn shutdown_eta(&self) -> U256 {
+        self.shutdown_eta.get()
+    }
+
+    pub fn refund_deadline(&self) -> U256 {
+        self.refund_deadline.get()
+    }
+
+    pub fn refundable_balance_of(&self, sponsor: Address) -> U256 {
+        self.sponsor_deposits.get(sponsor)
+    }
+
+    pub fn total_refundable(&self) -> U256 {
+        self.total_sponsor_deposits.get()
+    }
+
+    /// What `claim_refund` would pay `sponsor`: their deposit, scaled down pro rata when claims paid before
+    /// the shutdown left less than all deposits in the contract. The treasury and queued withdrawals are
+    /// not part of what sponsors share.
+    pub fn refund_due(&self, sponsor: Address) -> Result<U256, CommonError> {
+        let total = self.total_sponsor_deposits.get();
+        if total == U256::ZERO {
+            return Ok(U256::ZERO);
+        }
+        let available = self.contract_balance().saturating_sub(self.treasury.get()).saturating_sub(self.queued_total.get());
+        scale(self.sponsor_deposits.get(sponsor), available.min(total), total)
+    }
+
+    /// Assigns `member` the next bitmap index, or returns the one it already has.
+    pub fn register_member(&mut self, member: Address) -> Result<U256, CommonError> {
+        self.assert_owner()?;
+        Ok(self.ensure_member_index(member))
+    }
+
+    /// Returns the bitmap index of `member`, or zero when it is not registered.
+    pub fn member_index_of(&self, member: Address) -> U256 {
+        self.member_indices.get(member)
+    }
+
+    pub fn member_count(&self) -> U256 {
+        self.member_count.get()
+    }
+
+    pub fn set_operator(&mut self, operator: Address, enabled: bool) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.operators.setter(operator).set(enabled);
+
+        self.emit(|seq| OperatorUpdated { operator, enabled, seq });
+
+        Ok(())
+    }
+
+    pub fn is_operator(&self, account: Address) -> bool {
+        self.operators.get(account)
+    }
+
+    pub fn update_epoch_length(&mut self, new_length: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        if new_length == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+
+        self.epoch_length.set(new_length);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| EpochLengthUpdated {
+            sender,
+            epoch_length: new_length,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    /// Sets the bonus earned per consecutive epoch and its cap, both in `percentage_denominator` units.
+    pub fn update_streak_bonus(&mut self, bonus_per_epoch: U256, max_bonus: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.streak_bonus_per_epoch.set(bonus_per_epoch);
+        self.max_streak_bonus.set(max_bonus);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| StreakBonusUpdated {
+            sender,
+            bonus_per_epoch,
+            max_bonus,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    /// Counts a participation for `user` and extends or restarts their epoch streak.
+    pub fn record_participation(&mut self, user: Address) -> Result<(), CommonError> {
+        self.assert_operator()?;
+        self.assert_not_paused()?;
+
+        let epoch = self.current_epoch();
+        let count = self.participation_counts.get(user);
+        let last_epoch = self.last_participation_epochs.get(user);
+        let streak = self.participation_streaks.get(user);
+
+        let new_streak = if count == U256::ZERO {
+            U256::from(1)
+        } else if last_epoch == epoch {
+            streak
+        } else if last_epoch + U256::from(1) == epoch {
+            streak + U256::from(1)
+        } else {
+            U256::from(1)
+        };
+
+        let new_count = count + U256::from(1);
+        self.participation_counts.setter(user).set(new_count);
+        self.participation_streaks.setter(user).set(new_streak);
+        self.last_participation_epochs.setter(user).set(epoch);
+
+        self.emit(|seq| ParticipationRecorded {
+            user,
+            participation_count: new_count,
+            streak: new_streak,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn current_epoch(&self) -> U256 {
+        U256::from(self.vm().block_timestamp()) / self.epoch_length.get()
+    }
+
+    pub fn participation_count_of(&self, user: Address) -> U256 {
+        self.participation_counts.get(user)
+    }
+
+    /// Returns the number of consecutive epochs `user` participated in, zero once the streak is broken.
+    pub fn streak_of(&self, user: Address) -> U256 {
+        let streak = self.participation_streaks.get(user);
+        if streak == U256::ZERO {
+            return U256::ZERO;
+        }
+
+        let last_epoch = self.last_participation_epochs.get(user);
+        if last_epoch + U256::from(1) < self.current_epoch() {
+            return U256::ZERO;
+        }
+
+        streak
+    }
+
+    pub fn streak_bonus_of(&self, user: Address) -> U256 {
+        let bonus = self.streak_of(user).saturating_mul(self.streak_bonus_per_epoch.get());
+        bonus.min(self.max_streak_bonus.get())
+    }
+
+    /// Sets the per-epoch growth applied to accrued balances, in `percentage_denominator` units.
+    pub fn update_compound_rate(&mut self, new_rate: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.compound_rate.set(new_rate);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| CompoundRateUpdated {
+            sender,
+            compound_rate: new_rate,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    /// Accrue-now step of the accrue-now, claim-later flow: freezes `user`'s reward at the current
+    /// settings into their ledger, from which `claim` later pays it out. Same as `credit`.
+    pub fn accrue_reward(&mut self, user: Address, amount: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.credit(user, amount, start_time, end_time, has_bonus, has_strict_bonus)
+    }
+
+    /// Pays out the caller's whole balance in native currency from the sponsored funds, returning it.
+    ///
+    /// The share the caller chose to donate goes to their beneficiary instead.
+    pub fn claim(&mut self) -> Result<U256, CommonError> {
+        self.claim_ledger(self.vm().msg_sender(), None)
+    }
+
+    /// Pays out `amount` of the caller's balance like `claim`; the rest stays in the ledger and keeps compounding.
+    #[selector(name = "claim")]
+    pub fn claim_amount(&mut self, amount: U256) -> Result<U256, CommonError> {
+        self.claim_ledger(self.vm().msg_sender(), Some(amount))
+    }
+
+    /// Runs `claim` for `account`, paying `account`; callable by claimers `account` approved.
+    pub fn claim_for(&mut self, account: Address) -> Result<U256, CommonError> {
+        self.assert_claimer(account)?;
+        self.claim_ledger(account, None)
+    }
+
+    /// Lets `claimer` trigger `claim_for` and `claim_vested_for` on the caller's behalf; payouts still go to the caller.
+    pub fn approve_claimer(&mut self, claimer: Address) -> Result<(), CommonError> {
+        self.set_claimer(claimer, true)
+    }
+
+    pub fn revoke_claimer(&mut self, claimer: Address) -> Result<(), CommonError> {
+        self.set_claimer(claimer, false)
+    }
+
+    pub fn is_claimer(&self, account: Address, claimer: Address) -> bool {
+        self.claimers.get(account).get(claimer)
+    }
+
+    /// Sets the off-chain signer whose EIP-712 `RewardClaim`s `claim_with_signature` accepts; `Address::ZERO` disables them.
+    pub fn set_claim_signer(&mut self, signer: Address) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.claim_signer.set(signer);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| ClaimSignerUpdated { sender, signer, seq });
+
+        Ok(())
+    }
+
+    pub fn claim_signer(&self) -> Address {
+        self.claim_signer.get()
+    }
+
+    /// Nonce the next signed action of `account` must carry; each accepted one increments it.
+    pub fn nonce_of(&self, account: Address) -> U256 {
+        self.nonces.get(account)
+    }
+
+    /// EIP-712 domain separator of `RewardClaim` messages, for name `RewardProcessor` and version `1`.
+    pub fn domain_separator(&self) -> B256 {
+        self.eip712_domain().separator()
+    }
+
+    /// Prices `amount` over `start_time..end_time` with the global settings and pays the reward to the
+    /// caller at once, if the claim signer signed the matching `RewardClaim` for them.
+    ///
+    /// `signature` is the 65-byte `r || s || v`. A signed claim is bound to the caller's current nonce,
+    /// which it consumes, so it pays once and not after `deadline`.
+    pub fn claim_with_signature(&mut self, amount: U256, start_time: U256, end_time: U256, flags: u8, deadline: U256, signature: Bytes) -> Result<U256, CommonError> {
+        self.assert_not_shut_down()?;
+        self.assert_not_paused()?;
+
+        if U256::from(self.vm().block_timestamp()) > deadline {
+            return Err(CommonError::SignatureExpired(SignatureExpired {}));
+        }
+        let user = self.vm().msg_sender();
+        let nonce = self.nonces.get(user);
+        let digest = RewardClaim { account: user, amount, start_time, end_time, flags, deadline, nonce }.eip712_signing_hash(&self.eip712_domain());
+        let signer = self.claim_signer.get();
+        if signer == Address::ZERO || self.recover_signer(digest, &signature) != Some(signer) {
+            return Err(CommonError::InvalidSignature(InvalidSignature {}));
+        }
+        self.nonces.setter(user).set(nonce + U256::from(1));
+
+        let keep_bonuses = self.screen_user(user)?;
+        let breakdown = self.compute_breakdown(RewardRequest {
+            amount,
+            current_time: U256::from(self.vm().block_timestamp()),
+            start_time,
+            end_time,
+            has_bonus: flags & 1 != 0 && keep_bonuses,
+            has_strict_bonus: flags & 2 != 0 && keep_bonuses,
+            extra_bonus: if keep_bonuses { self.streak_bonus_of(user) } else { U256::ZERO },
+            bonus_params: self.global_bonus_params(),
+            has_penalty: false,
+        })?;
+        let reward = breakdown.total;
+        let total_calculated = checked_add(self.total_calculated.get(), reward)?;
+        let total_claimed = checked_add(self.total_claimed.get(), reward)?;
+        self.total_calculated.set(total_calculated);
+        self.total_claimed.set(total_claimed);
+
+        self.emit_claim_receipt(U256::ZERO, user, reward, BonusShares::of(&breakdown));
+        self.pay_out(U256::ZERO, user, reward)?;
+
+        Ok(reward)
+    }
+
+    /// Sets the ERC20 token `claim_erc20` pays ledger balances in; `Address::ZERO` disables token claims.
+    pub fn set_payout_token(&mut self, token: Address) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        if token != Address::ZERO {
+            self.assert_token_approved(token)?;
+        }
+
+        self.payout_token.set(token);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| PayoutTokenUpdated { sender, token, seq });
+
+        Ok(())
+    }
+
+    pub fn payout_token(&self) -> Address {
+        self.payout_token.get()
+    }
+
+    /// Sets the share of every claim, in basis points up to `MAX_CLAIM_FEE_BPS`, sent to `fee_recipient`.
+    pub fn set_claim_fee_bps(&mut self, fee_bps: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        if fee_bps > U256::from(MAX_CLAIM_FEE_BPS) {
+            return Err(CommonError::FeeTooHigh(FeeTooHigh {}));
+        }
+
+        self.claim_fee_bps.set(fee_bps);
+        self.emit_claim_fee_updated();
+
+        Ok(())
+    }
+
+    /// Sets the address claim fees go to; `Address::ZERO` stops fee collection.
+    pub fn set_fee_recipient(&mut self, recipient: Address) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.fee_recipient.set(recipient);
+        self.emit_claim_fee_updated();
+
+        Ok(())
+    }
+
+    pub fn claim_fee_bps(&self) -> U256 {
+        self.claim_fee_bps.get()
+    }
+
+    pub fn fee_recipient(&self) -> Address {
+        self.fee_recipient.get()
+    }
+
+    /// Replaces the integrator fee tiers: once an integrator's routed volume reaches `thresholds[i]` its claims
+    /// pay `fee_bps[i]` instead of `claim_fee_bps`, capped at the latter.
+    ///
+    /// Thresholds must be strictly increasing and fees non-increasing; empty lists remove all tiers.
+    pub fn set_fee_tiers(&mut self, thresholds: Vec<U256>, fee_bps: Vec<U256>) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        if thresholds.len() != fee_bps.len() {
+            return Err(CommonError::LengthMismatch(LengthMismatch {}));
+        }
+        if fee_bps.iter().any(|bps| *bps > U256::from(MAX_CLAIM_FEE_BPS)) {
+            return Err(CommonError::FeeTooHigh(FeeTooHigh {}));
+        }
+        if thresholds.windows(2).any(|pair| pair[0] >= pair[1]) || fee_bps.windows(2).any(|pair| pair[0] < pair[1]) {
+            return Err(CommonError::InvalidFeeTiers(InvalidFeeTiers {}));
+        }
+
+        self.fee_tier_thresholds.truncate(0);
+        self.fee_tier_bps.truncate(0);
+        for (threshold, bps) in thresholds.iter().zip(&fee_bps) {
+            self.fee_tier_thresholds.push(*threshold);
+            self.fee_tier_bps.push(*bps);
+        }
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| FeeTiersUpdated { sender, thresholds, fee_bps, seq });
+
+        Ok(())
+    }
+
+    /// Returns the `(thresholds, fee_bps)` set by `set_fee_tiers`.
+    pub fn fee_tiers(&self) -> (Vec<U256>, Vec<U256>) {
+        let thresholds = (0..self.fee_tier_thresholds.len()).filter_map(|index| self.fee_tier_thresholds.get(index)).collect();
+        let fee_bps = (0..self.fee_tier_bps.len()).filter_map(|index| self.fee_tier_bps.get(index)).collect();
+        (thresholds, fee_bps)
+    }
+
+    /// Registers or removes `integrator`; only registered integrators accrue routed volume and fee tiers.
+    pub fn set_integrator(&mut self, integrator: Address, enabled: bool) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.integrators.setter(integrator).set(enabled);
+
+        self.emit(|seq| IntegratorUpdated { integrator, enabled, seq });
+
+        Ok(())
+    }
+
+    pub fn is_integrator(&self, account: Address) -> bool {
+        self.integrators.get(account)
+    }
+
+    /// Claim volume `integrator` has routed, in whole-token units scaled to `INTERNAL_DECIMALS` across all tokens.
+    pub fn integrator_volume(&self, integrator: Address) -> U256 {
+        self.integrator_volume.get(integrator)
+    }
+
+    /// Returns the `(tier, fee_bps)` that applies to claims `integrator` routes; tier 0 pays `claim_fee_bps`.
+    pub fn integrator_tier(&self, integrator: Address) -> (U256, U256) {
+        let tier = self.fee_tier_for(self.integrator_volume.get(integrator));
+        (U256::from(tier), self.tier_fee_bps(tier))
+    }
+
+    /// Volume at which `integrator` reaches its next tier, or zero once it is in the top tier.
+    pub fn integrator_next_threshold(&self, integrator: Address) -> U256 {
+        let tier = self.fee_tier_for(self.integrator_volume.get(integrator));
+        self.fee_tier_thresholds.get(tier).unwrap_or_default()
+    }
+
+    /// Like `claim`, paid in `payout_token` from the contract's token balance instead of native currency.
+    ///
+    /// Donation preferences do not apply. Reverts with `TokenNotApproved` while no approved payout token
+    /// is set and with `TransferFailed` if the token transfer fails.
+    pub fn claim_erc20(&mut self) -> Result<U256, CommonError> {
+        self.assert_not_shut_down()?;
+        self.assert_not_paused()?;
+
+        let token = self.payout_token.get();
+        self.assert_token_approved(token)?;
+        let user = self.vm().msg_sender();
+        let amount = self.compounded_balance_of(user)?;
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+
+        let shares = self.settle_ledger_claim(user, amount)?;
+        self.send_token(U256::ZERO, token, user, amount)?;
+
+        self.emit_claim_receipt(U256::ZERO, user, amount, shares);
+        self.emit(|seq| CampaignRewardClaimed { campaign_id: U256::ZERO, user, token, amount, seq });
+
+        Ok(amount)
+    }
+
+    /// Turns on the withdrawal queue: native payouts are queued and can be executed `delay` seconds later,
+    /// leaving room to freeze suspicious ones. Zero pays out at once again; queued withdrawals keep their time.
+    pub fn set_withdrawal_delay(&mut self, delay: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.withdrawal_delay.set(delay);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| WithdrawalDelayUpdated { sender, delay, seq });
+
+        Ok(())
+    }
+
+    pub fn withdrawal_delay(&self) -> U256 {
+        self.withdrawal_delay.get()
+    }
+
+    pub fn withdrawal_count(&self) -> U256 {
+        self.withdrawal_count.get()
+    }
+
+    /// Returns `(user, campaign_id, amount, ready_at, frozen, executed)` of queued withdrawal `withdrawal_id`.
+    pub fn withdrawal(&self, withdrawal_id: U256) -> (Address, U256, U256, U256, bool, bool) {
+        let withdrawal = self.withdrawals.get(withdrawal_id);
+        (
+            withdrawal.user.get(),
+            withdrawal.campaign_id.get(),
+            withdrawal.amount.get(),
+            withdrawal.ready_at.get(),
+            withdrawal.frozen.get(),
+            withdrawal.executed.get(),
+        )
+    }
+
+    /// Holds back or releases a queued withdrawal; callable by the owner or the guardian.
+    pub fn freeze_withdrawal(&mut self, withdrawal_id: U256, frozen: bool) -> Result<(), CommonError> {
+        self.assert_owner_or_guardian()?;
+        self.assert_withdrawal_pending(withdrawal_id)?;
+
+        self.withdrawals.setter(withdrawal_id).frozen.set(frozen);
+
+        self.emit(|seq| WithdrawalFrozen { withdrawal_id, frozen, seq });
+
+        Ok(())
+    }
+
+    /// Pays out a queued withdrawal once its delay has passed; callable by anyone, such as a keeper. After an
+    /// emergency shutdown the delay no longer applies, so the queue can be settled right away.
+    ///
+    /// Reverts with `WithdrawalNotReady` before then and with `WithdrawalLocked` while it is frozen.
+    pub fn execute_withdrawal(&mut self, withdrawal_id: U256) -> Result<U256, CommonError> {
+        self.assert_withdrawal_pending(withdrawal_id)?;
+
+        let withdrawal = self.withdrawals.get(withdrawal_id);
+        if withdrawal.frozen.get() {
+            return Err(CommonError::WithdrawalLocked(WithdrawalLocked {}));
+        }
+        if !self.is_shut_down() && U256::from(self.vm().block_timestamp()) < withdrawal.ready_at.get() {
+            return Err(CommonError::WithdrawalNotReady(WithdrawalNotReady {}));
+        }
+        let (user, campaign_id, amount) = (withdrawal.user.get(), withdrawal.campaign_id.get(), withdrawal.amount.get());
+        let queued_total = checked_sub(self.queued_total.get(), amount)?;
+        self.withdrawals.setter(withdrawal_id).executed.set(true);
+        self.queued_total.set(queued_total);
+
+        // Whoever executes the withdrawal only settles it, so it is not routed volume.
+        let donation = self.dispatch_native(campaign_id, user, user, amount)?;
+
+        self.emit(|seq| WithdrawalExecuted { withdrawal_id, user, amount, donation, seq });
+
+        Ok(amount)
+    }
+
+    /// Native currency held by the contract.
+    pub fn contract_balance(&self) -> U256 {
+        self.vm().balance(self.vm().contract_address())
+    }
+
+    /// Native currency held back for refundable sponsor deposits, for rewards issued but not yet claimed and
+    /// for queued withdrawals.
+    ///
+    /// Compounding growth not yet realized in a ledger is not included.
+    pub fn reserved_balance(&self) -> U256 {
+        let unclaimed = self.total_calculated.get().saturating_sub(self.total_claimed.get());
+        self.total_sponsor_deposits.get().saturating_add(unclaimed).saturating_add(self.queued_total.get())
+    }
+
+    /// Part of `contract_balance` beyond `reserved_balance`.
+    pub fn surplus(&self) -> U256 {
+        self.contract_balance().saturating_sub(self.reserved_balance())
+    }
+
+    /// Sends the whole `surplus` to the owner and returns it; the treasury keeps only what backs rewards owed.
+    pub fn withdraw_surplus(&mut self) -> Result<U256, CommonError> {
+        self.assert_owner()?;
+
+        let amount = self.surplus();
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+        self.debit_treasury(amount);
+        let recipient = self.owner.get();
+        self.transfer_native(recipient, amount)?;
+
+        self.emit(|seq| SurplusWithdrawn { recipient, amount, seq });
+
+        Ok(amount)
+    }
+
+    /// Adds the attached value to the treasury the rewards are paid from.
+    #[payable]
+    pub fn deposit(&mut self) -> Result<(), CommonError> {
+        let amount = self.vm().msg_value();
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+
+        let treasury = checked_add(self.treasury.get(), amount)?;
+        self.treasury.set(treasury);
+
+        let depositor = self.vm().msg_sender();
+        self.emit(|seq| TreasuryDeposited { depositor, token: Address::ZERO, amount, seq });
+
+        Ok(())
+    }
+
+    /// Pulls `amount` of the approved `token` from the caller into the treasury; needs a prior ERC20 approval.
+    pub fn deposit_token(&mut self, token: Address, amount: U256) -> Result<(), CommonError> {
+        self.assert_token_approved(token)?;
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+
+        self.pull_token_deposit(token, amount)
+    }
+
+    /// Like `deposit_token`, approving the pull with an EIP-2612 `permit` signed by the caller.
+    ///
+    /// A failing permit is ignored, so a permit front-run by someone else does not block the deposit;
+    /// the pull then needs an allowance as usual.
+    pub fn fund_with_permit(&mut self, token: Address, amount: U256, deadline: U256, v: u8, r: B256, s: B256) -> Result<(), CommonError> {
+        self.assert_token_approved(token)?;
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+
+        let permit = IERC20Permit::permitCall { owner: self.vm().msg_sender(), spender: self.vm().contract_address(), value: amount, deadline, v, r, s };
+        let _ = self.vm().call(&Call::new(), token, &permit.abi_encode());
+        self.pull_token_deposit(token, amount)
+    }
+
+    /// Sends `amount` of the native treasury to `to`.
+    ///
+    /// Reverts with `AmountExceedsBalance` past the treasury or past `surplus`, so rewards owed and
+    /// sponsor deposits stay covered.
+    pub fn withdraw(&mut self, to: Address, amount: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+        let treasury = self.treasury.get();
+        if amount > treasury || amount > self.surplus() {
+            return Err(CommonError::AmountExceedsBalance(AmountExceedsBalance {}));
+        }
+
+        self.treasury.set(treasury - amount);
+        self.transfer_native(to, amount)?;
+
+        self.emit(|seq| TreasuryWithdrawn { recipient: to, amount, seq });
+
+        Ok(())
+    }
+
+    /// Native currency deposited through `deposit` and not withdrawn.
+    pub fn treasury_balance(&self) -> U256 {
+        self.treasury.get()
+    }
+
+    /// Amount of `token` deposited through `deposit_token`.
+    pub fn treasury_token_balance(&self, token: Address) -> U256 {
+        self.treasury_tokens.get(token)
+    }
+
+    /// Sends `amount` of `token` held by the contract to `to`, for tokens sent to it by mistake.
+    ///
+    /// Reverts with `AmountExceedsBalance` past the part of the balance not held for campaign pools, the
+    /// treasury or, for the payout token, unclaimed rewards.
+    pub fn rescue_token(&mut self, token: Address, to: Address, amount: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+        let contract = self.vm().contract_address();
+        let balance = self.static_call_sol(token, &IERC20::balanceOfCall { account: contract }).map_or(U256::ZERO, |returned| returned._0);
+        if amount > balance.saturating_sub(self.held_token_balance(token)) {
+            return Err(CommonError::AmountExceedsBalance(AmountExceedsBalance {}));
+        }
+
+        self.call_token(token, IERC20::transferCall { to, amount }.abi_encode())?;
+
+        self.emit(|seq| AssetsRescued { token, recipient: to, amount, seq });
+
+        Ok(())
+    }
+
+    /// Sends `amount` of native currency to `to`, up to the part of `surplus` not deposited in the treasury.
+    pub fn rescue_eth(&mut self, to: Address, amount: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+        if amount > self.surplus().saturating_sub(self.treasury.get()) {
+            return Err(CommonError::AmountExceedsBalance(AmountExceedsBalance {}));
+        }
+
+        self.transfer_native(to, amount)?;
+
+        self.emit(|seq| AssetsRescued { token: Address::ZERO, recipient: to, amount, seq });
+
+        Ok(())
+    }
+
+    /// Sets how rewards accrued in `campaign_id` unlock: nothing until `cliff` seconds after the
+    /// campaign start, then linearly until `duration` seconds after it. A zero `duration` and `cliff`
+    /// credits rewards straight to the ledger. Only configurable while the campaign is a draft.
+    ///
+    /// Callable by the owner or an operator of `campaign_id`.
+    pub fn set_campaign_vesting(&mut self, campaign_id: U256, cliff: U256, duration: U256) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_campaign_admin(campaign_id)?;
+
+        if self.campaign_state_of(campaign_id) != CampaignState::Draft {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+        if duration > U256::ZERO && cliff > duration {
+            return Err(CommonError::InvalidTimeWindow(InvalidTimeWindow {}));
+        }
+
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.vesting_cliff.set(cliff);
+        campaign.vesting_duration.set(duration);
+
+        self.emit(|seq| CampaignVestingUpdated { campaign_id, cliff, duration, seq });
+
+        Ok(())
+    }
+
+    /// Limits claims of `campaign_id`'s rewards to `[claim_start, claim_deadline]`; a zero deadline never closes.
+    /// Only configurable while the campaign is a draft; see `extend_claim_deadline` afterwards.
+    ///
+    /// Callable by the owner or an operator of `campaign_id`.
+    pub fn set_campaign_claim_window(&mut self, campaign_id: U256, claim_start: U256, claim_deadline: U256) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_campaign_admin(campaign_id)?;
+
+        if self.campaign_state_of(campaign_id) != CampaignState::Draft {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+        if claim_deadline > U256::ZERO && claim_deadline <= claim_start {
+            return Err(CommonError::InvalidTimeWindow(InvalidTimeWindow {}));
+        }
+
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.claim_start.set(claim_start);
+        campaign.claim_deadline.set(claim_deadline);
+
+        self.emit(|seq| CampaignClaimWindowUpdated { campaign_id, claim_start, claim_deadline, seq });
+
+        Ok(())
+    }
+
+    /// Pushes `campaign_id`'s claim deadline back to `new_deadline`; allowed once per campaign.
+    pub fn extend_claim_deadline(&mut self, campaign_id: U256, new_deadline: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+
+        let campaign = self.campaigns.get(campaign_id);
+        if campaign.swept.get() {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+        if campaign.deadline_extended.get() {
+            return Err(CommonError::DeadlineAlreadyExtended(DeadlineAlreadyExtended {}));
+        }
+        let deadline = campaign.claim_deadline.get();
+        if deadline == U256::ZERO || new_deadline <= deadline {
+            return Err(CommonError::InvalidTimeWindow(InvalidTimeWindow {}));
+        }
+
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.claim_deadline.set(new_deadline);
+        campaign.deadline_extended.set(true);
+
+        self.emit(|seq| ClaimDeadlineExtended { campaign_id, claim_deadline: new_deadline, seq });
+
+        Ok(())
+    }
+
+    /// Expires everything still unclaimed in `campaign_id` once its claim deadline has passed, returning
+    /// the amount. Pending grants are zeroed and the funds stay in the pool for other rewards.
+    pub fn sweep_expired(&mut self, campaign_id: U256) -> Result<U256, CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+
+        let campaign = self.campaigns.get(campaign_id);
+        if campaign.swept.get() {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+        let deadline = campaign.claim_deadline.get();
+        let now = U256::from(self.vm().block_timestamp());
+        if deadline == U256::ZERO || !self.active_boundary_policy().has_ended(now, deadline) {
+            return Err(CommonError::ClaimWindowOpen(ClaimWindowOpen {}));
+        }
+
+        let amount = checked_sub(campaign.spent.get(), campaign.claimed.get())?;
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.swept.set(true);
+        campaign.expired.set(amount);
+
+        self.emit(|seq| RewardsExpired { campaign_id, amount, seq });
+
+        Ok(amount)
+    }
+
+    /// Returns how much of `campaign_id`'s rewards expired unclaimed in `sweep_expired`.
+    pub fn expired_rewards(&self, campaign_id: U256) -> U256 {
+        self.campaigns.get(campaign_id).expired.get()
+    }
+
+    /// Freezes calculations, accruals and claims in `campaign_id` alone, leaving other campaigns running.
+    ///
+    /// Callable by global operators and by operators of `campaign_id`.
+    pub fn pause_campaign(&mut self, campaign_id: U256) -> Result<(), CommonError> {
+        self.set_campaign_paused(campaign_id, true)
+    }
+
+    pub fn unpause_campaign(&mut self, campaign_id: U256) -> Result<(), CommonError> {
+        self.set_campaign_paused(campaign_id, false)
+    }
+
+    pub fn is_campaign_paused(&self, campaign_id: U256) -> bool {
+        self.campaigns.get(campaign_id).paused.get()
+    }
+
+    /// Pulls `amount` of `token` from the caller into `campaign_id`'s reward pool; needs a prior ERC20 approval.
+    ///
+    /// The first funding fixes the campaign's reward token; its grants are then paid from the pool.
+    pub fn fund_campaign(&mut self, campaign_id: U256, token: Address, amount: U256) -> Result<(), CommonError> {
+        self.assert_not_shut_down()?;
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_token_approved(token)?;
+
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+        let campaign = self.campaigns.get(campaign_id);
+        let current_token = campaign.reward_token.get();
+        if current_token != Address::ZERO && current_token != token {
+            return Err(CommonError::TokenMismatch(TokenMismatch {}));
+        }
+        if !matches!(self.campaign_state_of(campaign_id), CampaignState::Draft | CampaignState::Active) {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+
+        let pool = checked_add(campaign.pool.get(), amount)?;
+        let sponsor = self.vm().msg_sender();
+        let contract = self.vm().contract_address();
+        self.call_token(token, IERC20::transferFromCall { from: sponsor, to: contract, amount }.abi_encode())?;
+
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.reward_token.set(token);
+        campaign.pool.set(pool);
+        self.record_campaign_sponsorship(campaign_id, sponsor, amount)?;
+        let pooled = checked_add(self.pooled_tokens.get(token), amount)?;
+        self.pooled_tokens.setter(token).set(pooled);
+
+        self.emit(|seq| CampaignFunded { campaign_id, sponsor, token, amount, seq });
+
+        Ok(())
+    }
+
+    /// Total of `token` held across all campaign pools.
+    pub fn pooled_balance(&self, token: Address) -> U256 {
+        self.pooled_tokens.get(token)
+    }
+
+    /// Returns the `(token, balance)` of `campaign_id`'s reward pool; a zero token means native funding.
+    pub fn campaign_pool(&self, campaign_id: U256) -> (Address, U256) {
+        let campaign = self.campaigns.get(campaign_id);
+        (campaign.reward_token.get(), campaign.pool.get())
+    }
+
+    /// Splits native claims in `campaign_id` across `tokens`, each paid its share of the claim by `weights`
+    /// in basis points, which must add up to `BPS_DENOMINATOR`. `Address::ZERO` stands for native currency;
+    /// token legs are paid from the treasury. Empty lists restore plain native payouts.
+    ///
+    /// Reverts with `TokenMismatch` for campaigns funded with a token pool.
+    pub fn set_campaign_payout_legs(&mut self, campaign_id: U256, tokens: Vec<Address>, weights: Vec<U256>) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_campaign_admin(campaign_id)?;
+
+        if tokens.len() != weights.len() {
+            return Err(CommonError::LengthMismatch(LengthMismatch {}));
+        }
+        if self.campaigns.get(campaign_id).reward_token.get() != Address::ZERO {
+            return Err(CommonError::TokenMismatch(TokenMismatch {}));
+        }
+        let mut total_weight = U256::ZERO;
+        for (token, weight) in tokens.iter().zip(&weights) {
+            if *token != Address::ZERO {
+                self.assert_token_approved(*token)?;
+            }
+            total_weight = checked_add(total_weight, *weight)?;
+        }
+        if !tokens.is_empty() && total_weight != U256::from(BPS_DENOMINATOR) {
+            return Err(CommonError::InvalidPayoutLegs(InvalidPayoutLegs {}));
+        }
+
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.leg_tokens.truncate(0);
+        campaign.leg_weights.truncate(0);
+        for (token, weight) in tokens.iter().zip(&weights) {
+            campaign.leg_tokens.push(*token);
+            campaign.leg_weights.push(*weight);
+        }
+
+        self.emit(|seq| CampaignPayoutLegsUpdated { campaign_id, tokens, weights, seq });
+
+        Ok(())
+    }
+
+    /// Returns the `(tokens, weights)` native claims in `campaign_id` are split across.
+    pub fn campaign_payout_legs(&self, campaign_id: U256) -> (Vec<Address>, Vec<U256>) {
+        let campaign = self.campaigns.get(campaign_id);
+        let tokens = (0..campaign.leg_tokens.len()).filter_map(|index| campaign.leg_tokens.get(index)).collect();
+        let weights = (0..campaign.leg_weights.len()).filter_map(|index| campaign.leg_weights.get(index)).collect();
+        (tokens, weights)
+    }
+
+    /// Pushes back the end of `campaign_id` while it is still running, at most `MAX_CAMPAIGN_EXTENSIONS` times.
+    pub fn extend_campaign(&mut self, campaign_id: U256, new_end_time: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+
+        let campaign = self.campaigns.get(campaign_id);
+        let old_end_time = campaign.end_time.get();
+        let now = U256::from(self.vm().block_timestamp());
+        if !matches!(self.campaign_state_of(campaign_id), CampaignState::Draft | CampaignState::Active)
+            || self.active_boundary_policy().has_ended(now, old_end_time)
+        {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+        if new_end_time <= old_end_time {
+            return Err(CommonError::InvalidTimeWindow(InvalidTimeWindow {}));
+        }
+        let extensions = campaign.extensions.get().to::<u8>();
+        if extensions >= MAX_CAMPAIGN_EXTENSIONS {
+            return Err(CommonError::ExtensionLimitReached(ExtensionLimitReached {}));
+        }
+
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.end_time.set(new_end_time);
+        campaign.extensions.set(U8::from(extensions + 1));
+
+        self.emit(|seq| CampaignExtended { campaign_id, old_end_time, new_end_time, seq });
+
+        Ok(())
+    }
+
+    pub fn campaign_extensions(&self, campaign_id: U256) -> u8 {
+        self.campaigns.get(campaign_id).extensions.get().to::<u8>()
+    }
+
+    /// Restricts `campaign_id` to its participant set, or opens it to everyone again.
+    ///
+    /// Callable by the owner or an operator of `campaign_id`.
+    pub fn set_campaign_restricted(&mut self, campaign_id: U256, restricted: bool) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_campaign_admin(campaign_id)?;
+
+        self.campaigns.setter(campaign_id).restricted.set(restricted);
+
+        self.emit(|seq| CampaignRestrictionUpdated { campaign_id, restricted, seq });
+
+        Ok(())
+    }
+
+    /// Adds `members` to or removes them from `campaign_id`'s participant set, registering them as
+    /// members so their flags share bitmap words.
+    ///
+    /// Callable by the owner or an operator of `campaign_id`.
+    pub fn set_campaign_participants(&mut self, campaign_id: U256, members: Vec<Address>, allowed: bool) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_campaign_admin(campaign_id)?;
+
+        for &member in &members {
+            let index = self.ensure_member_index(member);
+            self.campaigns.setter(campaign_id).participants.set_to(index, allowed);
+        }
+
+        let count = U256::from(members.len());
+        self.emit(|seq| CampaignParticipantsUpdated { campaign_id, count, allowed, seq });
+
+        Ok(())
+    }
+
+    /// Whether `account` may accrue and claim in `campaign_id`: always for unrestricted campaigns.
+    pub fn is_campaign_participant(&self, campaign_id: U256, account: Address) -> bool {
+        let campaign = self.campaigns.get(campaign_id);
+        if !campaign.restricted.get() {
+            return true;
+        }
+        self.member_flag(&campaign.participants, account)
+    }
+
+    /// Returns the `(claim_start, claim_deadline)` of `campaign_id`.
+    pub fn campaign_claim_window(&self, campaign_id: U256) -> Result<(U256, U256), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        let campaign = self.campaigns.get(campaign_id);
+        Ok((campaign.claim_start.get(), campaign.claim_deadline.get()))
+    }
+
+    /// One-line status of `user` in `campaign_id` for wallets and bots that cannot decode structs, e.g.
+    /// `"Eligible: yes, est. reward: 12.5 RWD, window ends in 3 hours"`.
+    ///
+    /// The estimate is the unlocked part of the user's grant in campaigns with grants and their ledger
+    /// balance otherwise; native rewards are shown as ETH.
+    pub fn claim_summary(&self, user: Address, campaign_id: U256) -> Result<String, CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+
+        let campaign = self.campaigns.get(campaign_id);
+        let eligible = self.is_campaign_participant(campaign_id, user) && !campaign.paused.get() && self.claim_window_open(campaign_id);
+        let estimate = if self.has_grants(campaign_id) { self.releasable(campaign_id, user)? } else { self.compounded_balance_of(user)? };
+        let token = campaign.reward_token.get();
+        let (decimals, symbol) = if token == Address::ZERO {
+            (INTERNAL_DECIMALS, String::from("ETH"))
+        } else {
+            let info = self.reward_tokens.get(token);
+            (info.decimals.get().to::<u8>(), info.symbol.get_string())
+        };
+
+        let now = U256::from(self.vm().block_timestamp());
+        let deadline = campaign.claim_deadline.get();
+        let window = if deadline == U256::ZERO {
+            String::from("no claim deadline")
+        } else if self.active_boundary_policy().has_ended(now, deadline) {
+            String::from("window closed")
+        } else {
+            format!("window ends in {} hours", (deadline - now).div_ceil(U256::from(3600)))
+        };
+
+        Ok(format!(
+            "Eligible: {}, est. reward: {} {}, {}",
+            if eligible { "yes" } else { "no" },
+            format_units(estimate, decimals),
+            symbol,
+            window,
+        ))
+    }
+
+    /// Returns the `(cliff, duration)` vesting schedule of `campaign_id`.
+    pub fn campaign_vesting(&self, campaign_id: U256) -> Result<(U256, U256), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        let campaign = self.campaigns.get(campaign_id);
+        Ok((campaign.vesting_cliff.get(), campaign.vesting_duration.get()))
+    }
+
+    /// Returns how much of `user`'s vesting rewards is unlocked and not yet released, across campaigns.
+    pub fn vested_of(&self, user: Address) -> Result<U256, CommonError> {
+        let campaigns = self.vesting_campaigns.get(user);
+        let mut total = U256::ZERO;
+        for index in 0..campaigns.len() {
+            let campaign_id = campaigns.get(index).unwrap_or_default();
+            total = checked_add(total, self.releasable(campaign_id, user)?)?;
+        }
+        Ok(total)
+    }
+
+    /// Returns `user`'s `(granted, released)` vesting rewards in `campaign_id`.
+    pub fn vesting_of(&self, campaign_id: U256, user: Address) -> (U256, U256) {
+        let campaign = self.campaigns.get(campaign_id);
+        (campaign.vesting_granted.get(user), campaign.vesting_released.get(user))
+    }
+
+    /// Pays out the unlocked part of the caller's campaign grants, like `claim`, and returns it.
+    ///
+    /// Paused campaigns, restricted ones the caller left, and those outside their claim window are
+    /// skipped; if nothing else is claimable this reverts with `CampaignPaused`, `NotEligible` or
+    /// `ClaimWindowClosed`.
+    ///
+    /// Grants of ERC20-funded campaigns are paid in full in the campaign token from its pool; only
+    /// native payouts are subject to the caller's donation.
+    pub fn claim_vested(&mut self) -> Result<U256, CommonError> {
+        self.claim_grants(self.vm().msg_sender())
+    }
+
+    /// Pays out the unlocked grants of the caller in `campaign_ids` as one transfer with one
+    /// `BatchRewardClaimed` event, returning the total.
+    ///
+    /// All campaigns with something to claim must pay in the same token and without payout legs. Unlike `claim_vested`, a listed
+    /// campaign that is paused, restricted against the caller or outside its claim window reverts the batch.
+    pub fn claim_all(&mut self, campaign_ids: Vec<U256>) -> Result<U256, CommonError> {
+        self.assert_not_shut_down()?;
+        self.assert_not_paused()?;
+
+        let user = self.vm().msg_sender();
+        let mut amount = U256::ZERO;
+        let mut token = None;
+        let mut campaign_count = U256::ZERO;
+        let mut shares = BonusShares::default();
+        for campaign_id in campaign_ids {
+            let releasable = self.releasable(campaign_id, user)?;
+            if releasable == U256::ZERO {
+                continue;
+            }
+            let campaign = self.campaigns.get(campaign_id);
+            if campaign.paused.get() {
+                return Err(CommonError::CampaignPaused(CampaignPaused {}));
+            }
+            if !self.is_campaign_participant(campaign_id, user) {
+                return Err(CommonError::NotEligible(NotEligible {}));
+            }
+            if !self.claim_window_open(campaign_id) {
+                return Err(CommonError::ClaimWindowClosed(ClaimWindowClosed {}));
+            }
+            let campaign_token = campaign.reward_token.get();
+            if *token.get_or_insert(campaign_token) != campaign_token || !campaign.leg_tokens.is_empty() {
+                return Err(CommonError::TokenMismatch(TokenMismatch {}));
+            }
+
+            shares = shares.checked_add(self.released_bonus_shares(campaign_id, user, releasable)?)?;
+            let released = checked_add(campaign.vesting_released.get(user), releasable)?;
+            let claimed = checked_add(campaign.claimed.get(), releasable)?;
+            let pool = if campaign_token == Address::ZERO { campaign.pool.get() } else { checked_sub(campaign.pool.get(), releasable)? };
+            let mut campaign = self.campaigns.setter(campaign_id);
+            campaign.vesting_released.setter(user).set(released);
+            campaign.claimed.set(claimed);
+            campaign.pool.set(pool);
+            self.record_campaign_claim(campaign_id, user);
+            if campaign_token != Address::ZERO {
+                self.release_pooled(campaign_token, releasable)?;
+            }
+            amount = checked_add(amount, releasable)?;
+            campaign_count += U256::from(1);
+        }
+        let Some(token) = token else {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        };
+        let total_claimed = checked_add(self.total_claimed.get(), amount)?;
+        self.total_claimed.set(total_claimed);
+
+        if token == Address::ZERO {
+            self.send_native(U256::ZERO, user, amount)?;
+        } else {
+            self.send_token(U256::ZERO, token, user, amount)?;
+        }
+
+        self.emit_claim_receipt(U256::ZERO, user, amount, shares);
+        self.emit(|seq| BatchRewardClaimed { user, token, amount, campaign_count, seq });
+
+        Ok(amount)
+    }
+
+    /// Runs `claim_vested` for `account`, paying `account`; callable by claimers `account` approved.
+    pub fn claim_vested_for(&mut self, account: Address) -> Result<U256, CommonError> {
+        self.assert_claimer(account)?;
+        self.claim_grants(account)
+    }
+
+    /// Same as `credit`, under its original name.
+    pub fn accrue(&mut self, user: Address, amount: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.credit(user, amount, start_time, end_time, has_bonus, has_strict_bonus)
+    }
+
+    /// Prices `amount` with the reward formula and credits the result to `user`'s ledger, compounding
+    /// the outstanding balance up to the current epoch first.
+    pub fn credit(&mut self, user: Address, amount: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.assert_operator()?;
+        self.assert_not_shut_down()?;
+        self.assert_not_paused()?;
+
+        let keep_bonuses = self.screen_user(user)?;
+        let breakdown = self.compute_breakdown(RewardRequest {
+            amount,
+            current_time: U256::from(self.vm().block_timestamp()),
+            start_time,
+            end_time,
+            has_bonus: has_bonus && keep_bonuses,
+            has_strict_bonus: has_strict_bonus && keep_bonuses,
+            extra_bonus: if keep_bonuses { self.streak_bonus_of(user) } else { U256::ZERO },
+            bonus_params: self.global_bonus_params(),
+            has_penalty: false,
+        })?;
+        self.credit_reward(U256::ZERO, user, breakdown.total, BonusShares::of(&breakdown))?;
+
+        Ok(breakdown.total)
+    }
+
+    /// Like `accrue`, priced with `campaign_id`'s settings and window and charged to its budget.
+    ///
+    /// Callable by global operators and by operators of `campaign_id`.
+    ///
+    /// Reverts with `BudgetExceeded` if the reward and the referrer's share of it would take the campaign's
+    /// issued total past its budget.
+    /// In campaigns with a vesting schedule, claim window or token pool the reward is granted for
+    /// `claim_vested` instead of credited; for pools, `BudgetNotFunded` guards against unbacked grants.
+    pub fn accrue_for_campaign(&mut self, campaign_id: U256, user: Address, amount: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.assert_campaign_operator(campaign_id)?;
+        self.assert_not_shut_down()?;
+        self.assert_not_paused()?;
+        self.assert_campaign_active(campaign_id)?;
+        if !self.is_campaign_participant(campaign_id, user) {
+            return Err(CommonError::NotEligible(NotEligible {}));
+        }
+        if !self.campaign_window_open(campaign_id) {
+            return Err(CommonError::OutsideCampaignWindow(OutsideCampaignWindow {}));
+        }
+
+        let breakdown = self.price_campaign_reward(campaign_id, user, amount, has_bonus, has_strict_bonus)?;
+        let reward = breakdown.total;
+        self.record_campaign_multiplier(campaign_id, breakdown.time_multiplier);
+        let referrer = self.referrers.get(user);
+        let referral = if referrer != Address::ZERO && self.is_campaign_participant(campaign_id, referrer) {
+            scale(reward, self.campaigns.get(campaign_id).referral_bps.get(), U256::from(BPS_DENOMINATOR))?
+        } else {
+            U256::ZERO
+        };
+        self.charge_campaign(campaign_id, user, checked_add(reward, referral)?)?;
+        self.issue_campaign_reward(campaign_id, user, reward, BonusShares::of(&breakdown))?;
+        if referral > U256::ZERO {
+            let earnings = checked_add(self.referral_earnings.get(referrer), referral)?;
+            self.referral_earnings.setter(referrer).set(earnings);
+            self.issue_campaign_reward(campaign_id, referrer, referral, BonusShares::default())?;
+
+            self.emit(|seq| ReferralCredited { campaign_id, referrer, referee: user, amount: referral, seq });
+        }
+
+        Ok(reward)
+    }
+
+    /// Sets the root of `campaign_id`'s merkle drop for `claim_with_proof`; zero closes the drop.
+    pub fn set_campaign_merkle_root(&mut self, campaign_id: U256, merkle_root: B256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+
+        self.campaigns.setter(campaign_id).merkle_root.set(merkle_root);
+
+        self.emit(|seq| CampaignMerkleRootUpdated { campaign_id, merkle_root, seq });
+
+        Ok(())
+    }
+
+    pub fn campaign_merkle_root(&self, campaign_id: U256) -> B256 {
+        self.campaigns.get(campaign_id).merkle_root.get()
+    }
+
+    pub fn is_merkle_claimed(&self, campaign_id: U256, index: U256) -> bool {
+        self.campaigns.get(campaign_id).merkle_claimed.get(index)
+    }
+
+    /// Claims leaf `index` of `campaign_id`'s merkle drop for the caller: prices `amount` like
+    /// `accrue_for_campaign` and pays the reward out at once, charged to the campaign's budget and pool.
+    ///
+    /// Leaves are `keccak256(keccak256(abi.encode(index, account, amount, has_bonus, has_strict_bonus)))`
+    /// in a sorted-pair tree, as built by OpenZeppelin's `StandardMerkleTree`.
+    pub fn claim_with_proof(&mut self, campaign_id: U256, index: U256, amount: U256, has_bonus: bool, has_strict_bonus: bool, proof: Vec<B256>) -> Result<U256, CommonError> {
+        self.assert_not_shut_down()?;
+        self.assert_not_paused()?;
+        self.assert_campaign_active(campaign_id)?;
+
+        let user = self.vm().msg_sender();
+        if !self.is_campaign_participant(campaign_id, user) {
+            return Err(CommonError::NotEligible(NotEligible {}));
+        }
+        let campaign = self.campaigns.get(campaign_id);
+        if campaign.merkle_claimed.get(index) {
+            return Err(CommonError::AlreadyClaimed(AlreadyClaimed {}));
+        }
+        let leaf = keccak(keccak((index, user, amount, has_bonus, has_strict_bonus).abi_encode()));
+        let root = campaign.merkle_root.get();
+        if root == B256::ZERO || !merkle::verify(&proof, root, leaf) {
+            return Err(CommonError::InvalidProof(InvalidProof {}));
+        }
+
+        let breakdown = self.price_campaign_reward(campaign_id, user, amount, has_bonus, has_strict_bonus)?;
+        let reward = breakdown.total;
+        self.charge_campaign(campaign_id, user, reward)?;
+        self.record_campaign_multiplier(campaign_id, breakdown.time_multiplier);
+        let campaign = self.campaigns.get(campaign_id);
+        let (claimed, token, pool) = (checked_add(campaign.claimed.get(), reward)?, campaign.reward_token.get(), campaign.pool.get());
+        let total_calculated = checked_add(self.total_calculated.get(), reward)?;
+        let total_claimed = checked_add(self.total_claimed.get(), reward)?;
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.merkle_claimed.set(index);
+        campaign.claimed.set(claimed);
+        self.record_campaign_claim(campaign_id, user);
+        self.total_calculated.set(total_calculated);
+        self.total_claimed.set(total_claimed);
+
+        self.emit(|seq| MerkleClaimed { campaign_id, index, user, amount, reward, seq });
+
+        if token == Address::ZERO {
+            self.emit_claim_receipt(campaign_id, user, reward, BonusShares::of(&breakdown));
+            self.pay_out_campaign(campaign_id, user, reward)?;
+            return Ok(reward);
+        }
+        self.campaigns.setter(campaign_id).pool.set(checked_sub(pool, reward)?);
+        self.release_pooled(token, reward)?;
+        self.send_token(campaign_id, token, user, reward)?;
+
+        self.emit_claim_receipt(campaign_id, user, reward, BonusShares::of(&breakdown));
+        self.emit(|seq| CampaignRewardClaimed { campaign_id, user, token, amount: reward, seq });
+
+        Ok(reward)
+    }
+
+    /// Records that `referee` was referred by `referrer`; a referee's referrer is set once and for good.
+    ///
+    /// Afterwards `referrer` earns each campaign's `referral_bps` of what `referee` accrues in it,
+    /// charged to the campaign's budget on top of the referee's reward.
+    pub fn register_referral(&mut self, referee: Address, referrer: Address) -> Result<(), CommonError> {
+        self.assert_operator()?;
+
+        if referrer == Address::ZERO || referrer == referee || self.referrers.get(referee) != Address::ZERO {
+            return Err(CommonError::InvalidReferral(InvalidReferral {}));
+        }
+        self.referrers.setter(referee).set(referrer);
+
+        self.emit(|seq| ReferralRegistered { referee, referrer, seq });
+
+        Ok(())
+    }
+
+    pub fn referrer_of(&self, referee: Address) -> Address {
+        self.referrers.get(referee)
+    }
+
+    /// Total referral rewards `referrer` has earned across campaigns.
+    pub fn referral_earnings_of(&self, referrer: Address) -> U256 {
+        self.referral_earnings.get(referrer)
+    }
+
+    /// Sets the share of referees' rewards in `campaign_id` paid to their referrers, in basis points.
+    ///
+    /// Callable by the owner or an operator of `campaign_id`.
+    pub fn set_campaign_referral_rate(&mut self, campaign_id: U256, referral_bps: U256) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_campaign_admin(campaign_id)?;
+
+        if referral_bps > U256::from(BPS_DENOMINATOR) {
+            return Err(CommonError::InvalidReferral(InvalidReferral {}));
+        }
+        self.campaigns.setter(campaign_id).referral_bps.set(referral_bps);
+
+        self.emit(|seq| CampaignReferralRateUpdated { campaign_id, referral_bps, seq });
+
+        Ok(())
+    }
+
+    pub fn campaign_referral_rate(&self, campaign_id: U256) -> U256 {
+        self.campaigns.get(campaign_id).referral_bps.get()
+    }
+
+    /// Returns `user`'s unclaimed balance including compounding for every epoch since its last update.
+    pub fn compounded_balance_of(&self, user: Address) -> Result<U256, CommonError> {
+        let ledger = self.ledgers.get(user);
+        let balance = checked_sub(ledger.accrued.get(), ledger.claimed.get())?;
+        if balance == U256::ZERO {
+            return Ok(U256::ZERO);
+        }
+
+        // Counted from the last update's timestamp so a changed epoch length cannot put it in a later epoch.
+        let epochs = self.current_epoch().saturating_sub(ledger.last_update.get() / self.epoch_length.get());
+        self.compound(balance, self.compound_rate.get(), epochs)
+    }
+
+    /// Returns what `user` can currently claim; same as `compounded_balance_of`.
+    pub fn balance_of(&self, user: Address) -> Result<U256, CommonError> {
+        self.compounded_balance_of(user)
+    }
+
+    /// Returns `user`'s ledger as `(accrued, claimed, last_update)`.
+    ///
+    /// `accrued` counts everything ever credited plus compounding growth realized up to `last_update`.
+    pub fn ledger_of(&self, user: Address) -> (U256, U256, U256) {
+        let ledger = self.ledgers.get(user);
+        (ledger.accrued.get(), ledger.claimed.get(), ledger.last_update.get())
+    }
+
+    pub fn compound_rate(&self) -> U256 {
+        self.compound_rate.get()
+    }
+
+    /// Selects a `BoundaryPolicy` by its discriminant.
+    pub fn update_boundary_policy(&mut self, new_policy: u8) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        if BoundaryPolicy::from_u8(new_policy).is_none() {
+            return Err(CommonError::InvalidBoundaryPolicy(InvalidBoundaryPolicy {}));
+        }
+
+        self.boundary_policy.set(U8::from(new_policy));
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| BoundaryPolicyUpdated {
+            sender,
+            boundary_policy: new_policy,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn boundary_policy(&self) -> u8 {
+        self.boundary_policy.get().to::<u8>()
+    }
+
+    /// Selects how decay and bonus divisions round: 0 floor, 1 ceil, 2 round half up.
+    pub fn update_rounding_mode(&mut self, new_mode: u8) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        if Rounding::from_u8(new_mode).is_none() {
+            return Err(CommonError::InvalidRoundingMode(InvalidRoundingMode {}));
+        }
+
+        self.rounding_mode.set(U8::from(new_mode));
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| RoundingModeUpdated {
+            sender,
+            rounding_mode: new_mode,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn rounding_mode(&self) -> u8 {
+        self.rounding_mode.get().to::<u8>()
+    }
+
+    /// Selects a `DecayMode` by its discriminant.
+    pub fn update_decay_mode(&mut self, new_mode: u8) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        let mode = DecayMode::from_u8(new_mode).ok_or(CommonError::InvalidDecayMode(InvalidDecayMode {}))?;
+        if mode == DecayMode::TwoSegment && self.decay_midpoint_fraction.get() == U256::ZERO {
+            return Err(CommonError::InvalidDecayMidpoint(InvalidDecayMidpoint {}));
+        }
+
+        self.decay_mode.set(U8::from(new_mode));
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| DecayModeUpdated {
+            sender,
+            decay_mode: new_mode,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn decay_mode(&self) -> u8 {
+        self.decay_mode.get().to::<u8>()
+    }
+
+    pub fn update_step_interval(&mut self, new_interval: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        if new_interval == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+
+        self.step_interval.set(new_interval);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| StepIntervalUpdated {
+            sender,
+            step_interval: new_interval,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn step_interval(&self) -> U256 {
+        self.step_interval.get()
+    }
+
+    /// Allowlists `token` as a reward token after reading its `decimals()` and `symbol()`.
+    ///
+    /// Reverts with `InvalidDecimals` if the token reports more than `MAX_REWARD_DECIMALS`.
+    pub fn approve_token(&mut self, token: Address) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        let unavailable = || CommonError::TokenMetadataUnavailable(TokenMetadataUnavailable {});
+        let decimals = self.static_call_sol(token, &IERC20Metadata::decimalsCall {}).ok_or_else(unavailable)?._0;
+        let symbol = self.static_call_sol(token, &IERC20Metadata::symbolCall {}).ok_or_else(unavailable)?._0;
+        if decimals > MAX_REWARD_DECIMALS {
+            return Err(CommonError::InvalidDecimals(InvalidDecimals {}));
+        }
+
+        let mut entry = self.reward_tokens.setter(token);
+        entry.approved.set(true);
+        entry.decimals.set(U8::from(decimals));
+        entry.symbol.set_str(&symbol);
+
+        self.emit(|seq| TokenApproved { token, decimals, symbol, seq });
+
+        Ok(())
+    }
+
+    pub fn revoke_token(&mut self, token: Address) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_token_approved(token)?;
+
+        self.reward_tokens.setter(token).approved.set(false);
+
+        self.emit(|seq| TokenRevoked { token, seq });
+
+        Ok(())
+    }
+
+    /// Returns whether `token` is allowlisted together with the decimals and symbol stored at approval.
+    pub fn token_info(&self, token: Address) -> (bool, u8, String) {
+        let entry = self.reward_tokens.get(token);
+        (entry.approved.get(), entry.decimals.get().to::<u8>(), entry.symbol.get_string())
+    }
+
+    /// Sequence number carried by the most recently emitted event; every event increments it by one.
+    pub fn last_event_seq(&self) -> U256 {
+        self.event_seq.get()
+    }
+
+    pub fn set_guardian(&mut self, new_guardian: Address) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        let previous_guardian = self.guardian.get();
+        self.guardian.set(new_guardian);
+
+        self.emit(|seq| GuardianUpdated {
+            previous_guardian,
+            new_guardian,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn guardian(&self) -> Address {
+        self.guardian.get()
+    }
+
+    /// Records the caller's approval of a destructive action, as owner or as guardian.
+    ///
+    /// Both roles are matched against the direct caller, so a contract the owner calls through cannot
+    /// confirm on their behalf.
+    pub fn confirm_action(&mut self, action: u8) -> Result<(), CommonError> {
+        let now = U256::from(self.vm().block_timestamp());
+        let key = U256::from(action);
+        let confirmer = self.vm().msg_sender();
+
+        if self.assert_owner_is_caller().is_ok() {
+            self.owner_confirmations.setter(key).set(now);
+        } else if confirmer == self.guardian.get() && confirmer != Address::ZERO {
+            self.guardian_confirmations.setter(key).set(now);
+        } else {
+            return Err(CommonError::Unauthorized(Unauthorized {}));
+        }
+
+        self.emit(|seq| ActionConfirmed {
+            confirmer,
+            action,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    /// Returns whether both the owner and the guardian confirmed `action` within `CONFIRMATION_WINDOW`.
+    pub fn is_action_confirmed(&self, action: u8) -> bool {
+        let key = U256::from(action);
+        let now = U256::from(self.vm().block_timestamp());
+        let window = U256::from(CONFIRMATION_WINDOW);
+
+        let owner_at = self.owner_confirmations.get(key);
+        let guardian_at = self.guardian_confirmations.get(key);
+
+        owner_at != U256::ZERO
+            && guardian_at != U256::ZERO
+            && owner_at + window >= now
+            && guardian_at + window >= now
+    }
+
+    pub fn set_donation_beneficiary(&mut self, beneficiary: Address, approved: bool) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        if beneficiary == Address::ZERO {
+            return Err(CommonError::InvalidDonation(InvalidDonation {}));
+        }
+
+        self.donation_beneficiaries.setter(beneficiary).set(approved);
+
+        self.emit(|seq| DonationBeneficiaryUpdated { beneficiary, approved, seq });
+
+        Ok(())
+    }
+
+    pub fn is_donation_beneficiary(&self, beneficiary: Address) -> bool {
+        self.donation_beneficiaries.get(beneficiary)
+    }
+
+    /// Opts the caller into donating `bps` of each claim to an approved `beneficiary`; `bps == 0` opts out.
+    pub fn set_donation(&mut self, beneficiary: Address, bps: U256) -> Result<(), CommonError> {
+        let user = self.vm().msg_sender();
+        let beneficiary = if bps == U256::ZERO { Address::ZERO } else { beneficiary };
+
+        if bps > U256::from(BPS_DENOMINATOR) || (bps > U256::ZERO && !self.donation_beneficiaries.get(beneficiary)) {
+            return Err(CommonError::InvalidDonation(InvalidDonation {}));
+        }
+
+        self.donation_targets.setter(user).set(beneficiary);
+        self.donation_bps.setter(user).set(bps);
+
+        self.emit(|seq| DonationPreferenceUpdated { user, beneficiary, bps, seq });
+
+        Ok(())
+    }
+
+    /// Returns the beneficiary and share in basis points `user` donates from each claim.
+    pub fn donation_of(&self, user: Address) -> (Address, U256) {
+        (self.donation_targets.get(user), self.donation_bps.get(user))
+    }
+
+    /// Splits a claim of `amount` by `user` into `(donation, remainder)`.
+    ///
+    /// Nothing is donated once the chosen beneficiary has been removed from the list.
+    pub fn donation_split(&self, user: Address, amount: U256) -> Result<(U256, U256), CommonError> {
+        let beneficiary = self.donation_targets.get(user);
+        if !self.donation_beneficiaries.get(beneficiary) {
+            return Ok((U256::ZERO, amount));
+        }
+
+        let donation = scale(amount, self.donation_bps.get(user), U256::from(BPS_DENOMINATOR))?;
+        Ok((donation, amount - donation))
+    }
+
+    pub fn donated_total(&self, beneficiary: Address) -> U256 {
+        self.donated_totals.get(beneficiary)
+    }
+
+    /// Sets the WETH contract native payouts are wrapped with for users who opted in; `Address::ZERO` pays
+    /// everyone in native currency.
+    pub fn set_weth(&mut self, weth: Address) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.weth.set(weth);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| WethUpdated { sender, weth, seq });
+
+        Ok(())
+    }
+
+    pub fn weth(&self) -> Address {
+        self.weth.get()
+    }
+
+    /// Opts the caller into receiving native payouts as WETH, for recipients that cannot take plain transfers.
+    pub fn set_receive_weth(&mut self, enabled: bool) -> Result<(), CommonError> {
+        let user = self.vm().msg_sender();
+        self.wants_weth.setter(user).set(enabled);
+
+        self.emit(|seq| WethPreferenceUpdated { user, enabled, seq });
+
+        Ok(())
+    }
+
+    pub fn receives_weth(&self, user: Address) -> bool {
+        self.wants_weth.get(user)
+    }
+
+    /// Lets the L1 owner and operators act through retryable tickets, whose L2 sender is their aliased address.
+    pub fn set_accept_aliased_callers(&mut self, enabled: bool) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.accept_aliased_callers.set(enabled);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| AliasedCallersUpdated { sender, enabled, seq });
+
+        Ok(())
+    }
+
+    pub fn accepts_aliased_callers(&self) -> bool {
+        self.accept_aliased_callers.get()
+    }
+
+    /// Computes the time multiplier with 18 decimals instead of `percentage_denominator` units.
+    pub fn update_wad_precision(&mut self, wad_precision: bool) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.wad_precision.set(wad_precision);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| PrecisionModeUpdated { sender, wad_precision, seq });
+
+        Ok(())
+    }
+
+    pub fn wad_precision(&self) -> bool {
+        self.wad_precision.get()
+    }
+
+    /// Rewards positions priced more than `window` seconds before `start_time` with an extra `bonus`.
+    ///
+    /// `bonus` is in `percentage_denominator` units; zero disables the early-bird bonus.
+    pub fn update_early_bird(&mut self, window: U256, bonus: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.early_bird_window.set(window);
+        self.early_bird_bonus.set(bonus);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| EarlyBirdUpdated { sender, window, bonus, seq });
+
+        Ok(())
+    }
+
+    pub fn early_bird(&self) -> (U256, U256) {
+        (self.early_bird_window.get(), self.early_bird_bonus.get())
+    }
+
+    /// Pauses funding, participation and accrual for `duration` seconds; callable by the owner or the guardian.
+    ///
+    /// The pause lifts itself once the duration elapses, so a lost key cannot freeze the contract.
+    pub fn pause_for(&mut self, duration: U256) -> Result<(), CommonError> {
+        self.assert_owner_or_guardian()?;
+
+        if duration == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+
+        let until = checked_add(U256::from(self.vm().block_timestamp()), duration)?;
+        self.paused_until.set(until);
+
+        let sender = self.vm().msg_sender();
+        self.emit(|seq| Paused { sender, until, seq });
+
+        Ok(())
+    }
+
+    /// Clears the pause: at any time for the owner or the guardian, once expired for anyone else.
+    pub fn try_unpause(&mut self) -> Result<(), CommonError> {
+        if self.paused_until.get() == U256::ZERO {
+            return Ok(());
+        }
+        if self.is_paused() && self.assert_owner_or_guardian().is_err() {
+            return Err(CommonError::PauseNotExpired(PauseNotExpired {}));
+        }
+
+        self.paused_until.set(U256::ZERO);
+
+        let sender = self.vm().msg_sender();
+        self.emit(|seq| Unpaused { sender, seq });
+
+        Ok(())
+    }
+
+    pub fn is_paused(&self) -> bool {
+        U256::from(self.vm().block_timestamp()) < self.paused_until.get()
+    }
+
+    pub fn paused_until(&self) -> U256 {
+        self.paused_until.get()
+    }
+
+    /// Keeps the multiplier at its last in-window value for `grace_period` seconds after `end_time`.
+    pub fn update_grace_period(&mut self, grace_period: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.grace_period.set(grace_period);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| GracePeriodUpdated { sender, grace_period, seq });
+
+        Ok(())
+    }
+
+    pub fn grace_period(&self) -> U256 {
+        self.grace_period.get()
+    }
+
+    pub fn update_bonus_policy(&mut self, new_policy: u8) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        if BonusPolicy::from_u8(new_policy).is_none() {
+            return Err(CommonError::InvalidBonusPolicy(InvalidBonusPolicy {}));
+        }
+
+        self.bonus_policy.set(U8::from(new_policy));
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| BonusPolicyUpdated {
+            sender,
+            bonus_policy: new_policy,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn bonus_policy(&self) -> u8 {
+        self.bonus_policy.get().to::<u8>()
+    }
+
+    /// Sets where the two segments of `DecayMode::TwoSegment` meet.
+    ///
+    /// `fraction` is the share of the window elapsed at the midpoint and `multiplier` the value there,
+    /// both in `percentage_denominator` units; the multiplier must lie between 50% and 100%.
+    pub fn update_decay_midpoint(&mut self, fraction: U256, multiplier: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        let denominator = self.percentage_denominator.get();
+        if fraction == U256::ZERO || fraction >= denominator || multiplier < denominator / U256::from(2) || multiplier > denominator {
+            return Err(CommonError::InvalidDecayMidpoint(InvalidDecayMidpoint {}));
+        }
+
+        self.decay_midpoint_fraction.set(fraction);
+        self.decay_midpoint_multiplier.set(multiplier);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| DecayMidpointUpdated { sender, fraction, multiplier, seq });
+
+        Ok(())
+    }
+
+    pub fn decay_midpoint(&self) -> (U256, U256) {
+        (self.decay_midpoint_fraction.get(), self.decay_midpoint_multiplier.get())
+    }
+
+    /// Mirrors the decay curve so the multiplier grows from 50% at `start_time` to 100% at `end_time`.
+    pub fn update_growth_mode(&mut self, growth_mode: bool) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.growth_mode.set(growth_mode);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| GrowthModeUpdated { sender, growth_mode, seq });
+
+        Ok(())
+    }
+
+    pub fn growth_mode(&self) -> bool {
+        self.growth_mode.get()
+    }
+
+    /// Configures the sybil scorer queried by `accrue`; `Address::ZERO` disables scoring.
+    ///
+    /// `gas_cap` limits the scorer call (zero means no cap) and `fail_open` decides whether
+    /// users pass or fail the check while the scorer is unavailable.
+    pub fn update_sybil_scorer(&mut self, scorer: Address, threshold: U256, policy: u8, gas_cap: u64, fail_open: bool) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        if SybilPolicy::from_u8(policy).is_none() {
+            return Err(CommonError::InvalidSybilPolicy(InvalidSybilPolicy {}));
+        }
+
+        self.sybil_scorer.set(scorer);
+        self.sybil_threshold.set(threshold);
+        self.sybil_policy.set(U8::from(policy));
+        self.sybil_gas_cap.set(U64::from(gas_cap));
+        self.sybil_fail_open.set(fail_open);
+
+        self.emit(|seq| SybilScorerUpdated {
+            scorer,
+            threshold,
+            policy,
+            gas_cap,
+            fail_open,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the scorer, threshold, policy, gas cap and fail-open flag.
+    pub fn sybil_config(&self) -> (Address, U256, u8, u64, bool) {
+        (
+            self.sybil_scorer.get(),
+            self.sybil_threshold.get(),
+            self.sybil_policy.get().to::<u8>(),
+            self.sybil_gas_cap.get().to::<u64>(),
+            self.sybil_fail_open.get(),
+        )
+    }
+
+    /// Registers a campaign with its own bonus settings and window, returning its id.
+    ///
+    /// `multiply_factor` and `percentage_bonus` are in `denominator` units; zero for any of the three
+    /// falls back to the global setting.
+    ///
+    /// Owner only; operators create campaigns through `create_campaign_from_template`.
+    pub fn create_campaign(&mut self, multiply_factor: U256, percentage_bonus: U256, denominator: U256, start_time: U256, end_time: U256) -> Result<U256, CommonError> {
+        self.assert_owner()?;
+        self.open_campaign(multiply_factor, percentage_bonus, denominator, start_time, end_time)
+    }
+
+    /// Creates a draft copy of `source_id` running from `new_start` to `new_end` and returns its id.
+    ///
+    /// Bonus settings, budget, vesting and metadata are copied; the claim window keeps its offset from
+    /// the start. Campaign operators are not copied. Only the owner may clone a campaign with a budget.
+    pub fn clone_campaign(&mut self, source_id: U256, new_start: U256, new_end: U256) -> Result<U256, CommonError> {
+        self.assert_campaign_exists(source_id)?;
+
+        let source = self.campaigns.get(source_id);
+        let (multiply_factor, percentage_bonus, denominator) = (source.multiply_factor.get(), source.percentage_bonus.get(), source.denominator.get());
+        let shift = |time: U256| -> Result<U256, CommonError> {
+            if time == U256::ZERO {
+                return Ok(U256::ZERO);
+            }
+            checked_sub(checked_add(time, new_start)?, source.start_time.get())
+        };
+        let claim_start = shift(source.claim_start.get())?;
+        let claim_deadline = shift(source.claim_deadline.get())?;
+        let (budget, vesting_cliff, vesting_duration) = (source.budget.get(), source.vesting_cliff.get(), source.vesting_duration.get());
+        let (name, metadata_uri_hash) = (source.name.get(), source.metadata_uri_hash.get());
+        if budget > U256::ZERO {
+            self.assert_owner()?;
+        }
+
+        let campaign_id = self.open_campaign(multiply_factor, percentage_bonus, denominator, new_start, new_end)?;
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.budget.set(budget);
+        campaign.vesting_cliff.set(vesting_cliff);
+        campaign.vesting_duration.set(vesting_duration);
+        campaign.claim_start.set(claim_start);
+        campaign.claim_deadline.set(claim_deadline);
+        campaign.name.set(name);
+        campaign.metadata_uri_hash.set(metadata_uri_hash);
+
+        self.emit(|seq| CampaignCloned { source_id, campaign_id, seq });
+
+        Ok(campaign_id)
+    }
+
+    /// Sets how many `Draft` or `Active` campaigns each non-owner operator may have created at once.
+    pub fn update_max_operator_campaigns(&mut self, max_open_campaigns: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.max_operator_campaigns.set(max_open_campaigns);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| OperatorCampaignLimitUpdated { sender, max_open_campaigns, seq });
+
+        Ok(())
+    }
+
+    pub fn max_operator_campaigns(&self) -> U256 {
+        self.max_operator_campaigns.get()
+    }
+
+    /// Returns how many of the campaigns `operator` created are still `Draft` or `Active`.
+    pub fn open_campaigns_of(&self, operator: Address) -> U256 {
+        self.open_campaigns.get(operator)
+    }
+
+    /// Registers a named preset of campaign settings for `create_campaign_from_template` and returns its id.
+    ///
+    /// Settings are in `create_campaign` units; `max_duration` caps the window of campaigns made from it,
+    /// zero leaving it unbounded.
+    pub fn create_template(&mut self, name: B256, multiply_factor: U256, percentage_bonus: U256, denominator: U256, budget: U256, max_duration: U256) -> Result<U256, CommonError> {
+        self.assert_owner()?;
+        self.check_campaign_params(multiply_factor, percentage_bonus, denominator)?;
+
+        let template_id = self.template_count.get() + U256::from(1);
+        self.template_count.set(template_id);
+        let mut template = self.templates.setter(template_id);
+        template.exists.set(true);
+        template.name.set(name);
+        template.multiply_factor.set(multiply_factor);
+        template.percentage_bonus.set(percentage_bonus);
+        template.denominator.set(denominator);
+        template.budget.set(budget);
+        template.max_duration.set(max_duration);
+
+        self.emit(|seq| CampaignTemplateCreated { template_id, name, multiply_factor, percentage_bonus, denominator, budget, max_duration, seq });
+
+        Ok(template_id)
+    }
+
+    /// Returns `(name, multiply_factor, percentage_bonus, denominator, budget, max_duration)` of `template_id`.
+    pub fn template(&self, template_id: U256) -> Result<(B256, U256, U256, U256, U256, U256), CommonError> {
+        let template = self.templates.get(template_id);
+        if !template.exists.get() {
+            return Err(CommonError::TemplateNotFound(TemplateNotFound {}));
+        }
+        Ok((
+            template.name.get(),
+            template.multiply_factor.get(),
+            template.percentage_bonus.get(),
+            template.denominator.get(),
+            template.budget.get(),
+            template.max_duration.get(),
+        ))
+    }
+
+    pub fn template_count(&self) -> U256 {
+        self.template_count.get()
+    }
+
+    /// Creates a draft campaign from `template_id`'s settings, budget and name, running from `start_time` to `end_time`.
+    ///
+    /// Open to the owner and operators, with the operator cap of `open_campaign`; reverts with `InvalidTimeWindow` if the window
+    /// is longer than the template's `max_duration`.
+    pub fn create_campaign_from_template(&mut self, template_id: U256, start_time: U256, end_time: U256) -> Result<U256, CommonError> {
+        let (name, multiply_factor, percentage_bonus, denominator, budget, max_duration) = self.template(template_id)?;
+        if max_duration > U256::ZERO && end_time > start_time && end_time - start_time > max_duration {
+            return Err(CommonError::InvalidTimeWindow(InvalidTimeWindow {}));
+        }
+
+        let campaign_id = self.open_campaign(multiply_factor, percentage_bonus, denominator, start_time, end_time)?;
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.budget.set(budget);
+        campaign.name.set(name);
+
+        self.emit(|seq| CampaignCreatedFromTemplate { template_id, campaign_id, seq });
+
+        Ok(campaign_id)
+    }
+
+    pub fn campaign_count(&self) -> U256 {
+        self.campaign_count.get()
+    }
+
+    /// Lists up to `limit` campaigns, skipping the first `offset`, as
+    /// `(id, state, name, start_time, end_time, budget, spent)` in creation order.
+    ///
+    /// `limit` is capped at `MAX_CAMPAIGN_PAGE`; an `offset` past the end returns an empty page.
+    pub fn get_campaigns(&self, offset: U256, limit: U256) -> Vec<(U256, u8, B256, U256, U256, U256, U256)> {
+        let count = self.campaign_count.get();
+        if offset >= count {
+            return Vec::new();
+        }
+        let limit = limit.min(U256::from(MAX_CAMPAIGN_PAGE)).min(count - offset);
+
+        let mut page = Vec::with_capacity(limit.to::<usize>());
+        let mut campaign_id = offset + U256::from(1);
+        while campaign_id <= offset + limit {
+            let campaign = self.campaigns.get(campaign_id);
+            page.push((
+                campaign_id,
+                campaign.state.get().to::<u8>(),
+                campaign.name.get(),
+                campaign.start_time.get(),
+                campaign.end_time.get(),
+                campaign.budget.get(),
+                campaign.spent.get(),
+            ));
+            campaign_id += U256::from(1);
+        }
+        page
+    }
+
+    /// Returns the stored `(multiply_factor, percentage_bonus, denominator, start_time, end_time)` of `campaign_id`.
+    pub fn campaign(&self, campaign_id: U256) -> Result<(U256, U256, U256, U256, U256), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        let campaign = self.campaigns.get(campaign_id);
+        Ok((
+            campaign.multiply_factor.get(),
+            campaign.percentage_bonus.get(),
+            campaign.denominator.get(),
+            campaign.start_time.get(),
+            campaign.end_time.get(),
+        ))
+    }
+
+    /// Sets the strict-bonus factor of `campaign_id`; zero falls back to the global `multiply_factor`.
+    ///
+    /// Callable by the owner or an operator of `campaign_id`.
+    pub fn set_campaign_factor(&mut self, campaign_id: U256, multiply_factor: U256) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_campaign_admin(campaign_id)?;
+
+        if multiply_factor > self.max_multiply_factor.get() {
+            return Err(CommonError::InvalidMultiplyFactor(InvalidMultiplyFactor {}));
+        }
+
+        self.campaigns.setter(campaign_id).multiply_factor.set(multiply_factor);
+
+        self.emit(|seq| CampaignFactorUpdated {
+            campaign_id,
+            multiply_factor,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    /// Returns the strict-bonus factor in effect for `campaign_id`.
+    pub fn campaign_factor(&self, campaign_id: U256) -> U256 {
+        let factor = self.campaigns.get(campaign_id).multiply_factor.get();
+        if factor == U256::ZERO {
+            return self.multiply_factor.get();
+        }
+        factor
+    }
+
+    /// Sets the percentage bonus of `campaign_id` in its `denominator` units; zero falls back to the global bonus.
+    ///
+    /// Callable by the owner or an operator of `campaign_id`.
+    pub fn set_campaign_bonus(&mut self, campaign_id: U256, percentage_bonus: U256) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_campaign_admin(campaign_id)?;
+
+        let denominator = self.campaigns.get(campaign_id).denominator.get();
+        let denominator = if denominator == U256::ZERO { self.percentage_denominator.get() } else { denominator };
+        if percentage_bonus > denominator {
+            return Err(CommonError::InvalidDenominator(InvalidDenominator {}));
+        }
+        self.campaigns.setter(campaign_id).percentage_bonus.set(percentage_bonus);
+
+        self.emit(|seq| CampaignBonusUpdated { campaign_id, percentage_bonus, seq });
+
+        Ok(())
+    }
+
+    /// Derives the percentage bonus of `campaign_id` from `oracle`'s latest value at pricing time, as
+    /// `value * scale / 1e18` clamped to `[min_bonus, max_bonus]` in the campaign's `denominator` units.
+    ///
+    /// `Address::ZERO` goes back to the stored bonus. Pricing reverts with `OracleUnavailable` while the
+    /// oracle cannot be read.
+    pub fn set_campaign_bonus_oracle(&mut self, campaign_id: U256, oracle: Address, scale: U256, min_bonus: U256, max_bonus: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+
+        let denominator = self.campaigns.get(campaign_id).denominator.get();
+        let denominator = if denominator == U256::ZERO { self.percentage_denominator.get() } else { denominator };
+        if min_bonus > max_bonus || max_bonus > denominator {
+            return Err(CommonError::InvalidDenominator(InvalidDenominator {}));
+        }
+
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.bonus_oracle.set(oracle);
+        campaign.oracle_scale.set(scale);
+        campaign.oracle_min_bonus.set(min_bonus);
+        campaign.oracle_max_bonus.set(max_bonus);
+
+        self.emit(|seq| CampaignBonusOracleUpdated { campaign_id, oracle, scale, min_bonus, max_bonus, seq });
+
+        Ok(())
+    }
+
+    /// Returns the `(oracle, scale, min_bonus, max_bonus)` configured for `campaign_id`.
+    pub fn campaign_bonus_oracle(&self, campaign_id: U256) -> (Address, U256, U256, U256) {
+        let campaign = self.campaigns.get(campaign_id);
+        (campaign.bonus_oracle.get(), campaign.oracle_scale.get(), campaign.oracle_min_bonus.get(), campaign.oracle_max_bonus.get())
+    }
+
+    /// Grants or revokes `operator`'s rights over `campaign_id` only: tuning its bonuses and accruing in it.
+    pub fn set_campaign_operator(&mut self, campaign_id: U256, operator: Address, enabled: bool) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+
+        let index = self.ensure_member_index(operator);
+        self.campaigns.setter(campaign_id).operators.set_to(index, enabled);
+
+        self.emit(|seq| CampaignOperatorUpdated { campaign_id, operator, enabled, seq });
+
+        Ok(())
+    }
+
+    pub fn is_campaign_operator(&self, campaign_id: U256, account: Address) -> bool {
+        self.member_flag(&self.campaigns.get(campaign_id).operators, account)
+    }
+
+    /// Sets the display name of `campaign_id` and the hash of its off-chain description, so UIs can fetch
+    /// the description from IPFS and check it against the stored hash.
+    ///
+    /// Callable by the owner or an operator of `campaign_id`.
+    pub fn set_campaign_metadata(&mut self, campaign_id: U256, name: B256, metadata_uri_hash: B256) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_campaign_admin(campaign_id)?;
+
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.name.set(name);
+        campaign.metadata_uri_hash.set(metadata_uri_hash);
+
+        self.emit(|seq| CampaignMetadataUpdated { campaign_id, name, metadata_uri_hash, seq });
+
+        Ok(())
+    }
+
+    /// Returns the `(name, metadata_uri_hash)` of `campaign_id`.
+    pub fn campaign_metadata(&self, campaign_id: U256) -> Result<(B256, B256), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        let campaign = self.campaigns.get(campaign_id);
+        Ok((campaign.name.get(), campaign.metadata_uri_hash.get()))
+    }
+
+    /// Sets the most `accrue_for_campaign` may issue in `campaign_id`; it may not drop below what was already issued.
+    pub fn set_campaign_budget(&mut self, campaign_id: U256, budget: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+
+        if budget < self.campaigns.get(campaign_id).spent.get() {
+            return Err(CommonError::BudgetExceeded(BudgetExceeded {}));
+        }
+        if self.campaign_state_of(campaign_id) == CampaignState::Active {
+            self.assert_budget_funded(campaign_id, budget)?;
+        }
+        self.campaigns.setter(campaign_id).budget.set(budget);
+
+        self.emit(|seq| CampaignBudgetUpdated { campaign_id, budget, seq });
+
+        Ok(())
+    }
+
+    /// Returns `(budget, spent)` of `campaign_id`.
+    pub fn campaign_budget(&self, campaign_id: U256) -> Result<(U256, U256), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        let campaign = self.campaigns.get(campaign_id);
+        Ok((campaign.budget.get(), campaign.spent.get()))
+    }
+
+    pub fn remaining_budget(&self, campaign_id: U256) -> Result<U256, CommonError> {
+        let (budget, spent) = self.campaign_budget(campaign_id)?;
+        Ok(budget.saturating_sub(spent))
+    }
+
+    /// Moves a `Draft` campaign to `Active`.
+    ///
+    /// Token campaigns must be backed: reverts with `BudgetNotFunded` unless the pool, plus whatever of the
+    /// token the contract holds beyond its commitments and the creator (or owner) allows it to pull, covers
+    /// the unclaimed budget.
+    pub fn activate_campaign(&mut self, campaign_id: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_budget_funded(campaign_id, self.campaigns.get(campaign_id).budget.get())?;
+        self.transition_campaign(campaign_id, &[CampaignState::Draft], CampaignState::Active)?;
+
+        self.emit(|seq| CampaignActivated { campaign_id, seq });
+
+        Ok(())
+    }
+
+    /// Cancels a `Draft` or `Active` campaign.
+    pub fn cancel_campaign(&mut self, campaign_id: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.transition_campaign(campaign_id, &[CampaignState::Draft, CampaignState::Active], CampaignState::Cancelled)?;
+
+        self.emit(|seq| CampaignCancelled { campaign_id, seq });
+
+        Ok(())
+    }
+
+    /// Moves an `Active` campaign to `Ended` once its window is over.
+    pub fn finalize_campaign(&mut self, campaign_id: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+
+        let now = U256::from(self.vm().block_timestamp());
+        if !self.active_boundary_policy().has_ended(now, self.campaigns.get(campaign_id).end_time.get()) {
+            return Err(CommonError::CampaignNotEnded(CampaignNotEnded {}));
+        }
+        self.transition_campaign(campaign_id, &[CampaignState::Active], CampaignState::Ended)?;
+
+        self.emit(|seq| CampaignFinalized { campaign_id, seq });
+        self.emit_campaign_report(campaign_id)?;
+
+        Ok(())
+    }
+
+    /// Collapses an ended or cancelled campaign with nothing left to claim into its totals and a hash
+    /// of its settings, clearing the rest of its storage. Per-user grants are cleared by `prune_vesting`.
+    pub fn archive_campaign(&mut self, campaign_id: U256) -> Result<B256, CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+
+        let campaign = self.campaigns.get(campaign_id);
+        if campaign.archived.get() || !matches!(self.campaign_state_of(campaign_id), CampaignState::Ended | CampaignState::Cancelled) {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+        // Rewards credited to the ledger are owed by the ledger, so only grants can be outstanding.
+        let (spent, claimed, expired) = (campaign.spent.get(), campaign.claimed.get(), campaign.expired.get());
+        if checked_sub(checked_sub(spent, campaign.credited.get())?, claimed)? > expired {
+            return Err(CommonError::OutstandingRewards(OutstandingRewards {}));
+        }
+
+        let params_hash = self.campaign_params_hash(campaign_id);
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.multiply_factor.erase();
+        campaign.percentage_bonus.erase();
+        campaign.denominator.erase();
+        campaign.start_time.erase();
+        campaign.end_time.erase();
+        campaign.budget.erase();
+        campaign.name.erase();
+        campaign.metadata_uri_hash.erase();
+        campaign.vesting_cliff.erase();
+        campaign.vesting_duration.erase();
+        campaign.claim_start.erase();
+        campaign.claim_deadline.erase();
+        campaign.deadline_extended.erase();
+        campaign.paused.erase();
+        campaign.extensions.erase();
+        campaign.restricted.erase();
+        campaign.bonus_oracle.erase();
+        campaign.oracle_scale.erase();
+        campaign.oracle_min_bonus.erase();
+        campaign.oracle_max_bonus.erase();
+        campaign.referral_bps.erase();
+        campaign.merkle_root.erase();
+        campaign.archived.set(true);
+        campaign.params_hash.set(params_hash);
+
+        self.emit(|seq| CampaignArchived { campaign_id, params_hash, spent, claimed, expired, seq });
+
+        Ok(params_hash)
+    }
+
+    /// Returns `(archived, params_hash, spent, claimed, expired)` for `campaign_id`.
+    pub fn campaign_summary(&self, campaign_id: U256) -> Result<(bool, B256, U256, U256, U256), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        let campaign = self.campaigns.get(campaign_id);
+        Ok((campaign.archived.get(), campaign.params_hash.get(), campaign.spent.get(), campaign.claimed.get(), campaign.expired.get()))
+    }
+
+    /// Drops archived campaigns from `user`'s grants and clears their per-user records; callable by anyone.
+    pub fn prune_vesting(&mut self, user: Address) -> Result<U256, CommonError> {
+        let mut pruned = U256::ZERO;
+        let mut index = 0;
+        while index < self.vesting_campaigns.get(user).len() {
+            let campaign_id = self.vesting_campaigns.get(user).get(index).unwrap_or_default();
+            if !self.campaigns.get(campaign_id).archived.get() {
+                index += 1;
+                continue;
+            }
+
+            let mut campaign = self.campaigns.setter(campaign_id);
+            campaign.vesting_granted.delete(user);
+            campaign.vesting_released.delete(user);
+            let mut campaigns = self.vesting_campaigns.setter(user);
+            let last = campaigns.pop().unwrap_or_default();
+            if let Some(mut slot) = campaigns.setter(index) {
+                slot.set(last);
+            }
+            pruned += U256::from(1);
+        }
+        Ok(pruned)
+    }
+
+    /// Returns participant count, issued and claimed totals, unissued budget and state of `campaign_id` in one call.
+    ///
+    /// `participants` counts distinct users that were accrued a reward in the campaign.
+    pub fn get_campaign_stats(&self, campaign_id: U256) -> Result<CampaignStats, CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        let campaign = self.campaigns.get(campaign_id);
+        Ok(CampaignStats {
+            participants: campaign.participant_count.get(),
+            total_accrued: campaign.spent.get(),
+            total_claimed: campaign.claimed.get(),
+            remaining_budget: campaign.budget.get().saturating_sub(campaign.spent.get()),
+            state: campaign.state.get().to::<u8>(),
+        })
+    }
+
+    /// Moves the unissued budget and unreserved pool of `from_id` into `into_id` and cancels `from_id`
+    /// if it was still open, returning the budget moved.
+    ///
+    /// Rewards already issued stay with `from_id` and remain claimable there. Both campaigns must pay
+    /// in the same token.
+    pub fn merge_campaigns(&mut self, into_id: U256, from_id: U256) -> Result<U256, CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(into_id)?;
+        self.assert_campaign_exists(from_id)?;
+
+        let (into, from) = (self.campaigns.get(into_id), self.campaigns.get(from_id));
+        if into_id == from_id
+            || into.archived.get()
+            || from.archived.get()
+            || !matches!(self.campaign_state_of(into_id), CampaignState::Draft | CampaignState::Active)
+        {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+        if into.reward_token.get() != from.reward_token.get() {
+            return Err(CommonError::TokenMismatch(TokenMismatch {}));
+        }
+        let budget = from.budget.get().saturating_sub(from.spent.get());
+        let pool = self.unreserved_pool(from_id)?;
+
+        if matches!(self.campaign_state_of(from_id), CampaignState::Draft | CampaignState::Active) {
+            self.transition_campaign(from_id, &[CampaignState::Draft, CampaignState::Active], CampaignState::Cancelled)?;
+            self.emit(|seq| CampaignCancelled { campaign_id: from_id, seq });
+        }
+        self.move_budget(from_id, into_id, budget, pool)?;
+
+        Ok(budget)
+    }
+
+    /// Moves the unissued budget and unreserved pool of `from_id` into `to_id` once `from_id`'s claim
+    /// deadline has passed, returning the budget moved.
+    ///
+    /// Rewards still unclaimed in `from_id` are expired first as by `sweep_expired`, so their funds move
+    /// with the pool. Reverts with `ClaimWindowOpen` before the deadline or when `from_id` has none.
+    pub fn rollover_budget(&mut self, from_id: U256, to_id: U256) -> Result<U256, CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(from_id)?;
+        self.assert_campaign_exists(to_id)?;
+
+        let (from, to) = (self.campaigns.get(from_id), self.campaigns.get(to_id));
+        if from_id == to_id || to.archived.get() || !matches!(self.campaign_state_of(to_id), CampaignState::Draft | CampaignState::Active) {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+        if from.reward_token.get() != to.reward_token.get() {
+            return Err(CommonError::TokenMismatch(TokenMismatch {}));
+        }
+        let deadline = from.claim_deadline.get();
+        if deadline == U256::ZERO || !self.active_boundary_policy().has_ended(U256::from(self.vm().block_timestamp()), deadline) {
+            return Err(CommonError::ClaimWindowOpen(ClaimWindowOpen {}));
+        }
+        if !from.swept.get() {
+            self.sweep_expired(from_id)?;
+        }
+
+        let from = self.campaigns.get(from_id);
+        let budget = from.budget.get().saturating_sub(from.spent.get());
+        let pool = self.unreserved_pool(from_id)?;
+        self.move_budget(from_id, to_id, budget, pool)?;
+
+        self.emit(|seq| BudgetRolledOver { from_id, to_id, budget, pool, seq });
+
+        Ok(budget)
+    }
+
+    /// Carves `amount` of `campaign_id`'s unissued budget into a draft copy running from `new_start`
+    /// to `new_end`, returning its id.
+    ///
+    /// The copy is made as by `clone_campaign`; for token campaigns up to `amount` of the unreserved
+    /// pool moves along with the budget.
+    pub fn split_campaign(&mut self, campaign_id: U256, amount: U256, new_start: U256, new_end: U256) -> Result<U256, CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+
+        let campaign = self.campaigns.get(campaign_id);
+        if campaign.archived.get() {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+        if amount > campaign.budget.get().saturating_sub(campaign.spent.get()) {
+            return Err(CommonError::BudgetExceeded(BudgetExceeded {}));
+        }
+        let token = campaign.reward_token.get();
+        let pool = self.unreserved_pool(campaign_id)?.min(amount);
+
+        let new_id = self.clone_campaign(campaign_id, new_start, new_end)?;
+        let mut copy = self.campaigns.setter(new_id);
+        copy.budget.set(U256::ZERO);
+        copy.reward_token.set(token);
+        self.move_budget(campaign_id, new_id, amount, pool)?;
+
+        Ok(new_id)
+    }
+
+    /// Returns the storage layout version of `campaign_id`.
+    pub fn campaign_version(&self, campaign_id: U256) -> Result<u8, CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        Ok(self.campaign_version_of(campaign_id))
+    }
+
+    /// Upgrades a version 1 campaign in place to version 2.
+    ///
+    /// Version 2 is the first layout that records its version, so the step only stamps it; later
+    /// layout changes backfill their fields in the step that introduces them.
+    pub fn migrate_campaign_v1_to_v2(&mut self, campaign_id: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        self.assert_campaign_exists(campaign_id)?;
+        if self.campaign_version_of(campaign_id) != 1 {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+
+        self.campaigns.setter(campaign_id).version.set(U8::from(2));
+
+        self.emit(|seq| CampaignMigrated { campaign_id, from_version: 1, to_version: 2, seq });
+
+        Ok(())
+    }
+
+    pub fn campaign_state(&self, campaign_id: U256) -> Result<u8, CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        Ok(self.campaigns.get(campaign_id).state.get().to::<u8>())
+    }
+
+    /// Reward for `amount` in `campaign_id` at the current block timestamp, over the campaign's own window.
+    pub fn calculate_campaign_reward(&self, campaign_id: U256, amount: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.assert_campaign_active(campaign_id)?;
+        let bonus_params = self.campaign_bonus_params(campaign_id)?;
+        let campaign = self.campaigns.get(campaign_id);
+        self.quote_reward(RewardRequest {
+            amount,
+            current_time: U256::from(self.vm().block_timestamp()),
+            start_time: campaign.start_time.get(),
+            end_time: campaign.end_time.get(),
+            has_bonus,
+            has_strict_bonus,
+            extra_bonus: U256::ZERO,
+            bonus_params,
+            has_penalty: false,
+        })
+    }
+
+    /// Like `calculate_reward`, with the bonuses using `campaign_id`'s settings.
+    pub fn calculate_reward_for_campaign(&self, campaign_id: U256, amount: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.assert_campaign_active(campaign_id)?;
+        self.quote_reward(RewardRequest {
+            amount,
+            current_time: U256::from(self.vm().block_timestamp()),
+            start_time,
+            end_time,
+            has_bonus,
+            has_strict_bonus,
+            extra_bonus: U256::ZERO,
+            bonus_params: self.campaign_bonus_params(campaign_id)?,
+            has_penalty: false,
+        })
+    }
+
+    /// Schedules the end of the percentage and strict bonuses; zero keeps a bonus active indefinitely.
+    pub fn update_bonus_expiry(&mut self, bonus_valid_until: U256, strict_bonus_valid_until: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.bonus_valid_until.set(bonus_valid_until);
+        self.strict_bonus_valid_until.set(strict_bonus_valid_until);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| BonusExpiryUpdated {
+            sender,
+            bonus_valid_until,
+            strict_bonus_valid_until,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    pub fn bonus_expiry(&self) -> (U256, U256) {
+        (self.bonus_valid_until.get(), self.strict_bonus_valid_until.get())
+    }
+
+    /// Returns `(elapsed, remaining, current_multiplier, phase)` for the window at the current block,
+    /// with the multiplier in `percentage_denominator` units and `phase` a `WindowPhase`.
+    pub fn window_status(&self, start_time: U256, end_time: U256) -> Result<(U256, U256, U256, u8), CommonError> {
+        if end_time <= start_time {
+            return Err(CommonError::InvalidTimeWindow(InvalidTimeWindow {}));
+        }
+
+        let now = U256::from(self.vm().block_timestamp());
+        let policy = self.active_boundary_policy();
+        let phase = if !policy.has_started(now, start_time) {
+            WindowPhase::PreStart
+        } else if policy.has_ended(now, end_time) {
+            WindowPhase::Ended
+        } else {
+            WindowPhase::Active
+        };
+
+        let elapsed = now.saturating_sub(start_time).min(end_time - start_time);
+        let remaining = end_time.saturating_sub(now);
+        let multiplier = self.time_multiplier(now, start_time, end_time)?;
+
+        Ok((elapsed, remaining, multiplier, phase as u8))
+    }
+
+    /// Switches high-volume events to their packed `*Compact` variants to save log data.
+    pub fn update_compact_events(&mut self, compact_events: bool) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.compact_events.set(compact_events);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| CompactEventsUpdated { sender, compact_events, seq });
+
+        Ok(())
+    }
+
+    pub fn compact_events(&self) -> bool {
+        self.compact_events.get()
+    }
+
+    /// Sets the share of the amount taken off rewards flagged with `has_penalty`, in `percentage_denominator` units.
+    pub fn update_penalty(&mut self, penalty_bps: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.penalty_bps.set(penalty_bps);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| PenaltyUpdated { sender, penalty_bps, seq });
+
+        Ok(())
+    }
+
+    pub fn penalty_bps(&self) -> U256 {
+        self.penalty_bps.get()
+    }
+
+    /// Like `calculate_reward`, minus the early-exit penalty when `has_penalty` is set; never below zero.
+    pub fn calculate_reward_with_penalty(&self, amount: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool, has_penalty: bool) -> Result<U256, CommonError> {
+        self.quote_reward(RewardRequest {
+            amount,
+            current_time: U256::from(self.vm().block_timestamp()),
+            start_time,
+            end_time,
+            has_bonus,
+            has_strict_bonus,
+            extra_bonus: U256::ZERO,
+            bonus_params: self.global_bonus_params(),
+            has_penalty,
+        })
+    }
+
+    /// Opens a lucky-draw round committed to `keccak256(secret)`.
+    ///
+    /// Rewards accrued while the round is open are entered; after `reveal_lucky_draw` a
+    /// `win_chance` share of entrants (in `percentage_denominator` units) can settle an extra
+    /// `bonus_factor` of their round rewards.
+    pub fn commit_lucky_draw(&mut self, commitment: B256, bonus_factor: U256, win_chance: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        let current = self.draw_round.get();
+        if current > U256::ZERO && !self.lucky_draws.get(current).revealed.get() {
+            return Err(CommonError::DrawInProgress(DrawInProgress {}));
+        }
+
+        let round = current + U256::from(1);
+        self.draw_round.set(round);
+
+        let mut draw = self.lucky_draws.setter(round);
+        draw.commitment.set(commitment);
+        draw.entropy.set(commitment);
+        draw.bonus_factor.set(bonus_factor);
+        draw.win_chance.set(win_chance);
+
+        self.emit(|seq| LuckyDrawCommitted {
+            round,
+            commitment,
+            bonus_factor,
+            win_chance,
+            seq,
+        });
+
+        Ok(())
+    }
+
+    /// Closes the open round by revealing the committed secret; the seed mixes it with the entrants' entropy.
+    pub fn reveal_lucky_draw(&mut self, secret: B256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        let round = self.draw_round.get();
+        let draw = self.lucky_draws.get(round);
+        if round == U256::ZERO || draw.revealed.get() {
+            return Err(CommonError::DrawNotOpen(DrawNotOpen {}));
+        }
+        if keccak(secret) != draw.commitment.get() {
+            return Err(CommonError::InvalidReveal(InvalidReveal {}));
+        }
+
+        let seed = keccak([secret.as_slice(), draw.entropy.get().as_slice()].concat());
+
+        let mut draw = self.lucky_draws.setter(round);
+        draw.seed.set(seed);
+        draw.revealed.set(true);
+
+        self.emit(|seq| LuckyDrawRevealed { round, seed, seq });
+
+        Ok(())
+    }
+
+    /// Whether `user` entered the revealed `round` and was drawn.
+    pub fn is_lucky_winner(&self, round: U256, user: Address) -> bool {
+        let draw = self.lucky_draws.get(round);
+        if !draw.revealed.get() || draw.rewards.get(user) == U256::ZERO {
+            return false;
+        }
+
+        let roll = U256::from_be_bytes(keccak([draw.seed.get().as_slice(), user.as_slice()].concat()).0);
+        roll % self.percentage_denominator.get() < draw.win_chance.get()
+    }
+
+    /// Credits a winner's lucky bonus to their accrued balance; callable by anyone.
+    pub fn settle_lucky_draw(&mut self, round: U256, user: Address) -> Result<U256, CommonError> {
+        self.assert_not_shut_down()?;
+        self.assert_not_paused()?;
+
+        let draw = self.lucky_draws.get(round);
+        if !draw.revealed.get() {
+            return Err(CommonError::DrawNotRevealed(DrawNotRevealed {}));
+        }
+        if draw.settled.get(user) {
+            return Err(CommonError::DrawAlreadySettled(DrawAlreadySettled {}));
+        }
+        if !self.is_lucky_winner(round, user) {
+            return Err(CommonError::NotDrawWinner(NotDrawWinner {}));
+        }
+
+        let bonus = scale(draw.rewards.get(user), draw.bonus_factor.get(), self.percentage_denominator.get())?;
+        let total_calculated = checked_add(self.total_calculated.get(), bonus)?;
+
+        self.lucky_draws.setter(round).settled.setter(user).set(true);
+        self.total_calculated.set(total_calculated);
+        self.add_to_ledger(user, bonus)?;
+
+        self.emit(|seq| LuckyDrawSettled { round, user, bonus, seq });
+
+        Ok(bonus)
+    }
+
+    pub fn lucky_draw_round(&self) -> U256 {
+        self.draw_round.get()
+    }
+
+    /// Rewards `user` accrued while `round` was open.
+    pub fn lucky_draw_entry(&self, round: U256, user: Address) -> U256 {
+        self.lucky_draws.get(round).rewards.get(user)
+    }
+
+    /// Scales bonuses with the square root of the amount to dampen large positions.
+    ///
+    /// Bonuses are computed on `sqrt(amount * reference_amount)`, which equals `amount` at
+    /// `reference_amount`: smaller positions get relatively more, larger ones less. Zero disables.
+    pub fn update_sqrt_bonus_scaling(&mut self, reference_amount: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.sqrt_bonus_reference.set(reference_amount);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| SqrtBonusScalingUpdated { sender, reference_amount, seq });
+
+        Ok(())
+    }
+
+    pub fn sqrt_bonus_reference(&self) -> U256 {
+        self.sqrt_bonus_reference.get()
+    }
+
+    /// Sets the decimals of the amounts passed in and returned, e.g. 6 for USDC.
+    ///
+    /// Amounts are scaled to `INTERNAL_DECIMALS` before the reward math and back afterwards,
+    /// so amount-denominated settings such as `sqrt_bonus_reference` are in internal units.
+    pub fn set_reward_decimals(&mut self, decimals: u8) -> Result<(), CommonError> {
+        self.assert_owner()?;
+        if decimals > MAX_REWARD_DECIMALS {
+            return Err(CommonError::InvalidDecimals(InvalidDecimals {}));
+        }
+
+        self.reward_decimals.set(U8::from(decimals));
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| RewardDecimalsUpdated { sender, decimals, seq });
+
+        Ok(())
+    }
+
+    pub fn reward_decimals(&self) -> u8 {
+        self.reward_decimals.get().to::<u8>()
+    }
+
+    /// Scales `amount` from `reward_decimals` to `INTERNAL_DECIMALS`.
+    pub fn to_internal(&self, amount: U256) -> Result<U256, CommonError> {
+        let decimals = self.reward_decimals();
+        if decimals <= INTERNAL_DECIMALS {
+            checked_mul(amount, decimals_factor(INTERNAL_DECIMALS - decimals))
+        } else {
+            Ok(amount / decimals_factor(decimals - INTERNAL_DECIMALS))
+        }
+    }
+
+    /// Scales `amount` from `INTERNAL_DECIMALS` back to `reward_decimals`, rounding down.
+    pub fn from_internal(&self, amount: U256) -> U256 {
+        let decimals = self.reward_decimals();
+        if decimals <= INTERNAL_DECIMALS {
+            amount / decimals_factor(INTERNAL_DECIMALS - decimals)
+        } else {
+            amount.saturating_mul(decimals_factor(decimals - INTERNAL_DECIMALS))
+        }
+    }
+
+    /// Rewards below `threshold`, in `reward_decimals`, are paid as zero, or revert with
+    /// `ZeroValue` when `revert_on_dust` is set. Zero disables the threshold.
+    pub fn update_dust_threshold(&mut self, threshold: U256, revert_on_dust: bool) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.dust_threshold.set(threshold);
+        self.dust_reverts.set(revert_on_dust);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| DustThresholdUpdated { sender, threshold, revert_on_dust, seq });
+
+        Ok(())
+    }
+
+    pub fn dust_threshold(&self) -> (U256, bool) {
+        (self.dust_threshold.get(), self.dust_reverts.get())
+    }
+
+    /// Minimum time between two `has_bonus` boosts for the same user in `calculate_and_record`.
+    pub fn update_bonus_cooldown(&mut self, cooldown: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.bonus_cooldown.set(cooldown);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| BonusCooldownUpdated { sender, cooldown, seq });
+
+        Ok(())
+    }
+
+    pub fn bonus_cooldown(&self) -> U256 {
+        self.bonus_cooldown.get()
+    }
+
+    pub fn last_bonus_at(&self, user: Address) -> U256 {
+        self.last_bonus_at.get(user)
+    }
+
+    /// Prices a position for `user` at the current block timestamp and stamps the bonus cooldown.
+    ///
+    /// `has_bonus` only applies if `user` has not received it within `bonus_cooldown`; when it
+    /// does, the current timestamp is recorded as their last bonus.
+    pub fn calculate_and_record(&mut self, user: Address, amount: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.assert_operator()?;
+        self.assert_not_shut_down()?;
+        self.assert_not_paused()?;
+
+        let keep_bonuses = self.screen_user(user)?;
+        let current_time = U256::from(self.vm().block_timestamp());
+        let last_bonus_at = self.last_bonus_at.get(user);
+        let cooled_down = last_bonus_at == U256::ZERO || current_time >= last_bonus_at.saturating_add(self.bonus_cooldown.get());
+        let bonus_applied = has_bonus && keep_bonuses && cooled_down;
+
+        let reward = self.compute_reward(RewardRequest {
+            amount,
+            current_time,
+            start_time,
+            end_time,
+            has_bonus: bonus_applied,
+            has_strict_bonus: has_strict_bonus && keep_bonuses,
+            extra_bonus: U256::ZERO,
+            bonus_params: self.global_bonus_params(),
+            has_penalty: false,
+        })?;
+        if bonus_applied {
+            self.last_bonus_at.setter(user).set(current_time);
+        }
+
+        self.emit(|seq| RewardRecorded { campaign_id: U256::ZERO, user, reward, bonus_applied, seq });
+
+        Ok(reward)
+    }
+
+    /// Caps `bonus + strict_bonus` at `max_total_bonus_bps` of the amount, in basis points. Zero disables the cap.
+    pub fn update_max_total_bonus(&mut self, max_total_bonus_bps: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.max_total_bonus_bps.set(max_total_bonus_bps);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| MaxTotalBonusUpdated { sender, max_total_bonus_bps, seq });
+
+        Ok(())
+    }
+
+    pub fn max_total_bonus_bps(&self) -> U256 {
+        self.max_total_bonus_bps.get()
+    }
+
+    /// Halves the strict bonus factor every `interval` seconds from `genesis`. A zero interval disables halving.
+    pub fn update_halving_schedule(&mut self, genesis: U256, interval: U256) -> Result<(), CommonError> {
+        self.assert_owner()?;
+
+        self.halving_genesis.set(genesis);
+        self.halving_interval.set(interval);
+
+        let sender = self.vm().tx_origin();
+        self.emit(|seq| HalvingScheduleUpdated { sender, genesis, interval, seq });
+
+        Ok(())
+    }
+
+    pub fn halving_schedule(&self) -> (U256, U256) {
+        (self.halving_genesis.get(), self.halving_interval.get())
+    }
+
+    /// Halvings elapsed at the current block timestamp.
+    pub fn halving_epoch(&self) -> U256 {
+        let now = U256::from(self.vm().block_timestamp());
+        reward_math::halving_epoch(self.halving_genesis.get(), self.halving_interval.get(), now)
+    }
+
+    /// `multiply_factor` after the halvings elapsed at the current block timestamp.
+    pub fn effective_multiply_factor(&self) -> U256 {
+        self.halved_factor(self.multiply_factor.get(), U256::from(self.vm().block_timestamp()))
+    }
+
+    /// Snapshots the current bonus settings into a new epoch checkpoint and returns its id, starting at 1.
+    ///
+    /// Rewards priced with `calculate_reward_for_epoch` keep using the snapshot whatever the live settings become.
+    pub fn roll_epoch(&mut self) -> Result<U256, CommonError> {
+        self.assert_operator()?;
+
+        let params = self.global_bonus_params();
+        let epoch_id = self.epoch_checkpoint_count.get() + U256::from(1);
+        self.epoch_checkpoint_count.set(epoch_id);
+        let now = U256::from(self.vm().block_timestamp());
+        let mut checkpoint = self.epoch_checkpoints.setter(epoch_id);
+        checkpoint.percentage_bonus.set(params.percentage_bonus);
+        checkpoint.percentage_denominator.set(params.bonus_denominator);
+        checkpoint.multiply_factor.set(params.multiply_factor);
+        checkpoint.strict_bonus_denominator.set(params.strict_bonus_denominator);
+        checkpoint.taken_at.set(now);
+
+        self.emit(|seq| EpochRolled {
+            epoch_id,
+            percentage_bonus: params.percentage_bonus,
+            percentage_denominator: params.bonus_denominator,
+            multiply_factor: params.multiply_factor,
+            strict_bonus_denominator: params.strict_bonus_denominator,
+            seq,
+        });
+
+        Ok(epoch_id)
+    }
+
+    pub fn epoch_checkpoint_count(&self) -> U256 {
+        self.epoch_checkpoint_count.get()
+    }
+
+    /// Returns the `(percentage_bonus, percentage_denominator, multiply_factor, strict_bonus_denominator, taken_at)`
+    /// snapshotted for `epoch_id`.
+    pub fn epoch_checkpoint(&self, epoch_id: U256) -> Result<(U256, U256, U256, U256, U256), CommonError> {
+        let params = self.epoch_bonus_params(epoch_id)?;
+        Ok((
+            params.percentage_bonus,
+            params.bonus_denominator,
+            params.multiply_factor,
+            params.strict_bonus_denominator,
+            self.epoch_checkpoints.get(epoch_id).taken_at.get(),
+        ))
+    }
+
+    /// Like `calculate_reward`, with the bonuses using the settings snapshotted for `epoch_id`.
+    pub fn calculate_reward_for_epoch(&self, epoch_id: U256, amount: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        self.quote_reward(RewardRequest {
+            amount,
+            current_time: U256::from(self.vm().block_timestamp()),
+            start_time,
+            end_time,
+            has_bonus,
+            has_strict_bonus,
+            extra_bonus: U256::ZERO,
+            bonus_params: self.epoch_bonus_params(epoch_id)?,
+            has_penalty: false,
+        })
+    }
+
+    /// Returns the `(multiply_factor, percentage_bonus, percentage_denominator, strict_bonus_denominator)`
+    /// that `calculate_reward_at_time` prices with at `timestamp`.
+    pub fn get_params_at(&self, timestamp: U256) -> (U256, U256, U256, U256) {
+        let params = self.bonus_params_at(timestamp);
+        (params.multiply_factor, params.percentage_bonus, params.bonus_denominator, params.strict_bonus_denominator)
+    }
+
+    pub fn param_history_count(&self) -> U256 {
+        self.param_history_count.get()
+    }
+
+    /// Rewards credited to users so far by accruals and lucky draws, excluding compounding growth.
+    pub fn total_calculated(&self) -> U256 {
+        self.total_calculated.get()
+    }
+
+    /// Rewards paid out of the accrued balances so far.
+    pub fn total_claimed(&self) -> U256 {
+        self.total_claimed.get()
+    }
+
+    /// Returns the `(calculated, claimed)` reward totals of `campaign_id`.
+    pub fn campaign_totals(&self, campaign_id: U256) -> Result<(U256, U256), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        let campaign = self.campaigns.get(campaign_id);
+        Ok((campaign.spent.get(), campaign.claimed.get()))
+    }
+}
+
+impl RewardProcessor {
+    fn compute_reward(&self, request: RewardRequest) -> Result<U256, CommonError> {
+        Ok(self.compute_breakdown(request)?.total)
+    }
+
+    /// `compute_reward` for the `calculate_reward*` quotes, doubled when the lucky draw hits.
+    ///
+    /// The draw is predictable by the caller, so paths that credit or pay rewards price with
+    /// `compute_reward` and never see it.
+    fn quote_reward(&self, request: RewardRequest) -> Result<U256, CommonError> {
+        self.lucky_draw(self.compute_reward(request)?)
+    }
+
+    /// `compute_breakdown` with the lucky draw applied to `total`, as `quote_reward` does.
+    fn quote_breakdown(&self, request: RewardRequest) -> Result<RewardBreakdown, CommonError> {
+        let mut breakdown = self.compute_breakdown(request)?;
+        breakdown.total = self.lucky_draw(breakdown.total)?;
+        Ok(breakdown)
+    }
+
+    fn lucky_draw(&self, total: U256) -> Result<U256, CommonError> {
+        if self.get_pseudo_random() {
+            return checked_add(total, total);
+        }
+        Ok(total)
+    }
+
+    fn compute_breakdown(&self, mut request: RewardRequest) -> Result<RewardBreakdown, CommonError> {
+        if request.end_time <= request.start_time {
+            return Err(CommonError::InvalidTimeWindow(InvalidTimeWindow {}));
+        }
+        request.amount = self.to_internal(request.amount)?;
+
+        let denominator = self.percentage_denominator.get();
+        let (time_multiplier, decayed) = if self.wad_precision.get() {
+            let multiplier = self.time_multiplier_in(request.current_time, request.start_time, request.end_time, WAD)?;
+            (scale(multiplier, denominator, WAD)?, scale_rounded(request.amount, multiplier, WAD, self.active_rounding())?)
+        } else {
+            let multiplier = self.time_multiplier(request.current_time, request.start_time, request.end_time)?;
+            (multiplier, scale_rounded(request.amount, multiplier, denominator, self.active_rounding())?)
+        };
+
+        self.finish_breakdown(request, time_multiplier, decayed)
+    }
+
+    /// Adds the bonuses on top of an already decayed amount.
+    ///
+    /// Takes amounts in internal decimals and returns the breakdown in `reward_decimals`.
+    fn finish_breakdown(&self, request: RewardRequest, time_multiplier: U256, decayed: U256) -> Result<RewardBreakdown, CommonError> {
+        let RewardRequest { amount, current_time, start_time, has_bonus, has_strict_bonus, extra_bonus, bonus_params, has_penalty, .. } = request;
+
+        let denominator = self.percentage_denominator.get();
+        let rounding = self.active_rounding();
+        let (has_bonus, has_strict_bonus) = self.unexpired_bonus_flags(current_time, has_bonus, has_strict_bonus);
+
+        let bonus_base = self.bonus_base(amount)?;
+
+        let mut bonus = U256::ZERO;
+        if self.active_bonus_policy().pays_percentage_bonus(has_bonus, has_strict_bonus) {
+            bonus = scale_rounded(bonus_base, bonus_params.percentage_bonus, bonus_params.bonus_denominator, rounding)?;
+        }
+        let extra_bonus = checked_add(extra_bonus, self.early_bird_bonus_at(current_time, start_time))?;
+        if extra_bonus > U256::ZERO {
+            bonus = checked_add(bonus, scale_rounded(bonus_base, extra_bonus, denominator, rounding)?)?;
+        }
+
+        let mut strict_bonus = U256::ZERO;
+        if has_strict_bonus {
+            let multiply_factor = self.halved_factor(bonus_params.multiply_factor, current_time);
+            strict_bonus = scale_rounded(bonus_base, multiply_factor, bonus_params.strict_bonus_denominator, rounding)?;
+        }
+
+        let max_total_bonus_bps = self.max_total_bonus_bps.get();
+        if max_total_bonus_bps > U256::ZERO {
+            let cap = scale(amount, max_total_bonus_bps, U256::from(BPS_DENOMINATOR))?;
+            (bonus, strict_bonus) = reward_math::cap_bonuses(bonus, strict_bonus, cap);
+        }
+
+        let mut penalty = U256::ZERO;
+        if has_penalty {
+            penalty = scale_rounded(amount, self.penalty_bps.get(), denominator, rounding)?;
+        }
+
+        let total = checked_add(checked_add(decayed, bonus)?, strict_bonus)?.saturating_sub(penalty);
+        let total = self.apply_dust_threshold(self.from_internal(total))?;
+
+        Ok(RewardBreakdown {
+            base: self.from_internal(amount),
+            decayed: self.from_internal(decayed),
+            time_multiplier,
+            bonus: self.from_internal(bonus),
+            strict_bonus: self.from_internal(strict_bonus),
+            penalty: self.from_internal(penalty),
+            total,
+        })
+    }
+
+    /// Reward per unit of amount for a position opened now, in `percentage_denominator` units.
+    fn window_rate(&self, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<U256, CommonError> {
+        if end_time <= start_time {
+            return Err(CommonError::InvalidTimeWindow(InvalidTimeWindow {}));
+        }
+
+        let current_time = U256::from(self.vm().block_timestamp());
+        let (has_bonus, has_strict_bonus) = self.unexpired_bonus_flags(current_time, has_bonus, has_strict_bonus);
+        let denominator = self.percentage_denominator.get();
+        let mut bonus_rate = self.early_bird_bonus_at(current_time, start_time);
+        if self.active_bonus_policy().pays_percentage_bonus(has_bonus, has_strict_bonus) {
+            bonus_rate = checked_add(bonus_rate, self.percentage_bonus.get())?;
+        }
+        if has_strict_bonus {
+            let multiply_factor = self.halved_factor(self.multiply_factor.get(), current_time);
+            bonus_rate = checked_add(bonus_rate, scale(multiply_factor, denominator, self.strict_bonus_denominator.get())?)?;
+        }
+        let max_total_bonus_bps = self.max_total_bonus_bps.get();
+        if max_total_bonus_bps > U256::ZERO {
+            bonus_rate = bonus_rate.min(scale(max_total_bonus_bps, denominator, U256::from(BPS_DENOMINATOR))?);
+        }
+
+        checked_add(self.time_multiplier(current_time, start_time, end_time)?, bonus_rate)
+    }
+
+    fn streamed_multiplier(&self, duration: U256) -> Result<(U256, U256), CommonError> {
+        reward_math::streamed_multiplier(&self.decay_curve(), duration).ok_or(CommonError::MathOverflow(MathOverflow {}))
+    }
+
+    /// Decay from 100% at `start_time` to 50% at `end_time`, in `percentage_denominator` units.
+    fn time_multiplier(&self, current_time: U256, start_time: U256, end_time: U256) -> Result<U256, CommonError> {
+        self.time_multiplier_in(current_time, start_time, end_time, self.percentage_denominator.get())
+    }
+
+    /// Same curve as `time_multiplier`, with 100% represented by `max_multiplier`.
+    fn time_multiplier_in(&self, current_time: U256, start_time: U256, end_time: U256, max_multiplier: U256) -> Result<U256, CommonError> {
+        reward_math::time_multiplier(&self.decay_curve(), current_time, start_time, end_time, max_multiplier)
+            .ok_or(CommonError::MathOverflow(MathOverflow {}))
+    }
+
+    /// The configured decay settings as a `DecayCurve`.
+    fn decay_curve(&self) -> DecayCurve {
+        DecayCurve {
+            mode: self.active_decay_mode(),
+            rounding: self.active_rounding(),
+            denominator: self.percentage_denominator.get(),
+            step_interval: self.step_interval.get(),
+            midpoint_fraction: self.decay_midpoint_fraction.get(),
+            midpoint_multiplier: self.decay_midpoint_multiplier.get(),
+            grace_period: self.grace_period.get(),
+            growth: self.growth_mode.get(),
+        }
+    }
+
+    fn compound(&self, balance: U256, rate: U256, periods: U256) -> Result<U256, CommonError> {
+        reward_math::compound(balance, rate, periods, self.percentage_denominator.get()).ok_or(CommonError::MathOverflow(MathOverflow {}))
+    }
+
+    fn active_boundary_policy(&self) -> BoundaryPolicy {
+        BoundaryPolicy::from_u8(self.boundary_policy()).unwrap_or(BoundaryPolicy::InclusiveStartInclusiveEnd)
+    }
+
+    fn assert_token_approved(&self, token: Address) -> Result<(), CommonError> {
+        if !self.reward_tokens.get(token).approved.get() {
+            return Err(CommonError::TokenNotApproved(TokenNotApproved {}));
+        }
+        Ok(())
+    }
+
+    /// Calls an ERC20 transfer function on `token`, accepting an empty return for tokens that omit the `bool`.
+    fn call_token(&mut self, token: Address, calldata: Vec<u8>) -> Result<(), CommonError> {
+        let failed = || CommonError::TransferFailed(TransferFailed {});
+        let output = self.non_reentrant(|this| this.vm().call(&Call::new(), token, &calldata).map_err(|_| failed()))?;
+        if output.is_empty() {
+            return Ok(());
+        }
+        match IERC20::transferCall::abi_decode_returns(&output, true) {
+            Ok(returned) if returned._0 => Ok(()),
+            _ => Err(failed()),
+        }
+    }
+
+    /// Static-calls `target` with `call` and decodes the returned values.
+    fn static_call_sol<C: SolCall>(&self, target: Address, call: &C) -> Option<C::Return> {
+        self.static_call_sol_with_gas(target, call, u64::MAX)
+    }
+
+    fn static_call_sol_with_gas<C: SolCall>(&self, target: Address, call: &C, gas: u64) -> Option<C::Return> {
+        let output = self.vm().static_call(&Call::new().gas(gas), target, &call.abi_encode()).ok()?;
+        C::abi_decode_returns(&output, true).ok()
+    }
+
+    /// Logs the event built by `event` with the next value of `event_seq`.
+    fn emit<E: SolEvent>(&mut self, event: impl FnOnce(U256) -> E) {
+        let seq = self.event_seq.get() + U256::from(1);
+        self.event_seq.set(seq);
+        log(self.vm(), event(seq));
+    }
+
+    /// Emits `RewardAccruedCompact` in compact mode when both amounts fit in 128 bits,
+    /// `RewardAccrued` otherwise.
+    fn emit_reward_accrued(&mut self, campaign_id: U256, user: Address, reward: U256, balance: U256) {
+        match pack_u128_pair(reward, balance) {
+            Some(packed_amounts) if self.compact_events.get() => {
+                self.emit(|seq| RewardAccruedCompact { campaign_id, user, packed_amounts, seq });
+            }
+            _ => self.emit(|seq| RewardAccrued { campaign_id, user, reward, balance, seq }),
+        }
+    }
+
+    /// `emit_reward_accrued` for claims: packs `amount` and `donation` in compact mode.
+    fn emit_reward_claimed(&mut self, campaign_id: U256, user: Address, amount: U256, donation: U256) {
+        match pack_u128_pair(amount, donation) {
+            Some(packed_amounts) if self.compact_events.get() => {
+                self.emit(|seq| RewardClaimedCompact { campaign_id, user, packed_amounts, seq });
+            }
+            _ => self.emit(|seq| RewardClaimed { campaign_id, user, amount, donation, seq }),
+        }
+    }
+
+    fn active_decay_mode(&self) -> DecayMode {
+        DecayMode::from_u8(self.decay_mode()).unwrap_or(DecayMode::Linear)
+    }
+
+    fn active_rounding(&self) -> Rounding {
+        Rounding::from_u8(self.rounding_mode()).unwrap_or(Rounding::Floor)
+    }
+
+    fn consume_confirmations(&mut self, action: u8) -> Result<(), CommonError> {
+        if !self.is_action_confirmed(action) {
+            return Err(CommonError::ActionNotConfirmed(ActionNotConfirmed {}));
+        }
+
+        let key = U256::from(action);
+        self.owner_confirmations.setter(key).set(U256::ZERO);
+        self.guardian_confirmations.setter(key).set(U256::ZERO);
+
+        Ok(())
+    }
+
+    fn assert_operator(&self) -> Result<(), CommonError> {
+        let sender = self.vm().msg_sender();
+        if self.operators.get(sender) || (self.accept_aliased_callers.get() && self.operators.get(undo_l1_alias(sender))) {
+            return Ok(());
+        }
+        self.assert_owner()
+    }
+
+    fn assert_not_shut_down(&self) -> Result<(), CommonError> {
+        if self.is_shut_down() {
+            return Err(CommonError::ContractShutDown(ContractShutDown {}));
+        }
+        Ok(())
+    }
+
+    fn record_campaign_sponsorship(&mut self, campaign_id: U256, sponsor: Address, amount: U256) -> Result<(), CommonError> {
+        let campaign = self.campaigns.get(campaign_id);
+        let (sponsored, sponsored_total) = (checked_add(campaign.sponsored.get(sponsor), amount)?, checked_add(campaign.sponsored_total.get(), amount)?);
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.sponsored.setter(sponsor).set(sponsored);
+        campaign.sponsored_total.set(sponsored_total);
+        Ok(())
+    }
+
+    fn assert_refund_window_open(&self) -> Result<(), CommonError> {
+        if !self.is_shut_down() {
+            return Err(CommonError::NotShutDown(NotShutDown {}));
+        }
+        if U256::from(self.vm().block_timestamp()) >= self.refund_deadline.get() {
+            return Err(CommonError::RefundWindowClosed(RefundWindowClosed {}));
+        }
+        Ok(())
+    }
+
+    fn record_sponsor_deposit(&mut self, sponsor: Address, amount: U256) {
+        let deposited = self.sponsor_deposits.get(sponsor);
+        self.sponsor_deposits.setter(sponsor).set(deposited + amount);
+        self.total_sponsor_deposits.set(self.total_sponsor_deposits.get() + amount);
+
+        self.emit(|seq| SponsorFunded { sponsor, amount, seq });
+    }
+
+    /// Reads `account`'s flag in a bitmap keyed by member index; unregistered accounts have none set.
+    fn member_flag(&self, bitmap: &Bitmap, account: Address) -> bool {
+        let index = self.member_indices.get(account);
+        index != U256::ZERO && bitmap.get(index)
+    }
+
+    /// Indices start at 1 so that zero can mean "not registered".
+    fn ensure_member_index(&mut self, member: Address) -> U256 {
+        let existing = self.member_indices.get(member);
+        if existing != U256::ZERO {
+            return existing;
+        }
+
+        let index = self.member_count.get() + U256::from(1);
+        self.member_count.set(index);
+        self.member_indices.setter(member).set(index);
+
+        self.emit(|seq| MemberRegistered { member, index, seq });
+
+        index
+    }
+
+    fn active_bonus_policy(&self) -> BonusPolicy {
+        BonusPolicy::from_u8(self.bonus_policy()).unwrap_or(BonusPolicy::Additive)
+    }
+
+    /// `early_bird_bonus` when `current_time` is more than `early_bird_window` before `start_time`, zero otherwise.
+    fn early_bird_bonus_at(&self, current_time: U256, start_time: U256) -> U256 {
+        let window = self.early_bird_window.get();
+        if window > start_time || current_time >= start_time - window {
+            return U256::ZERO;
+        }
+        self.early_bird_bonus.get()
+    }
+
+    /// Like `assert_owner`, but matched against the direct caller instead of the transaction origin.
+    fn assert_owner_is_caller(&self) -> Result<(), CommonError> {
+        let sender = self.vm().msg_sender();
+        let owner = self.owner.get();
+        if sender != owner && !(self.accept_aliased_callers.get() && undo_l1_alias(sender) == owner) {
+            return Err(CommonError::Unauthorized(Unauthorized {}));
+        }
+        Ok(())
+    }
+
+    fn assert_owner_or_guardian(&self) -> Result<(), CommonError> {
+        let guardian = self.guardian.get();
+        if guardian != Address::ZERO && self.vm().msg_sender() == guardian {
+            return Ok(());
+        }
+        self.assert_owner()
+    }
+
+    fn assert_not_paused(&self) -> Result<(), CommonError> {
+        if self.is_paused() {
+            return Err(CommonError::ContractPaused(ContractPaused {}));
+        }
+        Ok(())
+    }
+
+    /// Checks `user` against the sybil scorer; returns whether bonuses should be kept.
+    fn screen_user(&self, user: Address) -> Result<bool, CommonError> {
+        let scorer = self.sybil_scorer.get();
+        if scorer == Address::ZERO {
+            return Ok(true);
+        }
+
+        let gas_cap = self.sybil_gas_cap.get().to::<u64>();
+        let gas = if gas_cap == 0 { u64::MAX } else { gas_cap };
+        let score = self.static_call_sol_with_gas(scorer, &ISybilScorer::scoreCall { account: user }, gas).map(|ret| ret._0);
+
+        let passed = match score {
+            Some(score) => score >= self.sybil_threshold.get(),
+            None if self.sybil_fail_open.get() => true,
+            None => false,
+        };
+        if passed {
+            return Ok(true);
+        }
+
+        match SybilPolicy::from_u8(self.sybil_policy.get().to::<u8>()).unwrap_or(SybilPolicy::Reject) {
+            SybilPolicy::StripBonuses => Ok(false),
+            SybilPolicy::Reject if score.is_none() => Err(CommonError::ScorerUnavailable(ScorerUnavailable {})),
+            SybilPolicy::Reject => Err(CommonError::SybilScoreTooLow(SybilScoreTooLow {})),
+        }
+    }
+
+    /// Drops the bonus flags whose `*_valid_until` lies before `current_time`; zero means no expiry.
+    fn unexpired_bonus_flags(&self, current_time: U256, has_bonus: bool, has_strict_bonus: bool) -> (bool, bool) {
+        let active = |valid_until: U256| valid_until == U256::ZERO || current_time <= valid_until;
+        (
+            has_bonus && active(self.bonus_valid_until.get()),
+            has_strict_bonus && active(self.strict_bonus_valid_until.get()),
+        )
+    }
+
+    /// Enters `reward` into the open lucky-draw round, if any, and folds the accrual into its entropy.
+    fn enter_lucky_draw(&mut self, user: Address, reward: U256) -> Result<(), CommonError> {
+        let round = self.draw_round.get();
+        if round == U256::ZERO || self.lucky_draws.get(round).revealed.get() {
+            return Ok(());
+        }
+
+        let draw = self.lucky_draws.get(round);
+        let entered = checked_add(draw.rewards.get(user), reward)?;
+        let timestamp = U256::from(self.vm().block_timestamp());
+        let entropy = keccak(
+            [
+                draw.entropy.get().as_slice(),
+                user.as_slice(),
+                &reward.to_be_bytes::<32>(),
+                &timestamp.to_be_bytes::<32>(),
+            ]
+            .concat(),
+        );
+
+        let mut draw = self.lucky_draws.setter(round);
+        draw.rewards.setter(user).set(entered);
+        draw.entropy.set(entropy);
+
+        Ok(())
+    }
+
+    /// Amount the bonuses are computed on: `amount` itself, or `sqrt(amount * sqrt_bonus_reference)`
+    /// when square-root scaling is enabled.
+    fn bonus_base(&self, amount: U256) -> Result<U256, CommonError> {
+        let reference = self.sqrt_bonus_reference.get();
+        if reference == U256::ZERO {
+            return Ok(amount);
+        }
+        Ok(isqrt(checked_mul(amount, reference)?))
+    }
+
+    /// Zeroes rewards below `dust_threshold`, or rejects them when `dust_reverts` is set.
+    fn apply_dust_threshold(&self, reward: U256) -> Result<U256, CommonError> {
+        if reward >= self.dust_threshold.get() {
+            return Ok(reward);
+        }
+        if self.dust_reverts.get() {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+        Ok(U256::ZERO)
+    }
+
+    /// `multiply_factor` after the halvings elapsed by `time`.
+    fn halved_factor(&self, multiply_factor: U256, time: U256) -> U256 {
+        reward_math::halved(multiply_factor, self.halving_genesis.get(), self.halving_interval.get(), time)
+    }
+
+    fn assert_campaign_exists(&self, campaign_id: U256) -> Result<(), CommonError> {
+        if !self.campaigns.get(campaign_id).exists.get() {
+            return Err(CommonError::CampaignNotFound(CampaignNotFound {}));
+        }
+        Ok(())
+    }
+
+    fn global_bonus_params(&self) -> BonusParams {
+        BonusParams {
+            percentage_bonus: self.percentage_bonus.get(),
+            bonus_denominator: self.percentage_denominator.get(),
+            multiply_factor: self.multiply_factor.get(),
+            strict_bonus_denominator: self.strict_bonus_denominator.get(),
+        }
+    }
+
+    /// `campaign_id`'s bonus settings, with unset fields taken from the global ones.
+    fn campaign_bonus_params(&self, campaign_id: U256) -> Result<BonusParams, CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        let campaign = self.campaigns.get(campaign_id);
+        let mut params = self.global_bonus_params();
+
+        let denominator = campaign.denominator.get();
+        if denominator > U256::ZERO {
+            params.bonus_denominator = denominator;
+            params.strict_bonus_denominator = denominator;
+        }
+        if campaign.percentage_bonus.get() > U256::ZERO {
+            params.percentage_bonus = campaign.percentage_bonus.get();
+        }
+        if campaign.bonus_oracle.get() != Address::ZERO {
+            params.percentage_bonus = self.oracle_bonus(campaign_id)?;
+        }
+        params.multiply_factor = self.campaign_factor(campaign_id);
+
+        Ok(params)
+    }
+
+    fn campaign_state_of(&self, campaign_id: U256) -> CampaignState {
+        CampaignState::from_u8(self.campaigns.get(campaign_id).state.get().to::<u8>()).unwrap_or(CampaignState::Draft)
+    }
+
+    /// Passes for an active campaign that is not paused.
+    fn assert_campaign_active(&self, campaign_id: U256) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        if self.campaign_state_of(campaign_id) != CampaignState::Active {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+        if self.campaigns.get(campaign_id).paused.get() {
+            return Err(CommonError::CampaignPaused(CampaignPaused {}));
+        }
+        Ok(())
+    }
+
+    /// Passes for global operators, the owner and operators of `campaign_id`.
+    fn assert_campaign_operator(&self, campaign_id: U256) -> Result<(), CommonError> {
+        if self.sender_operates_campaign(campaign_id) {
+            return Ok(());
+        }
+        self.assert_operator()
+    }
+
+    /// Moves `campaign_id` to `to` if it is currently in one of `from`.
+    fn transition_campaign(&mut self, campaign_id: U256, from: &[CampaignState], to: CampaignState) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        if !from.contains(&self.campaign_state_of(campaign_id)) {
+            return Err(CommonError::InvalidCampaignState(InvalidCampaignState {}));
+        }
+        self.campaigns.setter(campaign_id).state.set(U8::from(to as u8));
+
+        let creator = self.campaigns.get(campaign_id).creator.get();
+        if creator != Address::ZERO && matches!(to, CampaignState::Ended | CampaignState::Cancelled) {
+            let open_campaigns = self.open_campaigns.get(creator).saturating_sub(U256::from(1));
+            self.open_campaigns.setter(creator).set(open_campaigns);
+        }
+        Ok(())
+    }
+
+    /// Adds `reward`, of which `shares` are bonuses, to `user`'s compounded balance, enters it in the lucky draw and logs it.
+    fn credit_reward(&mut self, campaign_id: U256, user: Address, reward: U256, shares: BonusShares) -> Result<(), CommonError> {
+        let total_calculated = checked_add(self.total_calculated.get(), reward)?;
+        let ledger = self.ledgers.get(user);
+        let bonus = checked_add(ledger.bonus.get(), shares.bonus)?;
+        let strict_bonus = checked_add(ledger.strict_bonus.get(), shares.strict_bonus)?;
+
+        self.total_calculated.set(total_calculated);
+        let mut ledger = self.ledgers.setter(user);
+        ledger.bonus.set(bonus);
+        ledger.strict_bonus.set(strict_bonus);
+        let balance = self.add_to_ledger(user, reward)?;
+        self.enter_lucky_draw(user, reward)?;
+
+        self.emit_reward_accrued(campaign_id, user, reward, balance);
+
+        if campaign_id != U256::ZERO {
+            let credited = checked_add(self.campaigns.get(campaign_id).credited.get(), reward)?;
+            self.campaigns.setter(campaign_id).credited.set(credited);
+        }
+
+        Ok(())
+    }
+
+    fn sender_operates_campaign(&self, campaign_id: U256) -> bool {
+        let sender = self.vm().msg_sender();
+        let operators = &self.campaigns.get(campaign_id).operators;
+        self.member_flag(operators, sender) || (self.accept_aliased_callers.get() && self.member_flag(operators, undo_l1_alias(sender)))
+    }
+
+    /// Passes for the owner and for operators of `campaign_id`.
+    fn assert_campaign_admin(&self, campaign_id: U256) -> Result<(), CommonError> {
+        if self.sender_operates_campaign(campaign_id) {
+            return Ok(());
+        }
+        self.assert_owner()
+    }
+
+    fn epoch_bonus_params(&self, epoch_id: U256) -> Result<BonusParams, CommonError> {
+        if epoch_id == U256::ZERO || epoch_id > self.epoch_checkpoint_count.get() {
+            return Err(CommonError::EpochNotFound(EpochNotFound {}));
+        }
+        let checkpoint = self.epoch_checkpoints.get(epoch_id);
+        Ok(BonusParams {
+            percentage_bonus: checkpoint.percentage_bonus.get(),
+            bonus_denominator: checkpoint.percentage_denominator.get(),
+            multiply_factor: checkpoint.multiply_factor.get(),
+            strict_bonus_denominator: checkpoint.strict_bonus_denominator.get(),
+        })
+    }
+
+    /// Appends the current bonus settings to the history, replacing the entry already written this block.
+    fn record_bonus_params(&mut self) {
+        let params = self.global_bonus_params();
+        let now = U256::from(self.vm().block_timestamp());
+        let count = self.param_history_count.get();
+        let index = if count > U256::ZERO && self.param_history.get(count).taken_at.get() == now {
+            count
+        } else {
+            count + U256::from(1)
+        };
+        self.param_history_count.set(index);
+
+        let mut record = self.param_history.setter(index);
+        record.percentage_bonus.set(params.percentage_bonus);
+        record.percentage_denominator.set(params.bonus_denominator);
+        record.multiply_factor.set(params.multiply_factor);
+        record.strict_bonus_denominator.set(params.strict_bonus_denominator);
+        record.taken_at.set(now);
+    }
+
+    /// The bonus settings in effect at `timestamp`: the last history entry written at or before it.
+    ///
+    /// Times before the first entry resolve to the deployment settings.
+    fn bonus_params_at(&self, timestamp: U256) -> BonusParams {
+        let count = self.param_history_count.get();
+        if count == U256::ZERO {
+            return self.global_bonus_params();
+        }
+
+        let (mut low, mut high) = (U256::from(1), count);
+        while low < high {
+            let mid = (low + high + U256::from(1)) >> 1;
+            if self.param_history.get(mid).taken_at.get() <= timestamp {
+                low = mid;
+            } else {
+                high = mid - U256::from(1);
+            }
+        }
+
+        let record = self.param_history.get(low);
+        BonusParams {
+            percentage_bonus: record.percentage_bonus.get(),
+            bonus_denominator: record.percentage_denominator.get(),
+            multiply_factor: record.multiply_factor.get(),
+            strict_bonus_denominator: record.strict_bonus_denominator.get(),
+        }
+    }
+
+    /// Realizes `user`'s compounding growth, adds `amount` to their ledger and returns the new balance.
+    fn add_to_ledger(&mut self, user: Address, amount: U256) -> Result<U256, CommonError> {
+        let ledger = self.ledgers.get(user);
+        let outstanding = checked_sub(ledger.accrued.get(), ledger.claimed.get())?;
+        let balance = self.compounded_balance_of(user)?;
+        let accrued = checked_add(checked_add(ledger.accrued.get(), balance - outstanding)?, amount)?;
+        let epoch = self.current_epoch();
+        let now = U256::from(self.vm().block_timestamp());
+
+        let mut ledger = self.ledgers.setter(user);
+        ledger.accrued.set(accrued);
+        ledger.epoch.set(epoch);
+        ledger.last_update.set(now);
+
+        Ok(balance + amount)
+    }
+
+    /// Sends `amount` claimed by `user` to them, minus the share they donate, and emits the claim.
+    fn pay_out(&mut self, campaign_id: U256, user: Address, amount: U256) -> Result<(), CommonError> {
+        let donation = self.send_native(campaign_id, user, amount)?;
+
+        self.emit_reward_claimed(campaign_id, user, amount, donation);
+
+        Ok(())
+    }
+
+    /// Sends `amount` to `user` as `dispatch_native` describes, or queues it for `execute_withdrawal` while
+    /// a withdrawal delay is set, in which case nothing is donated yet.
+    fn send_native(&mut self, campaign_id: U256, user: Address, amount: U256) -> Result<U256, CommonError> {
+        if self.withdrawal_delay.get() > U256::ZERO {
+            self.queue_withdrawal(campaign_id, user, amount)?;
+            return Ok(U256::ZERO);
+        }
+        let router = self.vm().msg_sender();
+        self.dispatch_native(campaign_id, router, user, amount)
+    }
+
+    /// Sends `amount` to `user` minus the claim fee and the share they donate, which goes to their beneficiary,
+    /// and returns that share. The whole `amount` is paid from the treasury as far as it reaches.
+    fn dispatch_native(&mut self, campaign_id: U256, router: Address, user: Address, amount: U256) -> Result<U256, CommonError> {
+        self.debit_treasury(amount);
+        let fee = self.collect_fee(campaign_id, router, user, Address::ZERO, amount)?;
+        let (donation, payout) = self.donation_split(user, amount - fee)?;
+        if donation > U256::ZERO {
+            let beneficiary = self.donation_targets.get(user);
+            let donated = checked_add(self.donated_totals.get(beneficiary), donation)?;
+            self.donated_totals.setter(beneficiary).set(donated);
+            self.transfer_native(beneficiary, donation)?;
+
+            self.emit(|seq| DonationRouted { campaign_id, user, beneficiary, amount: donation, seq });
+        }
+        if payout > U256::ZERO {
+            self.deliver_native(user, payout)?;
+        }
+        Ok(donation)
+    }
+
+    fn grant_vesting(&mut self, campaign_id: U256, user: Address, reward: U256, shares: BonusShares) -> Result<(), CommonError> {
+        let campaign = self.campaigns.get(campaign_id);
+        let granted = campaign.vesting_granted.get(user);
+        let bonus = checked_add(campaign.granted_bonus.get(user), shares.bonus)?;
+        let strict_bonus = checked_add(campaign.granted_strict_bonus.get(user), shares.strict_bonus)?;
+        let total_calculated = checked_add(self.total_calculated.get(), reward)?;
+
+        if granted == U256::ZERO {
+            self.vesting_campaigns.setter(user).push(campaign_id);
+        }
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.vesting_granted.setter(user).set(checked_add(granted, reward)?);
+        campaign.granted_bonus.setter(user).set(bonus);
+        campaign.granted_strict_bonus.setter(user).set(strict_bonus);
+        self.total_calculated.set(total_calculated);
+        self.enter_lucky_draw(user, reward)?;
+
+        self.emit(|seq| VestingGranted { campaign_id, user, amount: reward, seq });
+
+        Ok(())
+    }
+
+    /// Unlocked and unreleased part of `user`'s grant in `campaign_id`; nothing once the grants expired.
+    fn releasable(&self, campaign_id: U256, user: Address) -> Result<U256, CommonError> {
+        let campaign = self.campaigns.get(campaign_id);
+        if campaign.swept.get() || campaign.archived.get() {
+            return Ok(U256::ZERO);
+        }
+        let unlocked = reward_math::vested(
+            campaign.vesting_granted.get(user),
+            campaign.start_time.get(),
+            campaign.vesting_cliff.get(),
+            campaign.vesting_duration.get(),
+            U256::from(self.vm().block_timestamp()),
+        )
+        .ok_or(CommonError::MathOverflow(MathOverflow {}))?;
+        Ok(unlocked.saturating_sub(campaign.vesting_released.get(user)))
+    }
+
+    /// Whether `campaign_id`'s rewards are held as grants for `claim_vested` rather than credited to the ledger.
+    fn has_grants(&self, campaign_id: U256) -> bool {
+        let campaign = self.campaigns.get(campaign_id);
+        campaign.reward_token.get() != Address::ZERO
+            || campaign.vesting_cliff.get() > U256::ZERO
+            || campaign.vesting_duration.get() > U256::ZERO
+            || campaign.claim_start.get() > U256::ZERO
+            || campaign.claim_deadline.get() > U256::ZERO
+    }
+
+    /// Whether the current block is inside `campaign_id`'s `[start_time, end_time]` under the boundary policy.
+    fn campaign_window_open(&self, campaign_id: U256) -> bool {
+        let campaign = self.campaigns.get(campaign_id);
+        let now = U256::from(self.vm().block_timestamp());
+        let policy = self.active_boundary_policy();
+        policy.has_started(now, campaign.start_time.get()) && !policy.has_ended(now, campaign.end_time.get())
+    }
+
+    fn claim_window_open(&self, campaign_id: U256) -> bool {
+        let campaign = self.campaigns.get(campaign_id);
+        let now = U256::from(self.vm().block_timestamp());
+        let deadline = campaign.claim_deadline.get();
+        let policy = self.active_boundary_policy();
+        policy.has_started(now, campaign.claim_start.get()) && (deadline == U256::ZERO || !policy.has_ended(now, deadline))
+    }
+
+    fn set_campaign_paused(&mut self, campaign_id: U256, paused: bool) -> Result<(), CommonError> {
+        self.assert_campaign_exists(campaign_id)?;
+        self.assert_campaign_operator(campaign_id)?;
+
+        self.campaigns.setter(campaign_id).paused.set(paused);
+
+        self.emit(|seq| CampaignPauseUpdated { campaign_id, paused, seq });
+
+        Ok(())
+    }
+
+    /// Hash of the settings that priced `campaign_id`'s rewards, kept once it is archived.
+    fn campaign_params_hash(&self, campaign_id: U256) -> B256 {
+        let campaign = self.campaigns.get(campaign_id);
+        let words = [
+            campaign.multiply_factor.get(),
+            campaign.percentage_bonus.get(),
+            campaign.denominator.get(),
+            campaign.start_time.get(),
+            campaign.end_time.get(),
+            campaign.budget.get(),
+            campaign.vesting_cliff.get(),
+            campaign.vesting_duration.get(),
+            campaign.claim_start.get(),
+            campaign.claim_deadline.get(),
+        ];
+        let mut data = Vec::with_capacity(32 * (words.len() + 2));
+        for word in words {
+            data.extend_from_slice(&word.to_be_bytes::<32>());
+        }
+        data.extend_from_slice(campaign.name.get().as_slice());
+        data.extend_from_slice(campaign.metadata_uri_hash.get().as_slice());
+        keccak(data)
+    }
+
+    /// Shared body of `create_campaign` and the operator-facing entry points.
+    ///
+    /// Operators other than the owner may keep at most `max_operator_campaigns` of their campaigns
+    /// in `Draft` or `Active` at once, and none while the cap is zero.
+    fn open_campaign(&mut self, multiply_factor: U256, percentage_bonus: U256, denominator: U256, start_time: U256, end_time: U256) -> Result<U256, CommonError> {
+        let creator = if self.assert_owner().is_ok() {
+            Address::ZERO
+        } else {
+            self.assert_operator()?;
+            self.vm().msg_sender()
+        };
+
+        self.check_campaign_params(multiply_factor, percentage_bonus, denominator)?;
+        if end_time <= start_time {
+            return Err(CommonError::InvalidTimeWindow(InvalidTimeWindow {}));
+        }
+
+        if creator != Address::ZERO {
+            let open_campaigns = self.open_campaigns.get(creator) + U256::from(1);
+            if open_campaigns > self.max_operator_campaigns.get() {
+                return Err(CommonError::CampaignLimitReached(CampaignLimitReached {}));
+            }
+            self.open_campaigns.setter(creator).set(open_campaigns);
+        }
+
+        let campaign_id = self.campaign_count.get() + U256::from(1);
+        self.campaign_count.set(campaign_id);
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.exists.set(true);
+        campaign.creator.set(creator);
+        campaign.version.set(U8::from(CAMPAIGN_VERSION));
+        campaign.multiply_factor.set(multiply_factor);
+        campaign.percentage_bonus.set(percentage_bonus);
+        campaign.denominator.set(denominator);
+        campaign.start_time.set(start_time);
+        campaign.end_time.set(end_time);
+
+        self.emit(|seq| CampaignCreated { campaign_id, multiply_factor, percentage_bonus, denominator, start_time, end_time, seq });
+
+        Ok(campaign_id)
+    }
+
+    /// Rejects campaign settings `create_campaign` would not accept.
+    fn check_campaign_params(&self, multiply_factor: U256, percentage_bonus: U256, denominator: U256) -> Result<(), CommonError> {
+        if multiply_factor > self.max_multiply_factor.get() {
+            return Err(CommonError::InvalidMultiplyFactor(InvalidMultiplyFactor {}));
+        }
+        if denominator > U256::ZERO && denominator < percentage_bonus {
+            return Err(CommonError::InvalidDenominator(InvalidDenominator {}));
+        }
+        Ok(())
+    }
+
+    fn campaign_version_of(&self, campaign_id: U256) -> u8 {
+        self.campaigns.get(campaign_id).version.get().to::<u8>().max(1)
+    }
+
+    /// Part of `campaign_id`'s pool not needed for its issued and unclaimed rewards.
+    fn unreserved_pool(&self, campaign_id: U256) -> Result<U256, CommonError> {
+        let campaign = self.campaigns.get(campaign_id);
+        let outstanding = checked_sub(checked_sub(campaign.spent.get(), campaign.claimed.get())?, campaign.expired.get())?;
+        Ok(campaign.pool.get().saturating_sub(outstanding))
+    }
+
+    fn move_budget(&mut self, from_id: U256, to_id: U256, budget: U256, pool: U256) -> Result<(), CommonError> {
+        let (from, to) = (self.campaigns.get(from_id), self.campaigns.get(to_id));
+        let (from_budget, from_pool) = (checked_sub(from.budget.get(), budget)?, checked_sub(from.pool.get(), pool)?);
+        let (to_budget, to_pool) = (checked_add(to.budget.get(), budget)?, checked_add(to.pool.get(), pool)?);
+
+        let mut from = self.campaigns.setter(from_id);
+        from.budget.set(from_budget);
+        from.pool.set(from_pool);
+        let mut to = self.campaigns.setter(to_id);
+        to.budget.set(to_budget);
+        to.pool.set(to_pool);
+        if pool > U256::ZERO {
+            let funder = match self.campaigns.get(to_id).creator.get() {
+                Address::ZERO => self.owner.get(),
+                creator => creator,
+            };
+            self.record_campaign_sponsorship(to_id, funder, pool)?;
+        }
+
+        self.emit(|seq| CampaignReconciled { from_id, to_id, budget, pool, seq });
+
+        Ok(())
+    }
+
+    /// `campaign_id`'s percentage bonus as read from its oracle, scaled and clamped.
+    fn oracle_bonus(&self, campaign_id: U256) -> Result<U256, CommonError> {
+        let campaign = self.campaigns.get(campaign_id);
+        let value = self
+            .static_call_sol(campaign.bonus_oracle.get(), &IValueOracle::latestValueCall {})
+            .ok_or(CommonError::OracleUnavailable(OracleUnavailable {}))?
+            ._0;
+        let bonus = mul_div(value, campaign.oracle_scale.get(), WAD).ok_or(CommonError::MathOverflow(MathOverflow {}))?;
+        Ok(bonus.clamp(campaign.oracle_min_bonus.get(), campaign.oracle_max_bonus.get()))
+    }
+
+    /// Grants `reward` in `campaign_id` for `claim_vested` or credits it to `user`'s ledger, whichever the campaign uses.
+    fn issue_campaign_reward(&mut self, campaign_id: U256, user: Address, reward: U256, shares: BonusShares) -> Result<(), CommonError> {
+        if self.has_grants(campaign_id) {
+            return self.grant_vesting(campaign_id, user, reward, shares);
+        }
+        self.credit_reward(campaign_id, user, reward, shares)
+    }
+
+    /// Realizes `user`'s compounding growth, books `amount` of their balance as claimed and returns the
+    /// bonus shares it carries, pro rata to the bonuses still in the balance.
+    fn settle_ledger_claim(&mut self, user: Address, amount: U256) -> Result<BonusShares, CommonError> {
+        let balance = self.add_to_ledger(user, U256::ZERO)?;
+        let ledger = self.ledgers.get(user);
+        let (bonus, strict_bonus) = (ledger.bonus.get(), ledger.strict_bonus.get());
+        let shares = BonusShares { bonus: scale(bonus, amount, balance)?, strict_bonus: scale(strict_bonus, amount, balance)? };
+        let claimed = checked_add(ledger.claimed.get(), amount)?;
+        let total_claimed = checked_add(self.total_claimed.get(), amount)?;
+        let mut ledger = self.ledgers.setter(user);
+        ledger.claimed.set(claimed);
+        ledger.bonus.set(bonus - shares.bonus);
+        ledger.strict_bonus.set(strict_bonus - shares.strict_bonus);
+        self.total_claimed.set(total_claimed);
+        Ok(shares)
+    }
+
+    /// Bonus shares of `amount` released from `user`'s grant in `campaign_id` on top of what they already
+    /// released, pro rata to the bonuses granted.
+    fn released_bonus_shares(&self, campaign_id: U256, user: Address, amount: U256) -> Result<BonusShares, CommonError> {
+        let campaign = self.campaigns.get(campaign_id);
+        let granted = campaign.vesting_granted.get(user);
+        let released = campaign.vesting_released.get(user);
+        let share = |component: U256| -> Result<U256, CommonError> {
+            Ok(scale(component, checked_add(released, amount)?, granted)?.saturating_sub(scale(component, released, granted)?))
+        };
+        Ok(BonusShares { bonus: share(campaign.granted_bonus.get(user))?, strict_bonus: share(campaign.granted_strict_bonus.get(user))? })
+    }
+
+    /// Emits a `ClaimReceipt` splitting `total` paid to `user` into base, bonus and strict bonus.
+    fn emit_claim_receipt(&mut self, campaign_id: U256, user: Address, total: U256, shares: BonusShares) {
+        let bonus = shares.bonus.min(total);
+        let strict_bonus = shares.strict_bonus.min(total - bonus);
+        let base = total - bonus - strict_bonus;
+
+        self.emit(|seq| ClaimReceipt { user, campaign_id, base, bonus, strict_bonus, total, seq });
+    }
+
+    /// Prices `amount` for `user` with `campaign_id`'s settings and window at the current time.
+    fn price_campaign_reward(&self, campaign_id: U256, user: Address, amount: U256, has_bonus: bool, has_strict_bonus: bool) -> Result<RewardBreakdown, CommonError> {
+        let keep_bonuses = self.screen_user(user)?;
+        let bonus_params = self.campaign_bonus_params(campaign_id)?;
+        let campaign = self.campaigns.get(campaign_id);
+        self.compute_breakdown(RewardRequest {
+            amount,
+            current_time: U256::from(self.vm().block_timestamp()),
+            start_time: campaign.start_time.get(),
+            end_time: campaign.end_time.get(),
+            has_bonus: has_bonus && keep_bonuses,
+            has_strict_bonus: has_strict_bonus && keep_bonuses,
+            extra_bonus: if keep_bonuses { self.streak_bonus_of(user) } else { U256::ZERO },
+            bonus_params,
+            has_penalty: false,
+        })
+    }
+
+    /// Checks via `balanceOf`/`allowance` that `budget` of a token campaign, less what was already claimed or
+    /// expired, is covered by its pool, the contract's uncommitted balance of the token and the allowance of
+    /// its creator, or of the owner for campaigns the owner created.
+    fn assert_budget_funded(&self, campaign_id: U256, budget: U256) -> Result<(), CommonError> {
+        let campaign = self.campaigns.get(campaign_id);
+        let token = campaign.reward_token.get();
+        if token == Address::ZERO {
+            return Ok(());
+        }
+        let unbacked = budget.saturating_sub(campaign.claimed.get()).saturating_sub(campaign.expired.get()).saturating_sub(campaign.pool.get());
+        if unbacked == U256::ZERO {
+            return Ok(());
+        }
+
+        let funder = match campaign.creator.get() {
+            Address::ZERO => self.owner.get(),
+            creator => creator,
+        };
+        let contract = self.vm().contract_address();
+        let balance = self.static_call_sol(token, &IERC20::balanceOfCall { account: contract }).map_or(U256::ZERO, |returned| returned._0);
+        let allowance = self.static_call_sol(token, &IERC20::allowanceCall { owner: funder, spender: contract }).map_or(U256::ZERO, |returned| returned._0);
+        if balance.saturating_sub(self.held_token_balance(token)).saturating_add(allowance) < unbacked {
+            return Err(CommonError::BudgetNotFunded(BudgetNotFunded {}));
+        }
+        Ok(())
+    }
+
+    /// Adds `issued` to `campaign_id`'s spent total for rewards to `user`, within its budget and, for
+    /// token campaigns, its pool.
+    fn charge_campaign(&mut self, campaign_id: U256, user: Address, issued: U256) -> Result<(), CommonError> {
+        let index = self.ensure_member_index(user);
+        let campaign = self.campaigns.get(campaign_id);
+        let spent = checked_add(campaign.spent.get(), issued)?;
+        if spent > campaign.budget.get() {
+            return Err(CommonError::BudgetExceeded(BudgetExceeded {}));
+        }
+        if campaign.reward_token.get() != Address::ZERO {
+            let outstanding = checked_sub(checked_sub(spent, campaign.claimed.get())?, campaign.expired.get())?;
+            if outstanding > campaign.pool.get() {
+                return Err(CommonError::BudgetNotFunded(BudgetNotFunded {}));
+            }
+        }
+
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.spent.set(spent);
+        if !campaign.participated.get(index) {
+            campaign.participated.set(index);
+            let participant_count = campaign.participant_count.get() + U256::from(1);
+            campaign.participant_count.set(participant_count);
+        }
+        Ok(())
+    }
+
+    fn eip712_domain(&self) -> Eip712Domain {
+        eip712_domain! {
+            name: "RewardProcessor",
+            version: "1",
+            chain_id: self.vm().chain_id(),
+            verifying_contract: self.vm().contract_address(),
+        }
+    }
+
+    /// Address that signed `digest`, recovered through the `ecrecover` precompile; `None` for malformed
+    /// or malleable signatures.
+    fn recover_signer(&self, digest: B256, signature: &[u8]) -> Option<Address> {
+        if signature.len() != 65 {
+            return None;
+        }
+        let (r, s, v) = (&signature[..32], &signature[32..64], signature[64]);
+        if U256::from_be_slice(s) > MAX_SIGNATURE_S || !matches!(v, 27 | 28) {
+            return None;
+        }
+
+        let input = [digest.as_slice(), U256::from(v).to_be_bytes::<32>().as_slice(), r, s].concat();
+        let output = self.vm().static_call(&Call::new(), Address::with_last_byte(1), &input).ok()?;
+        if output.len() != 32 {
+            return None;
+        }
+        let signer = Address::from_slice(&output[12..]);
+        (signer != Address::ZERO).then_some(signer)
+    }
+
+    /// Pays `user` `requested` of their ledger balance, or all of it for `None`, as `claim` describes.
+    fn claim_ledger(&mut self, user: Address, requested: Option<U256>) -> Result<U256, CommonError> {
+        self.assert_not_shut_down()?;
+        self.assert_not_paused()?;
+
+        let balance = self.compounded_balance_of(user)?;
+        let amount = requested.unwrap_or(balance);
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+        if amount > balance {
+            return Err(CommonError::AmountExceedsBalance(AmountExceedsBalance {}));
+        }
+
+        let shares = self.settle_ledger_claim(user, amount)?;
+        self.emit_claim_receipt(U256::ZERO, user, amount, shares);
+        self.pay_out(U256::ZERO, user, amount)?;
+
+        Ok(amount)
+    }
+
+    /// Pays `user` the unlocked part of their grants, as `claim_vested` describes.
+    fn claim_grants(&mut self, user: Address) -> Result<U256, CommonError> {
+        self.assert_not_shut_down()?;
+        self.assert_not_paused()?;
+
+        let campaign_count = self.vesting_campaigns.get(user).len();
+        let mut amount = U256::ZERO;
+        let mut window_closed = false;
+        let mut campaign_paused = false;
+        let mut not_eligible = false;
+        for index in 0..campaign_count {
+            let campaign_id = self.vesting_campaigns.get(user).get(index).unwrap_or_default();
+            let releasable = self.releasable(campaign_id, user)?;
+            if releasable == U256::ZERO {
+                continue;
+            }
+            if self.campaigns.get(campaign_id).paused.get() {
+                campaign_paused = true;
+                continue;
+            }
+            if !self.is_campaign_participant(campaign_id, user) {
+                not_eligible = true;
+                continue;
+            }
+            if !self.claim_window_open(campaign_id) {
+                window_closed = true;
+                continue;
+            }
+
+            let shares = self.released_bonus_shares(campaign_id, user, releasable)?;
+            let campaign = self.campaigns.get(campaign_id);
+            let released = checked_add(campaign.vesting_released.get(user), releasable)?;
+            let claimed = checked_add(campaign.claimed.get(), releasable)?;
+            let token = campaign.reward_token.get();
+            let pool = campaign.pool.get();
+            let total_claimed = checked_add(self.total_claimed.get(), releasable)?;
+            self.total_claimed.set(total_claimed);
+            let mut campaign = self.campaigns.setter(campaign_id);
+            campaign.vesting_released.setter(user).set(released);
+            campaign.claimed.set(claimed);
+            self.record_campaign_claim(campaign_id, user);
+            amount = checked_add(amount, releasable)?;
+
+            if token == Address::ZERO {
+                self.emit_claim_receipt(campaign_id, user, releasable, shares);
+                self.pay_out_campaign(campaign_id, user, releasable)?;
+                continue;
+            }
+            self.campaigns.setter(campaign_id).pool.set(checked_sub(pool, releasable)?);
+            self.release_pooled(token, releasable)?;
+            self.send_token(campaign_id, token, user, releasable)?;
+
+            self.emit_claim_receipt(campaign_id, user, releasable, shares);
+            self.emit(|seq| CampaignRewardClaimed { campaign_id, user, token, amount: releasable, seq });
+        }
+        if amount == U256::ZERO && campaign_paused {
+            return Err(CommonError::CampaignPaused(CampaignPaused {}));
+        }
+        if amount == U256::ZERO && not_eligible {
+            return Err(CommonError::NotEligible(NotEligible {}));
+        }
+        if amount == U256::ZERO && window_closed {
+            return Err(CommonError::ClaimWindowClosed(ClaimWindowClosed {}));
+        }
+        if amount == U256::ZERO {
+            return Err(CommonError::ZeroValue(ZeroValue {}));
+        }
+
+        Ok(amount)
+    }
+
+    fn assert_claimer(&self, account: Address) -> Result<(), CommonError> {
+        if !self.claimers.get(account).get(self.vm().msg_sender()) {
+            return Err(CommonError::Unauthorized(Unauthorized {}));
+        }
+        Ok(())
+    }
+
+    fn set_claimer(&mut self, claimer: Address, approved: bool) -> Result<(), CommonError> {
+        let account = self.vm().msg_sender();
+        self.claimers.setter(account).setter(claimer).set(approved);
+
+        self.emit(|seq| ClaimerUpdated { account, claimer, approved, seq });
+
+        Ok(())
+    }
+
+    /// Sends `amount` of native currency to `to` under the reentrancy lock.
+    fn transfer_native(&mut self, to: Address, amount: U256) -> Result<(), CommonError> {
+        self.non_reentrant(|this| this.vm().transfer_eth(to, amount).map_err(|_| CommonError::TransferFailed(TransferFailed {})))
+    }
+
+    /// Runs the external call `f` with the reentrancy lock held, so a callback that reaches another
+    /// transfer reverts with `ReentrantCall`.
+    fn non_reentrant<T>(&mut self, f: impl FnOnce(&mut Self) -> Result<T, CommonError>) -> Result<T, CommonError> {
+        if self.entered.get() {
+            return Err(CommonError::ReentrantCall(ReentrantCall {}));
+        }
+        self.entered.set(true);
+        let result = f(self);
+        self.entered.set(false);
+        result
+    }
+
+    /// Transfers `amount` of `token` claimed by `user` to them, minus the claim fee.
+    fn send_token(&mut self, campaign_id: U256, token: Address, user: Address, amount: U256) -> Result<(), CommonError> {
+        let router = self.vm().msg_sender();
+        let fee = self.collect_fee(campaign_id, router, user, token, amount)?;
+        self.call_token(token, IERC20::transferCall { to: user, amount: amount - fee }.abi_encode())
+    }
+
+    /// Sends the claim fee on `amount` of `token` (`Address::ZERO` for native currency) to the fee recipient
+    /// and returns it; nothing while no recipient is set.
+    ///
+    /// A claim `router` triggers for another `user` counts towards the router's volume when the owner registered
+    /// them with `set_integrator`, and pays the fee of their tier before the volume is added.
+    fn collect_fee(&mut self, campaign_id: U256, router: Address, user: Address, token: Address, amount: U256) -> Result<U256, CommonError> {
+        let fee_bps = if router != user && self.integrators.get(router) {
+            self.record_routed_volume(router, token, amount)?
+        } else {
+            self.claim_fee_bps.get()
+        };
+        let recipient = self.fee_recipient.get();
+        if recipient == Address::ZERO {
+            return Ok(U256::ZERO);
+        }
+        let fee = scale(amount, fee_bps, U256::from(BPS_DENOMINATOR))?;
+        if fee == U256::ZERO {
+            return Ok(U256::ZERO);
+        }
+
+        if token == Address::ZERO {
+            self.transfer_native(recipient, fee)?;
+        } else {
+            self.call_token(token, IERC20::transferCall { to: recipient, amount: fee }.abi_encode())?;
+        }
+
+        self.emit(|seq| FeeCollected { campaign_id, user, token, recipient, amount: fee, seq });
+
+        Ok(fee)
+    }
+
+    /// Adds `amount` of `token` to `integrator`'s routed volume, emitting `IntegratorTierChanged` when that
+    /// moves them to another tier, and returns the fee in basis points of the tier they were in.
+    fn record_routed_volume(&mut self, integrator: Address, token: Address, amount: U256) -> Result<U256, CommonError> {
+        let decimals = if token == Address::ZERO { INTERNAL_DECIMALS } else { self.reward_tokens.get(token).decimals.get().to::<u8>() };
+        let routed = if decimals <= INTERNAL_DECIMALS {
+            checked_mul(amount, decimals_factor(INTERNAL_DECIMALS - decimals))?
+        } else {
+            amount / decimals_factor(decimals - INTERNAL_DECIMALS)
+        };
+        let previous = self.integrator_volume.get(integrator);
+        let volume = checked_add(previous, routed)?;
+        self.integrator_volume.setter(integrator).set(volume);
+
+        let (previous_tier, tier) = (self.fee_tier_for(previous), self.fee_tier_for(volume));
+        if tier != previous_tier {
+            let (previous_tier, fee_bps, tier) = (U256::from(previous_tier), self.tier_fee_bps(tier), U256::from(tier));
+            self.emit(|seq| IntegratorTierChanged { integrator, previous_tier, tier, fee_bps, volume, seq });
+        }
+
+        Ok(self.tier_fee_bps(previous_tier))
+    }
+
+    /// Number of tier thresholds `volume` has reached.
+    fn fee_tier_for(&self, volume: U256) -> usize {
+        (0..self.fee_tier_thresholds.len()).take_while(|index| self.fee_tier_thresholds.get(*index).is_some_and(|threshold| volume >= threshold)).count()
+    }
+
+    fn tier_fee_bps(&self, tier: usize) -> U256 {
+        match tier.checked_sub(1).and_then(|index| self.fee_tier_bps.get(index)) {
+            Some(fee_bps) => fee_bps.min(self.claim_fee_bps.get()),
+            None => self.claim_fee_bps.get(),
+        }
+    }
+
+    fn emit_claim_fee_updated(&mut self) {
+        let (sender, fee_bps, recipient) = (self.vm().tx_origin(), self.claim_fee_bps.get(), self.fee_recipient.get());
+        self.emit(|seq| ClaimFeeUpdated { sender, fee_bps, recipient, seq });
+    }
+
+    /// Balance of `token` the contract holds for campaign pools, the treasury and, for the payout token,
+    /// unclaimed ledger rewards.
+    fn held_token_balance(&self, token: Address) -> U256 {
+        let mut held = self.treasury_tokens.get(token).saturating_add(self.pooled_tokens.get(token));
+        if token == self.payout_token.get() {
+            held = held.saturating_add(self.total_calculated.get().saturating_sub(self.total_claimed.get()));
+        }
+        held
+    }
+
+    /// Takes native currency leaving the contract off the treasury, down to zero.
+    fn debit_treasury(&mut self, amount: U256) {
+        let treasury = self.treasury.get().saturating_sub(amount);
+        self.treasury.set(treasury);
+    }
+
+    /// Takes `amount` paid out of a campaign pool of `token` off `pooled_tokens`.
+    fn release_pooled(&mut self, token: Address, amount: U256) -> Result<(), CommonError> {
+        let pooled = checked_sub(self.pooled_tokens.get(token), amount)?;
+        self.pooled_tokens.setter(token).set(pooled);
+        Ok(())
+    }
+
+    /// Pulls `amount` of `token` from the caller into the treasury.
+    fn pull_token_deposit(&mut self, token: Address, amount: U256) -> Result<(), CommonError> {
+        let depositor = self.vm().msg_sender();
+        let contract = self.vm().contract_address();
+        self.call_token(token, IERC20::transferFromCall { from: depositor, to: contract, amount }.abi_encode())?;
+        let treasury = checked_add(self.treasury_tokens.get(token), amount)?;
+        self.treasury_tokens.setter(token).set(treasury);
+
+        self.emit(|seq| TreasuryDeposited { depositor, token, amount, seq });
+
+        Ok(())
+    }
+
+    /// Pays `amount` claimed by `user` in the native campaign `campaign_id`, split across its payout legs if it has any.
+    fn pay_out_campaign(&mut self, campaign_id: U256, user: Address, amount: U256) -> Result<(), CommonError> {
+        let leg_count = self.campaigns.get(campaign_id).leg_tokens.len();
+        if leg_count == 0 {
+            return self.pay_out(campaign_id, user, amount);
+        }
+
+        let mut remaining = amount;
+        for index in 0..leg_count {
+            let campaign = self.campaigns.get(campaign_id);
+            let token = campaign.leg_tokens.get(index).unwrap_or_default();
+            let share = if index + 1 == leg_count {
+                remaining
+            } else {
+                scale(amount, campaign.leg_weights.get(index).unwrap_or_default(), U256::from(BPS_DENOMINATOR))?
+            };
+            remaining -= share;
+            if share == U256::ZERO {
+                continue;
+            }
+
+            if token == Address::ZERO {
+                self.pay_out(campaign_id, user, share)?;
+                continue;
+            }
+            let treasury = self.treasury_tokens.get(token);
+            if share > treasury {
+                return Err(CommonError::AmountExceedsBalance(AmountExceedsBalance {}));
+            }
+            self.treasury_tokens.setter(token).set(treasury - share);
+            self.send_token(campaign_id, token, user, share)?;
+
+            self.emit(|seq| CampaignRewardClaimed { campaign_id, user, token, amount: share, seq });
+        }
+        Ok(())
+    }
+
+    /// Sends `amount` of native currency to `user`, wrapped as WETH if they opted in and WETH is configured.
+    fn deliver_native(&mut self, user: Address, amount: U256) -> Result<(), CommonError> {
+        let weth = self.weth.get();
+        if weth == Address::ZERO || !self.wants_weth.get(user) {
+            return self.transfer_native(user, amount);
+        }
+
+        let deposit = IWETH::depositCall {}.abi_encode();
+        self.non_reentrant(|this| {
+            this.vm()
+                .call(&Call::new().value(amount), weth, &deposit)
+                .map_err(|_| CommonError::TransferFailed(TransferFailed {}))
+        })?;
+        self.call_token(weth, IERC20::transferCall { to: user, amount }.abi_encode())
+    }
+
+    fn queue_withdrawal(&mut self, campaign_id: U256, user: Address, amount: U256) -> Result<(), CommonError> {
+        let withdrawal_id = self.withdrawal_count.get() + U256::from(1);
+        let ready_at = checked_add(U256::from(self.vm().block_timestamp()), self.withdrawal_delay.get())?;
+        let queued_total = checked_add(self.queued_total.get(), amount)?;
+        self.withdrawal_count.set(withdrawal_id);
+        self.queued_total.set(queued_total);
+
+        let mut withdrawal = self.withdrawals.setter(withdrawal_id);
+        withdrawal.user.set(user);
+        withdrawal.campaign_id.set(campaign_id);
+        withdrawal.amount.set(amount);
+        withdrawal.ready_at.set(ready_at);
+
+        self.emit(|seq| WithdrawalQueued { withdrawal_id, campaign_id, user, amount, ready_at, seq });
+
+        Ok(())
+    }
+
+    fn assert_withdrawal_pending(&self, withdrawal_id: U256) -> Result<(), CommonError> {
+        let withdrawal = self.withdrawals.get(withdrawal_id);
+        if withdrawal.user.get() == Address::ZERO {
+            return Err(CommonError::WithdrawalNotFound(WithdrawalNotFound {}));
+        }
+        if withdrawal.executed.get() {
+            return Err(CommonError::AlreadyClaimed(AlreadyClaimed {}));
+        }
+        Ok(())
+    }
+
+    fn record_campaign_claim(&mut self, campaign_id: U256, user: Address) {
+        let index = self.ensure_member_index(user);
+        let campaign = self.campaigns.get(campaign_id);
+        let (claim_count, first_claim) = (campaign.claim_count.get() + U256::from(1), !campaign.claimed_by.get(index));
+        let claimant_count = campaign.claimant_count.get() + U256::from(first_claim as u8);
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.claim_count.set(claim_count);
+        campaign.claimant_count.set(claimant_count);
+        campaign.claimed_by.set(index);
+    }
+
+    fn record_campaign_multiplier(&mut self, campaign_id: U256, time_multiplier: U256) {
+        let campaign = self.campaigns.get(campaign_id);
+        let (multiplier_sum, priced_count) = (campaign.multiplier_sum.get().saturating_add(time_multiplier), campaign.priced_count.get() + U256::from(1));
+        let mut campaign = self.campaigns.setter(campaign_id);
+        campaign.multiplier_sum.set(multiplier_sum);
+        campaign.priced_count.set(priced_count);
+    }
+
+    /// Emits `campaign_id`'s `CampaignReport` from its stored aggregates: claims and claimants of grants and
+    /// merkle drops, the average time multiplier rewards were priced at, in `percentage_denominator` units, and
+    /// the share of the budget issued, in basis points.
+    fn emit_campaign_report(&mut self, campaign_id: U256) -> Result<(), CommonError> {
+        let campaign = self.campaigns.get(campaign_id);
+        let (claim_count, claimant_count, total_paid) = (campaign.claim_count.get(), campaign.claimant_count.get(), campaign.claimed.get());
+        let priced_count = campaign.priced_count.get();
+        let average_multiplier = if priced_count == U256::ZERO { U256::ZERO } else { campaign.multiplier_sum.get() / priced_count };
+        let budget = campaign.budget.get();
+        let budget_utilization_bps = if budget == U256::ZERO {
+            U256::ZERO
+        } else {
+            scale(campaign.spent.get(), U256::from(BPS_DENOMINATOR), budget)?
+        };
+
+        self.emit(|seq| CampaignReport { campaign_id, claim_count, claimant_count, total_paid, average_multiplier, budget_utilization_bps, seq });
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use alloy_primitives::Address;
+    use stylus_sdk::testing::{TestVM, TestVMBuilder};
+
+    use super::*;
+    use crate::alias::apply_l1_alias;
+
+    const GUARDIAN: Address = Address::new([0x0f; 20]);
+
+    fn confirm_by_owner_and_guardian(vm: &TestVM, contract: &mut RewardProcessor, owner: Address, action: u8) {
+        assert!(contract.set_guardian(GUARDIAN).is_ok());
+        assert!(contract.confirm_action(action).is_ok());
+        vm.set_sender(GUARDIAN);
+        assert!(contract.confirm_action(action).is_ok());
+        vm.set_sender(owner);
+    }
+
+    #[test]
+    fn test_assert_owner() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+
+        let result = contract.constructor(U256::from(1000000));
+
+        assert!(result.is_ok());
+        
+        let owner_check = contract.assert_owner();
+        assert!(owner_check.is_ok());
+        
+        let vm2 = TestVMBuilder::new()
+            .sender(Address::from([0x02; 20]))
+            .build();
+        
+        let contract2 = RewardProcessor::from(&vm2);
+        
+        let unauthorized_check = contract2.assert_owner();
+        assert!(unauthorized_check.is_err());
+        assert!(matches!(
+            unauthorized_check.unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+    }
+
+    #[test]
+    fn test_update_multiply_factor() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+
+        let result = contract.constructor(U256::from(1000000));
+
+        assert!(result.is_ok());
+        
+        let owner_check = contract.assert_owner();
+        assert!(owner_check.is_ok());
+        
+        let vm2 = TestVMBuilder::new()
+            .sender(Address::from([0x02; 20]))
+            .build();
+        
+        let mut contract2 = RewardProcessor::from(&vm2);
+        
+        let unauthorized_check = contract2.update_multiply_factor(U256::from(2000000));
+        assert!(unauthorized_check.is_err());
+        assert!(matches!(
+            unauthorized_check.unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+
+        let multiply_factor_check = contract.multiply_factor.get();
+        assert_eq!(multiply_factor_check, U256::from(1000000));
+
+        let update_result = contract.update_multiply_factor(U256::from(2000000));
+        assert!(update_result.is_ok());
+
+        let multiply_factor_check2 = contract.multiply_factor.get();
+        assert_eq!(multiply_factor_check2, U256::from(2000000));
+    }
+
+    #[test]
+    fn test_transfer_ownership_success() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+
+        let result = contract.constructor(U256::from(1000000));
+        assert!(result.is_ok());
+
+        let initial_owner = contract.owner.get();
+        assert_eq!(initial_owner, Address::from([0x01; 20]));
+
+        let new_owner_address = Address::from([0x03; 20]);
+        let transfer_result = contract.transfer_ownership(new_owner_address);
+        
+        assert!(transfer_result.is_ok());
+        
+        let updated_owner = contract.owner.get();
+        assert_eq!(updated_owner, new_owner_address);
+    }
+
+    #[test]
+    fn test_transfer_ownership_unauthorized() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+
+        let result = contract.constructor(U256::from(1000000));
+        assert!(result.is_ok());
+
+        let vm2 = TestVMBuilder::new()
+            .sender(Address::from([0x02; 20]))
+            .build();
+        
+        let mut contract2 = RewardProcessor::from(&vm2);
+        
+        let new_owner_address = Address::from([0x03; 20]);
+        let transfer_result = contract2.transfer_ownership(new_owner_address);
+        
+        assert!(transfer_result.is_err());
+        assert!(matches!(
+            transfer_result.unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+        
+        let owner_check = contract.owner.get();
+        assert_eq!(owner_check, Address::from([0x01; 20]));
+    }
+
+    #[test]
+    fn test_time_based_reward_decay() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let amount = U256::from(1000);
+        let start_time = U256::from(1000);
+        let end_time = U256::from(2000);
+
+        let reward_at_start = contract.calculate_reward_at_time(amount, U256::from(1000), start_time, end_time, false, false).unwrap();
+        assert_eq!(reward_at_start, amount);
+
+        let reward_at_middle = contract.calculate_reward_at_time(amount, U256::from(1500), start_time, end_time, false, false).unwrap();
+        assert_eq!(reward_at_middle, U256::from(750));
+
+        let reward_at_end = contract.calculate_reward_at_time(amount, U256::from(2000), start_time, end_time, false, false).unwrap();
+        assert_eq!(reward_at_end, U256::from(500));
+    }
+
+    #[test]
+    fn test_time_based_reward_with_bonuses() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let amount = U256::from(1000);
+        let start_time = U256::from(1000);
+        let end_time = U256::from(2000);
+
+        let reward_with_bonuses = contract.calculate_reward_at_time(amount, U256::from(1000), start_time, end_time, true, true).unwrap();
+        
+        let expected = U256::from(1000) + U256::from(100) + U256::from(500);
+        assert_eq!(reward_with_bonuses, expected);
+
+        let reward_middle_with_bonuses = contract.calculate_reward_at_time(amount, U256::from(1500), start_time, end_time, true, true).unwrap();
+        
+        let expected_middle = U256::from(750) + U256::from(100) + U256::from(500);
+        assert_eq!(reward_middle_with_bonuses, expected_middle);
+    }
+
+    #[test]
+    fn test_time_based_reward_edge_cases() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let amount = U256::from(1000);
+        let start_time = U256::from(1000);
+        let end_time = U256::from(2000);
+
+        let reward_before_start = contract.calculate_reward_at_time(amount, U256::from(500), start_time, end_time, false, false).unwrap();
+        assert_eq!(reward_before_start, amount);
+
+        let reward_after_end = contract.calculate_reward_at_time(amount, U256::from(3000), start_time, end_time, false, false).unwrap();
+        assert_eq!(reward_after_end, U256::from(500));
+    }
+
+    #[test]
+    fn test_constructor_invalid_multiply_factor() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        
+        let result = contract.constructor(U256::ZERO);
+        assert!(result.is_err());
+        assert!(matches!(
+            result.unwrap_err(),
+            ConstructorError::InvalidMultiplyFactor(_)
+        ));
+    }
+
+    #[test]
+    fn test_constructor_valid_initialization() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        
+        let multiply_factor = U256::from(12000);
+        let result = contract.constructor(multiply_factor);
+        assert!(result.is_ok());
+
+        // Check initial values
+        assert_eq!(contract.multiply_factor.get(), multiply_factor);
+        assert_eq!(contract.owner.get(), Address::from([0x01; 20]));
+        assert_eq!(contract.percentage_denominator.get(), U256::from(10000));
+        assert_eq!(contract.percentage_bonus.get(), U256::from(1000));
+    }
+
+    #[test]
+    fn test_update_percentage_bonus_success() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        assert_eq!(contract.percentage_bonus.get(), U256::from(1000));
+
+        let update_result = contract.update_percentage_bonus(U256::from(2000));
+        assert!(update_result.is_ok());
+        assert_eq!(contract.percentage_bonus.get(), U256::from(2000));
+    }
+
+    #[test]
+    fn test_update_percentage_bonus_unauthorized() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let vm2 = TestVMBuilder::new()
+            .sender(Address::from([0x02; 20]))
+            .build();
+        
+        let mut contract2 = RewardProcessor::from(&vm2);
+        
+        let update_result = contract2.update_percentage_bonus(U256::from(2000));
+        assert!(update_result.is_err());
+        assert!(matches!(
+            update_result.unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+    }
+
+    #[test]
+    fn test_update_percentage_bonus_zero_value() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let update_result = contract.update_percentage_bonus(U256::ZERO);
+        assert!(update_result.is_err());
+        assert!(matches!(
+            update_result.unwrap_err(),
+            CommonError::ZeroValue(_)
+        ));
+    }
+
+    #[test]
+    fn test_update_multiply_factor_zero_value() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let update_result = contract.update_multiply_factor(U256::ZERO);
+        assert!(update_result.is_err());
+        assert!(matches!(
+            update_result.unwrap_err(),
+            CommonError::InvalidMultiplyFactor(_)
+        ));
+    }
+
+    #[test]
+    fn test_get_pseudo_random_deterministic() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let random1 = contract.get_pseudo_random();
+        let random2 = contract.get_pseudo_random();
+        assert_eq!(random1, random2);
+    }
+
+    #[test]
+    fn test_get_pseudo_random_different_states() {
+        let vm1 = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let vm2 = TestVMBuilder::new()
+            .sender(Address::from([0x02; 20]))
+            .build();
+
+        let mut contract1 = RewardProcessor::from(&vm1);
+        let mut contract2 = RewardProcessor::from(&vm2);
+        
+        let result1 = contract1.constructor(U256::from(5000));
+        let result2 = contract2.constructor(U256::from(5000));
+        assert!(result1.is_ok());
+        assert!(result2.is_ok());
+
+        let random1 = contract1.get_pseudo_random();
+        let random2 = contract2.get_pseudo_random();
+        
+        assert!(matches!(random1, true | false));
+        assert!(matches!(random2, true | false));
+    }
+
+    #[test]
+    fn test_calculate_reward_current_time() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let amount = U256::from(1000);
+        let start_time = U256::from(1000);
+        let end_time = U256::from(2000);
+
+        let reward = contract.calculate_reward(amount, start_time, end_time, false, false).unwrap();
+        
+        assert!(reward >= U256::from(500));
+        assert!(reward <= U256::from(2000));
+    }
+
+    #[test]
+    fn test_reward_with_all_bonuses() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let amount = U256::from(1000);
+        let start_time = U256::from(1000);
+        let end_time = U256::from(2000);
+
+        let reward = contract.calculate_reward_at_time(amount, U256::from(1000), start_time, end_time, true, true).unwrap();
+        
+        assert!(reward >= U256::from(1600));
+        assert!(reward <= U256::from(3200));
+    }
+
+    #[test]
+    fn test_emergency_shutdown_requires_timelock() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+        vm.set_block_timestamp(1000);
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        assert!(matches!(
+            contract.emergency_shutdown().unwrap_err(),
+            CommonError::ShutdownNotScheduled(_)
+        ));
+
+        assert!(contract.schedule_emergency_shutdown().is_ok());
+        assert_eq!(contract.shutdown_eta(), U256::from(1000 + SHUTDOWN_TIMELOCK));
+
+        vm.set_block_timestamp(1000 + SHUTDOWN_TIMELOCK - 1);
+        assert!(matches!(
+            contract.emergency_shutdown().unwrap_err(),
+            CommonError::TimelockNotElapsed(_)
+        ));
+
+        vm.set_block_timestamp(1000 + SHUTDOWN_TIMELOCK);
+        confirm_by_owner_and_guardian(&vm, &mut contract, Address::from([0x01; 20]), ACTION_EMERGENCY_SHUTDOWN);
+        assert!(contract.emergency_shutdown().is_ok());
+        assert!(contract.is_shut_down());
+        assert_eq!(contract.refund_deadline(), U256::from(1000 + SHUTDOWN_TIMELOCK + REFUND_WINDOW));
+
+        vm.set_value(U256::from(10));
+        assert!(matches!(
+            contract.fund().unwrap_err(),
+            CommonError::ContractShutDown(_)
+        ));
+    }
+
+    #[test]
+    fn test_emergency_shutdown_cancel_and_unauthorized() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        assert!(contract.schedule_emergency_shutdown().is_ok());
+        assert!(contract.cancel_emergency_shutdown().is_ok());
+        assert_eq!(contract.shutdown_eta(), U256::ZERO);
+
+        vm.set_sender(Address::from([0x02; 20]));
+        assert!(matches!(
+            contract.schedule_emergency_shutdown().unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+    }
+
+    #[test]
+    fn test_sponsor_refunds_after_shutdown() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .value(U256::from(300))
+            .build();
+        let contract_address = vm.contract_address();
+        let sponsor = Address::from([0x02; 20]);
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+        assert_eq!(contract.refundable_balance_of(Address::from([0x01; 20])), U256::from(300));
+
+        vm.set_sender(sponsor);
+        vm.set_value(U256::from(200));
+        assert!(contract.fund().is_ok());
+        assert_eq!(contract.refundable_balance_of(sponsor), U256::from(200));
+        assert_eq!(contract.total_refundable(), U256::from(500));
+        vm.set_balance(contract_address, U256::from(500));
+
+        assert!(matches!(
+            contract.claim_refund().unwrap_err(),
+            CommonError::NotShutDown(_)
+        ));
+
+        vm.set_sender(Address::from([0x01; 20]));
+        assert!(contract.schedule_emergency_shutdown().is_ok());
+        vm.set_block_timestamp(SHUTDOWN_TIMELOCK);
+        confirm_by_owner_and_guardian(&vm, &mut contract, Address::from([0x01; 20]), ACTION_EMERGENCY_SHUTDOWN);
+        assert!(contract.emergency_shutdown().is_ok());
+
+        vm.set_sender(sponsor);
+        assert_eq!(contract.claim_refund().unwrap(), U256::from(200));
+        assert_eq!(vm.balance(sponsor), U256::from(200));
+        assert_eq!(contract.refundable_balance_of(sponsor), U256::ZERO);
+        assert_eq!(contract.total_refundable(), U256::from(300));
+        assert!(matches!(
+            contract.claim_refund().unwrap_err(),
+            CommonError::ZeroValue(_)
+        ));
+
+        vm.set_sender(Address::from([0x01; 20]));
+        vm.set_block_timestamp(SHUTDOWN_TIMELOCK + REFUND_WINDOW + 1);
+        assert!(matches!(
+            contract.claim_refund().unwrap_err(),
+            CommonError::RefundWindowClosed(_)
+        ));
+    }
+
+    #[test]
+    fn test_campaign_refunds_after_shutdown() {
+        let owner = Address::from([0x01; 20]);
+        let sponsor = Address::from([0x02; 20]);
+        let other_sponsor = Address::from([0x03; 20]);
+        let user = Address::from([0x04; 20]);
+        let token = Address::from([0x70; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        mock_token_metadata(&vm, token, 18, "RWD");
+        contract.approve_token(token).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(1000)).unwrap();
+        vm.set_sender(sponsor);
+        contract.fund_campaign(campaign_id, token, U256::from(600)).unwrap();
+        vm.set_sender(other_sponsor);
+        contract.fund_campaign(campaign_id, token, U256::from(400)).unwrap();
+        vm.set_sender(owner);
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_for_campaign(campaign_id, user, U256::from(100), false, false).unwrap();
+        assert!(matches!(contract.claim_campaign_refund(campaign_id), Err(CommonError::NotShutDown(_))));
+
+        contract.schedule_emergency_shutdown().unwrap();
+        vm.set_block_timestamp(1000 + SHUTDOWN_TIMELOCK);
+        confirm_by_owner_and_guardian(&vm, &mut contract, owner, ACTION_EMERGENCY_SHUTDOWN);
+        contract.emergency_shutdown().unwrap();
+
+        // Sponsors split the whole pool pro rata, including the reward issued but no longer claimable.
+        assert!(reward > U256::ZERO);
+        assert_eq!(contract.campaign_refundable_of(campaign_id, sponsor).unwrap(), U256::from(600));
+        assert!(matches!(contract.claim_campaign_refund(campaign_id), Err(CommonError::ZeroValue(_))));
+        vm.set_sender(sponsor);
+        let refund = contract.claim_campaign_refund(campaign_id).unwrap();
+        assert_eq!(refund, U256::from(600));
+        let logs = vm.get_emitted_logs();
+        let event = CampaignRefundClaimed::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((event.campaign_id, event.sponsor, event.token, event.amount), (campaign_id, sponsor, token, refund));
+        assert!(matches!(contract.claim_campaign_refund(campaign_id), Err(CommonError::ZeroValue(_))));
+        vm.set_sender(other_sponsor);
+        assert_eq!(contract.pooled_balance(token), U256::from(400));
+        assert_eq!(contract.claim_campaign_refund(campaign_id).unwrap(), U256::from(400));
+        assert_eq!(contract.campaign_pool(campaign_id), (token, U256::ZERO));
+        assert_eq!(contract.pooled_balance(token), U256::ZERO);
+
+        vm.set_block_timestamp(1000 + SHUTDOWN_TIMELOCK + REFUND_WINDOW + 1);
+        assert!(matches!(contract.claim_campaign_refund(campaign_id), Err(CommonError::RefundWindowClosed(_))));
+    }
+
+    #[test]
+    fn test_shutdown_settles_withdrawal_queue() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(1_000_000));
+        contract.set_withdrawal_delay(U256::from(30 * 24 * 3600)).unwrap();
+        contract.schedule_emergency_shutdown().unwrap();
+        vm.set_block_timestamp(SHUTDOWN_TIMELOCK);
+        let reward = contract.accrue_reward(user, U256::from(10_000), U256::from(SHUTDOWN_TIMELOCK), U256::from(2 * SHUTDOWN_TIMELOCK), false, false).unwrap();
+        vm.set_sender(user);
+        contract.claim_amount(reward / U256::from(2)).unwrap();
+        assert!(matches!(contract.execute_withdrawal(U256::from(1)), Err(CommonError::WithdrawalNotReady(_))));
+
+        vm.set_sender(owner);
+        confirm_by_owner_and_guardian(&vm, &mut contract, owner, ACTION_EMERGENCY_SHUTDOWN);
+        contract.emergency_shutdown().unwrap();
+
+        // The pending withdrawal no longer waits out its delay.
+        vm.set_sender(user);
+        assert_eq!(contract.execute_withdrawal(U256::from(1)).unwrap(), reward / U256::from(2));
+        assert_eq!(vm.balance(user), reward / U256::from(2));
+        assert_eq!(contract.reserved_balance(), reward - reward / U256::from(2));
+    }
+
+    #[test]
+    fn test_sponsor_refunds_share_what_claims_left() {
+        let owner = Address::from([0x01; 20]);
+        let sponsor = Address::from([0x02; 20]);
+        let other_sponsor = Address::from([0x03; 20]);
+        let user = Address::from([0x04; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_sender(sponsor);
+        vm.set_value(U256::from(6000));
+        contract.fund().unwrap();
+        vm.set_sender(other_sponsor);
+        vm.set_value(U256::from(4000));
+        contract.fund().unwrap();
+        vm.set_value(U256::ZERO);
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+
+        // The user's rewards are paid from the sponsors' funds, part of them through the withdrawal queue.
+        vm.set_sender(owner);
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(user, U256::from(2000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        vm.set_sender(user);
+        let paid = reward / U256::from(2);
+        contract.claim_amount(paid).unwrap();
+        vm.set_sender(owner);
+        contract.set_withdrawal_delay(U256::from(3600)).unwrap();
+        vm.set_sender(user);
+        contract.claim().unwrap();
+        let queued = reward - paid;
+        assert_eq!(contract.contract_balance(), U256::from(10_000) - paid);
+
+        vm.set_sender(owner);
+        contract.schedule_emergency_shutdown().unwrap();
+        vm.set_block_timestamp(1000 + SHUTDOWN_TIMELOCK);
+        confirm_by_owner_and_guardian(&vm, &mut contract, owner, ACTION_EMERGENCY_SHUTDOWN);
+        contract.emergency_shutdown().unwrap();
+
+        let left = U256::from(10_000) - paid - queued;
+        assert_eq!(contract.refund_due(sponsor).unwrap(), left * U256::from(6) / U256::from(10));
+        vm.set_sender(sponsor);
+        let refund = contract.claim_refund().unwrap();
+        assert_eq!(refund, left * U256::from(6) / U256::from(10));
+        vm.set_sender(other_sponsor);
+        assert_eq!(contract.claim_refund().unwrap(), left - refund);
+
+        // The queued withdrawal is still covered.
+        vm.set_sender(user);
+        assert_eq!(contract.execute_withdrawal(U256::from(1)).unwrap(), queued);
+        assert_eq!(vm.balance(user), reward);
+        assert_eq!(contract.contract_balance(), U256::ZERO);
+    }
+
+    #[test]
+    fn test_register_member_assigns_stable_indices() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let alice = Address::from([0x0a; 20]);
+        let bob = Address::from([0x0b; 20]);
+        assert_eq!(contract.member_index_of(alice), U256::ZERO);
+
+        assert_eq!(contract.register_member(alice).unwrap(), U256::from(1));
+        assert_eq!(contract.register_member(bob).unwrap(), U256::from(2));
+        assert_eq!(contract.register_member(alice).unwrap(), U256::from(1));
+        assert_eq!(contract.member_index_of(bob), U256::from(2));
+        assert_eq!(contract.member_count(), U256::from(2));
+
+        vm.set_sender(Address::from([0x02; 20]));
+        assert!(matches!(
+            contract.register_member(Address::from([0x0c; 20])).unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+    }
+
+    #[test]
+    fn test_record_participation_tracks_streaks() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+        let week = DEFAULT_EPOCH_LENGTH;
+        let user = Address::from([0x0a; 20]);
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        vm.set_block_timestamp(week);
+        assert!(contract.record_participation(user).is_ok());
+        assert!(contract.record_participation(user).is_ok());
+        assert_eq!(contract.participation_count_of(user), U256::from(2));
+        assert_eq!(contract.streak_of(user), U256::from(1));
+
+        vm.set_block_timestamp(2 * week);
+        assert!(contract.record_participation(user).is_ok());
+        assert_eq!(contract.streak_of(user), U256::from(2));
+
+        vm.set_block_timestamp(4 * week);
+        assert_eq!(contract.streak_of(user), U256::ZERO);
+        assert!(contract.record_participation(user).is_ok());
+        assert_eq!(contract.streak_of(user), U256::from(1));
+        assert_eq!(contract.participation_count_of(user), U256::from(4));
+    }
+
+    #[test]
+    fn test_record_participation_requires_operator() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+        let operator = Address::from([0x02; 20]);
+        let user = Address::from([0x0a; 20]);
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        assert!(contract.set_operator(operator, true).is_ok());
+        assert!(contract.is_operator(operator));
+
+        vm.set_sender(operator);
+        assert!(contract.record_participation(user).is_ok());
+
+        vm.set_sender(Address::from([0x03; 20]));
+        assert!(matches!(
+            contract.record_participation(user).unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+    }
+
+    #[test]
+    fn test_streak_bonus_is_capped_and_added_to_reward() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+        let week = DEFAULT_EPOCH_LENGTH;
+        let user = Address::from([0x0a; 20]);
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+        assert!(contract.update_streak_bonus(U256::from(100), U256::from(250)).is_ok());
+
+        for epoch in 1..=3 {
+            vm.set_block_timestamp(epoch * week);
+            assert!(contract.record_participation(user).is_ok());
+            assert_eq!(contract.streak_bonus_of(user), U256::from(epoch * 100).min(U256::from(250)));
+        }
+
+        let start_time = U256::from(3 * week);
+        let end_time = U256::from(4 * week);
+        let reward = contract.calculate_reward_for_user(user, U256::from(1000), start_time, end_time, false, false).unwrap();
+        assert_eq!(reward, U256::from(1025));
+
+        let stranger = Address::from([0x0b; 20]);
+        let reward = contract.calculate_reward_for_user(stranger, U256::from(1000), start_time, end_time, false, false).unwrap();
+        assert_eq!(reward, U256::from(1000));
+    }
+
+    #[test]
+    fn test_accrue_compounds_across_epochs() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+        let week = DEFAULT_EPOCH_LENGTH;
+        let user = Address::from([0x0a; 20]);
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+        assert!(contract.update_compound_rate(U256::from(1000)).is_ok());
+
+        vm.set_block_timestamp(week);
+        let start_time = U256::from(week);
+        let end_time = U256::from(2 * week);
+        let reward = contract.accrue(user, U256::from(1000), start_time, end_time, false, false).unwrap();
+        assert_eq!(reward, U256::from(1000));
+        assert_eq!(contract.compounded_balance_of(user).unwrap(), U256::from(1000));
+
+        vm.set_block_timestamp(3 * week);
+        assert_eq!(contract.compounded_balance_of(user).unwrap(), U256::from(1210));
+
+        let start_time = U256::from(3 * week);
+        let end_time = U256::from(4 * week);
+        assert!(contract.accrue(user, U256::from(1000), start_time, end_time, false, false).is_ok());
+        assert_eq!(contract.compounded_balance_of(user).unwrap(), U256::from(2210));
+
+        vm.set_block_timestamp(4 * week);
+        assert_eq!(contract.compounded_balance_of(user).unwrap(), U256::from(2431));
+    }
+
+    #[test]
+    fn test_compounding_survives_epoch_length_change() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+        let week = DEFAULT_EPOCH_LENGTH;
+        let user = Address::from([0x0a; 20]);
+
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_compound_rate(U256::from(1000)).unwrap();
+
+        vm.set_block_timestamp(10 * week);
+        let reward = contract.accrue(user, U256::from(1000), U256::from(10 * week), U256::from(11 * week), false, false).unwrap();
+        assert_eq!(reward, U256::from(1000));
+
+        // Epoch 10 of a week becomes epoch 2 of four weeks, before the epoch the accrual was stamped with.
+        contract.update_epoch_length(U256::from(4 * week)).unwrap();
+        assert_eq!(contract.compounded_balance_of(user).unwrap(), U256::from(1000));
+        vm.set_block_timestamp(12 * week);
+        assert_eq!(contract.compounded_balance_of(user).unwrap(), U256::from(1100));
+        assert!(contract.accrue(user, U256::from(1000), U256::from(12 * week), U256::from(13 * week), false, false).is_ok());
+        assert_eq!(contract.compounded_balance_of(user).unwrap(), U256::from(2100));
+    }
+
+    #[test]
+    fn test_accrue_requires_operator_and_live_contract() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+        let user = Address::from([0x0a; 20]);
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        vm.set_sender(Address::from([0x02; 20]));
+        assert!(matches!(
+            contract.accrue(user, U256::from(1000), U256::ZERO, U256::from(10), false, false).unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+
+        vm.set_sender(Address::from([0x01; 20]));
+        assert!(contract.schedule_emergency_shutdown().is_ok());
+        vm.set_block_timestamp(SHUTDOWN_TIMELOCK);
+        confirm_by_owner_and_guardian(&vm, &mut contract, Address::from([0x01; 20]), ACTION_EMERGENCY_SHUTDOWN);
+        assert!(contract.emergency_shutdown().is_ok());
+        assert!(matches!(
+            contract.accrue(user, U256::from(1000), U256::ZERO, U256::from(10), false, false).unwrap_err(),
+            CommonError::ContractShutDown(_)
+        ));
+    }
+
+    #[test]
+    fn test_boundary_policy_helpers() {
+        let start = U256::from(100);
+        let end = U256::from(200);
+        let cases = [
+            (BoundaryPolicy::InclusiveStartInclusiveEnd, true, false),
+            (BoundaryPolicy::InclusiveStartExclusiveEnd, true, true),
+            (BoundaryPolicy::ExclusiveStartInclusiveEnd, false, false),
+            (BoundaryPolicy::ExclusiveStartExclusiveEnd, false, true),
+        ];
+
+        for (policy, started_at_start, ended_at_end) in cases {
+            assert!(!policy.has_started(U256::from(99), start));
+            assert_eq!(policy.has_started(start, start), started_at_start);
+            assert!(policy.has_started(U256::from(101), start));
+
+            assert!(!policy.has_ended(U256::from(199), end));
+            assert_eq!(policy.has_ended(end, end), ended_at_end);
+            assert!(policy.has_ended(U256::from(201), end));
+        }
+    }
+
+    #[test]
+    fn test_boundary_policy_update_validation() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+        assert_eq!(contract.boundary_policy(), 0);
+
+        assert!(contract.update_boundary_policy(3).is_ok());
+        assert_eq!(contract.boundary_policy(), 3);
+
+        assert!(matches!(
+            contract.update_boundary_policy(4).unwrap_err(),
+            CommonError::InvalidBoundaryPolicy(_)
+        ));
+
+        vm.set_sender(Address::from([0x02; 20]));
+        assert!(matches!(
+            contract.update_boundary_policy(1).unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+    }
+
+    #[test]
+    fn test_decay_does_not_depend_on_boundary_policy() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let amount = U256::from(1000);
+        let start_time = U256::from(1000);
+        let end_time = U256::from(2000);
+
+        for policy in 0..4 {
+            assert!(contract.update_boundary_policy(policy).is_ok());
+            assert_eq!(contract.calculate_reward_at_time(amount, start_time, start_time, end_time, false, false).unwrap(), U256::from(1000));
+            assert_eq!(contract.calculate_reward_at_time(amount, U256::from(1001), start_time, end_time, false, false).unwrap(), U256::from(999));
+            assert_eq!(contract.calculate_reward_at_time(amount, U256::from(1999), start_time, end_time, false, false).unwrap(), U256::from(500));
+            assert_eq!(contract.calculate_reward_at_time(amount, end_time, start_time, end_time, false, false).unwrap(), U256::from(500));
+        }
+    }
+
+    #[test]
+    fn test_boundary_policy_applies_to_campaign_accrual() {
+        let user = Address::from([0x0a; 20]);
+        let cases = [
+            (BoundaryPolicy::InclusiveStartInclusiveEnd, true, true),
+            (BoundaryPolicy::InclusiveStartExclusiveEnd, true, false),
+            (BoundaryPolicy::ExclusiveStartInclusiveEnd, false, true),
+            (BoundaryPolicy::ExclusiveStartExclusiveEnd, false, false),
+        ];
+        for (policy, at_start, at_end) in cases {
+            let vm = TestVMBuilder::new()
+                .sender(Address::from([0x01; 20]))
+                .build();
+            let mut contract = RewardProcessor::from(&vm);
+            contract.constructor(U256::from(5000)).unwrap();
+            contract.update_boundary_policy(policy as u8).unwrap();
+            let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+            contract.set_campaign_budget(campaign_id, U256::from(1_000_000)).unwrap();
+            contract.activate_campaign(campaign_id).unwrap();
+
+            for (time, open) in [(999, false), (1000, at_start), (1001, true), (1999, true), (2000, at_end), (2001, false)] {
+                vm.set_block_timestamp(time);
+                let result = contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false);
+                if open {
+                    assert!(result.is_ok(), "{policy:?} at {time}");
+                } else {
+                    assert!(matches!(result, Err(CommonError::OutsideCampaignWindow(_))), "{policy:?} at {time}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_reward_math_keeps_precision_for_large_amounts() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let amount = U256::from(1) << 250;
+        let start_time = U256::from(1000);
+        let end_time = U256::from(2000);
+
+        let reward = contract.calculate_reward_at_time(amount, U256::from(1500), start_time, end_time, false, false).unwrap();
+        assert_eq!(reward, amount / U256::from(4) * U256::from(3));
+    }
+
+    #[test]
+    fn test_rounding_mode_pins_decay_and_bonus_values() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+        assert_eq!(contract.rounding_mode(), 0);
+
+        let start_time = U256::from(1000);
+        let end_time = U256::from(3000);
+        let cases = [(0u8, 999u64, 750, 100, 500), (1, 1000, 751, 101, 501), (2, 1000, 751, 100, 501)];
+
+        for (mode, decayed, decayed_exact_multiplier, bonus, strict_bonus) in cases {
+            assert!(contract.update_rounding_mode(mode).is_ok());
+
+            let reward = contract.calculate_reward_at_time(U256::from(1000), U256::from(1001), start_time, end_time, false, false).unwrap();
+            assert_eq!(reward, U256::from(decayed));
+
+            let reward = contract.calculate_reward_at_time(U256::from(1001), U256::from(2000), start_time, end_time, false, false).unwrap();
+            assert_eq!(reward, U256::from(decayed_exact_multiplier));
+
+            let with_bonus = contract.calculate_reward_at_time(U256::from(1001), U256::from(2000), start_time, end_time, true, false).unwrap();
+            assert_eq!(with_bonus - reward, U256::from(bonus));
+
+            let with_strict = contract.calculate_reward_at_time(U256::from(1001), U256::from(2000), start_time, end_time, false, true).unwrap();
+            assert_eq!(with_strict - reward, U256::from(strict_bonus));
+        }
+
+        assert!(matches!(
+            contract.update_rounding_mode(3).unwrap_err(),
+            CommonError::InvalidRoundingMode(_)
+        ));
+    }
+
+    #[test]
+    fn test_calculate_reward_reports_math_overflow() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let start_time = U256::from(1000);
+        let end_time = U256::from(2000);
+
+        let reward = contract.calculate_reward_at_time(U256::MAX, U256::from(1000), start_time, end_time, true, true);
+        assert!(matches!(reward.unwrap_err(), CommonError::MathOverflow(_)));
+
+        assert!(contract.update_max_multiply_factor(U256::MAX).is_ok());
+        assert!(contract.update_multiply_factor(U256::MAX).is_ok());
+        let reward = contract.calculate_reward_at_time(U256::from(1) << 128, U256::from(1000), start_time, end_time, false, true);
+        assert!(matches!(reward.unwrap_err(), CommonError::MathOverflow(_)));
+    }
+
+    #[test]
+    fn test_emergency_shutdown_requires_owner_and_guardian() {
+        let owner = Address::from([0x01; 20]);
+        let vm = TestVMBuilder::new()
+            .sender(owner)
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+        assert!(contract.set_guardian(GUARDIAN).is_ok());
+        assert_eq!(contract.guardian(), GUARDIAN);
+
+        assert!(contract.schedule_emergency_shutdown().is_ok());
+        vm.set_block_timestamp(SHUTDOWN_TIMELOCK);
+
+        assert!(contract.confirm_action(ACTION_EMERGENCY_SHUTDOWN).is_ok());
+        assert!(!contract.is_action_confirmed(ACTION_EMERGENCY_SHUTDOWN));
+        assert!(matches!(
+            contract.emergency_shutdown().unwrap_err(),
+            CommonError::ActionNotConfirmed(_)
+        ));
+
+        vm.set_sender(Address::from([0x02; 20]));
+        assert!(matches!(
+            contract.confirm_action(ACTION_EMERGENCY_SHUTDOWN).unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+
+        vm.set_sender(GUARDIAN);
+        assert!(contract.confirm_action(ACTION_EMERGENCY_SHUTDOWN).is_ok());
+        assert!(contract.is_action_confirmed(ACTION_EMERGENCY_SHUTDOWN));
+        assert!(matches!(
+            contract.emergency_shutdown().unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+
+        vm.set_sender(owner);
+        assert!(contract.emergency_shutdown().is_ok());
+        assert!(!contract.is_action_confirmed(ACTION_EMERGENCY_SHUTDOWN));
+    }
+
+    #[test]
+    fn test_action_confirmations_expire() {
+        let owner = Address::from([0x01; 20]);
+        let vm = TestVMBuilder::new()
+            .sender(owner)
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        vm.set_block_timestamp(1000);
+        assert!(contract.schedule_emergency_shutdown().is_ok());
+        confirm_by_owner_and_guardian(&vm, &mut contract, owner, ACTION_EMERGENCY_SHUTDOWN);
+        assert!(contract.is_action_confirmed(ACTION_EMERGENCY_SHUTDOWN));
+
+        vm.set_block_timestamp(1000 + SHUTDOWN_TIMELOCK);
+        assert!(!contract.is_action_confirmed(ACTION_EMERGENCY_SHUTDOWN));
+        assert!(matches!(
+            contract.emergency_shutdown().unwrap_err(),
+            CommonError::ActionNotConfirmed(_)
+        ));
+    }
+
+    #[test]
+    fn test_confirm_action_checks_direct_caller() {
+        let owner = Address::from([0x01; 20]);
+        let relay = Address::from([0x02; 20]);
+        let vm = TestVMBuilder::new()
+            .sender(owner)
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        assert!(contract.constructor(U256::from(5000)).is_ok());
+        assert!(contract.set_guardian(GUARDIAN).is_ok());
+        vm.set_block_timestamp(1000);
+
+        // A contract the owner's transaction passes through is not the owner.
+        vm.set_tx_origin(owner);
+        vm.set_sender(relay);
+        assert!(matches!(
+            contract.confirm_action(ACTION_EMERGENCY_SHUTDOWN).unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+
+        vm.set_sender(owner);
+        assert!(contract.confirm_action(ACTION_EMERGENCY_SHUTDOWN).is_ok());
+        vm.set_tx_origin(relay);
+        vm.set_sender(GUARDIAN);
+        assert!(contract.confirm_action(ACTION_EMERGENCY_SHUTDOWN).is_ok());
+        assert!(contract.is_action_confirmed(ACTION_EMERGENCY_SHUTDOWN));
+
+        // Executing the action authenticates the owner the same way.
+        vm.set_tx_origin(owner);
+        assert!(contract.schedule_emergency_shutdown().is_ok());
+        vm.set_block_timestamp(1000 + SHUTDOWN_TIMELOCK);
+        assert!(contract.confirm_action(ACTION_EMERGENCY_SHUTDOWN).is_ok());
+        vm.set_sender(owner);
+        assert!(contract.confirm_action(ACTION_EMERGENCY_SHUTDOWN).is_ok());
+        vm.set_sender(relay);
+        assert!(matches!(contract.emergency_shutdown().unwrap_err(), CommonError::Unauthorized(_)));
+        vm.set_sender(owner);
+        assert!(contract.emergency_shutdown().is_ok());
+    }
+
+    #[test]
+    fn test_calculate_reward_rejects_invalid_time_window() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let amount = U256::from(1000);
+
+        let inverted = contract.calculate_reward_at_time(amount, U256::from(1500), U256::from(2000), U256::from(1000), false, false);
+        assert!(matches!(inverted.unwrap_err(), CommonError::InvalidTimeWindow(_)));
+
+        let empty = contract.calculate_reward_at_time(amount, U256::from(1500), U256::from(1000), U256::from(1000), false, false);
+        assert!(matches!(empty.unwrap_err(), CommonError::InvalidTimeWindow(_)));
+
+        let user = Address::from([0x0a; 20]);
+        let accrued = contract.accrue(user, amount, U256::from(2000), U256::from(1000), false, false);
+        assert!(matches!(accrued.unwrap_err(), CommonError::InvalidTimeWindow(_)));
+    }
+
+    #[test]
+    fn test_constructor_rejects_multiply_factor_above_max() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+
+        let result = contract.constructor(U256::from(DEFAULT_MAX_MULTIPLY_FACTOR + 1));
+        assert!(matches!(
+            result.unwrap_err(),
+            ConstructorError::InvalidMultiplyFactor(_)
+        ));
+    }
+
+    #[test]
+    fn test_update_multiply_factor_respects_max() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+        assert_eq!(contract.max_multiply_factor(), U256::from(DEFAULT_MAX_MULTIPLY_FACTOR));
+
+        let too_large = U256::from(10).pow(U256::from(30));
+        assert!(matches!(
+            contract.update_multiply_factor(too_large).unwrap_err(),
+            CommonError::InvalidMultiplyFactor(_)
+        ));
+
+        assert!(contract.update_max_multiply_factor(U256::from(20000)).is_ok());
+        assert!(contract.update_multiply_factor(U256::from(20000)).is_ok());
+        assert!(matches!(
+            contract.update_multiply_factor(U256::from(20001)).unwrap_err(),
+            CommonError::InvalidMultiplyFactor(_)
+        ));
+
+        assert!(matches!(
+            contract.update_max_multiply_factor(U256::from(19999)).unwrap_err(),
+            CommonError::InvalidMultiplyFactor(_)
+        ));
+
+        vm.set_sender(Address::from([0x02; 20]));
+        assert!(matches!(
+            contract.update_max_multiply_factor(U256::from(30000)).unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+    }
+
+    #[test]
+    fn test_step_decay_holds_plateaus_between_steps() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+        assert_eq!(contract.step_interval(), U256::from(DEFAULT_STEP_INTERVAL));
+
+        assert!(contract.update_decay_mode(1).is_ok());
+        assert!(contract.update_step_interval(U256::from(250)).is_ok());
+
+        let amount = U256::from(1000);
+        let start_time = U256::from(1000);
+        let end_time = U256::from(2000);
+        let cases = [
+            (1000u64, 1000u64),
+            (1001, 1000),
+            (1249, 1000),
+            (1250, 875),
+            (1499, 875),
+            (1500, 750),
+            (1749, 750),
+            (1750, 625),
+            (1999, 625),
+            (2000, 500),
+        ];
+
+        for (time, expected) in cases {
+            let reward = contract.calculate_reward_at_time(amount, U256::from(time), start_time, end_time, false, false).unwrap();
+            assert_eq!(reward, U256::from(expected), "at {time}");
+        }
+    }
+
+    #[test]
+    fn test_decay_mode_validation() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+        assert_eq!(contract.decay_mode(), 0);
+
+        assert!(matches!(
+            contract.update_decay_mode(u8::MAX).unwrap_err(),
+            CommonError::InvalidDecayMode(_)
+        ));
+        assert!(matches!(
+            contract.update_step_interval(U256::ZERO).unwrap_err(),
+            CommonError::ZeroValue(_)
+        ));
+
+        vm.set_sender(Address::from([0x02; 20]));
+        assert!(matches!(
+            contract.update_decay_mode(1).unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+    }
+
+    #[test]
+    fn test_update_strict_bonus_denominator() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        assert_eq!(contract.strict_bonus_denominator(), U256::from(10000));
+
+        // The strict bonus moves to the new denominator while the percentage bonus keeps its own.
+        assert!(contract.update_strict_bonus_denominator(U256::from(100_000)).is_ok());
+        assert_eq!(contract.strict_bonus_denominator(), U256::from(100_000));
+        let breakdown = contract.calculate_reward_detailed(U256::from(1000), U256::from(1000), U256::from(1000), U256::from(2000), true, true).unwrap();
+        assert_eq!((breakdown.bonus, breakdown.strict_bonus), (U256::from(100), U256::from(50)));
+
+        assert!(matches!(
+            contract.update_strict_bonus_denominator(U256::ZERO).unwrap_err(),
+            CommonError::InvalidDenominator(_)
+        ));
+
+        vm.set_sender(Address::from([0x02; 20]));
+        assert!(matches!(
+            contract.update_strict_bonus_denominator(U256::from(10000)).unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+    }
+
+    #[test]
+    fn test_update_percentage_denominator() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        assert!(contract.update_percentage_denominator(U256::from(1_000_000)).is_ok());
+        assert_eq!(contract.percentage_denominator(), U256::from(1_000_000));
+        assert!(contract.update_percentage_bonus(U256::from(100_000)).is_ok());
+
+        let amount = U256::from(1000);
+        let reward = contract.calculate_reward_at_time(amount, U256::from(1000), U256::from(1000), U256::from(2000), true, false).unwrap();
+        assert_eq!(reward, U256::from(1100));
+
+        assert!(matches!(
+            contract.update_percentage_denominator(U256::ZERO).unwrap_err(),
+            CommonError::InvalidDenominator(_)
+        ));
+        assert!(matches!(
+            contract.update_percentage_denominator(U256::from(99_999)).unwrap_err(),
+            CommonError::InvalidDenominator(_)
+        ));
+
+        vm.set_sender(Address::from([0x02; 20]));
+        assert!(matches!(
+            contract.update_percentage_denominator(U256::from(10000)).unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+    }
+
+    fn mock_sybil_score(vm: &TestVM, scorer: Address, account: Address, score: u64) {
+        let calldata = ISybilScorer::scoreCall { account }.abi_encode();
+        let returned = ISybilScorer::scoreCall::abi_encode_returns(&(U256::from(score),));
+        vm.mock_static_call(scorer, calldata, Ok(returned));
+    }
+
+    /// Moves to the next block on which the lucky draw hits for the current sender.
+    fn hit_lucky_draw(vm: &TestVM, contract: &RewardProcessor) {
+        let mut block_number = vm.block_number();
+        while !contract.get_pseudo_random() {
+            block_number += 1;
+            vm.set_block_number(block_number);
+        }
+    }
+
+    fn mock_token_metadata(vm: &TestVM, token: Address, decimals: u8, symbol: &str) {
+        vm.mock_static_call(
+            token,
+            IERC20Metadata::decimalsCall {}.abi_encode(),
+            Ok(IERC20Metadata::decimalsCall::abi_encode_returns(&(decimals,))),
+        );
+        vm.mock_static_call(
+            token,
+            IERC20Metadata::symbolCall {}.abi_encode(),
+            Ok(IERC20Metadata::symbolCall::abi_encode_returns(&(symbol.to_string(),))),
+        );
+    }
+
+    #[test]
+    fn test_approve_token_stores_metadata() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+        let token = Address::from([0x70; 20]);
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        assert_eq!(contract.token_info(token), (false, 0, String::new()));
+
+        mock_token_metadata(&vm, token, 6, "USDC");
+        assert!(contract.approve_token(token).is_ok());
+        assert_eq!(contract.token_info(token), (true, 6, "USDC".to_string()));
+
+        assert!(contract.revoke_token(token).is_ok());
+        assert_eq!(contract.token_info(token), (false, 6, "USDC".to_string()));
+        assert!(matches!(
+            contract.revoke_token(token).unwrap_err(),
+            CommonError::TokenNotApproved(_)
+        ));
+    }
+
+    #[test]
+    fn test_approve_token_requires_metadata_and_owner() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+        let token = Address::from([0x70; 20]);
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        vm.mock_static_call(token, IERC20Metadata::decimalsCall {}.abi_encode(), Err(vec![]));
+        assert!(matches!(
+            contract.approve_token(token).unwrap_err(),
+            CommonError::TokenMetadataUnavailable(_)
+        ));
+
+        mock_token_metadata(&vm, token, 80, "HUGE");
+        assert!(matches!(
+            contract.approve_token(token).unwrap_err(),
+            CommonError::InvalidDecimals(_)
+        ));
+        assert_eq!(contract.token_info(token), (false, 0, String::new()));
+
+        mock_token_metadata(&vm, token, 18, "WETH");
+        vm.set_sender(Address::from([0x02; 20]));
+        assert!(matches!(
+            contract.approve_token(token).unwrap_err(),
+            CommonError::Unauthorized(_)
+        ));
+    }
+
+    #[test]
+    fn test_calculate_rewards_batch() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+        vm.set_block_timestamp(1500);
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+
+        let amounts = vec![U256::from(1000), U256::from(2000), U256::from(1000)];
+        let start_times = vec![U256::from(1000), U256::from(1500), U256::from(1000)];
+        let end_times = vec![U256::from(2000), U256::from(2500), U256::from(1200)];
+        let has_bonus = vec![false, true, false];
+        let has_strict_bonus = vec![false, false, true];
+
+        let rewards = contract.calculate_rewards_batch(amounts.clone(), start_times.clone(), end_times.clone(), has_bonus.clone(), has_strict_bonus.clone()).unwrap();
+        let expected: Vec<U256> = (0..3)
+            .map(|i| contract.calculate_reward(amounts[i], start_times[i], end_times[i], has_bonus[i], has_strict_bonus[i]).unwrap())
+            .collect();
+        assert_eq!(rewards, expected);
+        assert_eq!(rewards, vec![U256::from(750), U256::from(2200), U256::from(1000)]);
+
+        let mismatch = contract.calculate_rewards_batch(amounts, start_times, end_times, has_bonus, vec![false]);
+        assert!(matches!(mismatch.unwrap_err(), CommonError::LengthMismatch(_)));
+    }
+
+    #[test]
+    fn test_events_carry_monotonic_sequence_numbers() {
+        let vm = TestVMBuilder::new()
+            .sender(Address::from([0x01; 20]))
+            .build();
+
+        let mut contract = RewardProcessor::from(&vm);
+        let result = contract.constructor(U256::from(5000));
+        assert!(result.is_ok());
+        assert_eq!(contract.last_event_seq(), U256::ZERO);
+
+        assert!(contract.set_operator(Address::from([0x02; 20]), true).is_ok());
+        assert!(contract.update_epoch_length(U256::from(3600)).is_ok());
+        assert_eq!(contract.last_event_seq(), U256::from(2));
+
+        let logs = vm.get_emitted_logs();
+        assert_eq!(logs.len(), 2);
+
+        let operator_updated = OperatorUpdated::decode_raw_log(logs[0].0.iter().copied(), &logs[0].1, true).unwrap();
+        assert_eq!(operator_updated.seq, U256::from(1));
+
+        let epoch_length_updated = EpochLengthUpdated::decode_raw_log(logs[1].0.iter().copied(), &logs[1].1, true).unwrap();
+        assert_eq!(epoch_length_updated.seq, U256::from(2));
+        assert_eq!(epoch_length_updated.epoch_length, U256::from(3600));
+    }
+
+    #[test]
+    fn test_calculate_reward_detailed_breakdown() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let amount = U256::from(1000);
+        let breakdown = contract.calculate_reward_detailed(amount, U256::from(1500), U256::from(1000), U256::from(2000), true, true).unwrap();
+        assert_eq!(breakdown.base, amount);
+        assert_eq!(breakdown.time_multiplier, U256::from(7500));
+        assert_eq!(breakdown.decayed, U256::from(750));
+        assert_eq!(breakdown.bonus, U256::from(100));
+        assert_eq!(breakdown.strict_bonus, U256::from(500));
+
+        let sum = breakdown.decayed + breakdown.bonus + breakdown.strict_bonus;
+        assert!(breakdown.total == sum || breakdown.total == sum * U256::from(2));
+        let total = contract.calculate_reward_at_time(amount, U256::from(1500), U256::from(1000), U256::from(2000), true, true).unwrap();
+        assert_eq!(breakdown.total, total);
+    }
+
+    #[test]
+    fn test_calculate_reward_detailed_without_bonuses() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let breakdown = contract.calculate_reward_detailed(U256::from(1000), U256::from(3000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        assert_eq!(breakdown.time_multiplier, U256::from(5000));
+        assert_eq!(breakdown.decayed, U256::from(500));
+        assert_eq!(breakdown.bonus, U256::ZERO);
+        assert_eq!(breakdown.strict_bonus, U256::ZERO);
+
+        let invalid = contract.calculate_reward_detailed(U256::from(1000), U256::from(1500), U256::from(2000), U256::from(1000), false, false);
+        assert!(matches!(invalid, Err(CommonError::InvalidTimeWindow(_))));
+    }
+
+    #[test]
+    fn test_calculate_reward_streamed_linear() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        // Average of a 100% -> 50% linear ramp is 75%, independent of the window length.
+        let reward = contract.calculate_reward_streamed(U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        assert_eq!(reward, U256::from(750));
+        let reward = contract.calculate_reward_streamed(U256::from(1000), U256::from(0), U256::from(7), false, false).unwrap();
+        assert_eq!(reward, U256::from(750));
+
+        // Bonuses apply to the full amount, as for a lump sum.
+        let reward = contract.calculate_reward_streamed(U256::from(1000), U256::from(1000), U256::from(2000), true, true).unwrap();
+        assert_eq!(reward, U256::from(750 + 100 + 500));
+
+        let invalid = contract.calculate_reward_streamed(U256::from(1000), U256::from(2000), U256::from(2000), false, false);
+        assert!(matches!(invalid, Err(CommonError::InvalidTimeWindow(_))));
+    }
+
+    #[test]
+    fn test_calculate_reward_streamed_step() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_decay_mode(DecayMode::Step as u8).unwrap();
+
+        // Four equal steps at 100%, 87.5%, 75% and 62.5% average to 81.25%.
+        contract.update_step_interval(U256::from(250)).unwrap();
+        let reward = contract.calculate_reward_streamed(U256::from(10000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        assert_eq!(reward, U256::from(8125));
+
+        // Steps of 300s over 1000s: 100%, 85%, 70% for 300s each, then 55% for the last 100s.
+        contract.update_step_interval(U256::from(300)).unwrap();
+        let reward = contract.calculate_reward_streamed(U256::from(10000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        assert_eq!(reward, U256::from(8200));
+
+        // An interval longer than the window never steps down.
+        contract.update_step_interval(U256::from(5000)).unwrap();
+        let reward = contract.calculate_reward_streamed(U256::from(10000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        assert_eq!(reward, U256::from(10000));
+    }
+
+    #[test]
+    fn test_effective_apr() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let year = U256::from(SECONDS_PER_YEAR);
+        let start_time = U256::from(1000);
+
+        // A full 100% multiplier over one year is a 100% APR.
+        let apr = contract.effective_apr(start_time, start_time + year, false, false).unwrap();
+        assert_eq!(apr, U256::from(10000));
+
+        // The same rate earned over half a year doubles when annualized.
+        let apr = contract.effective_apr(start_time, start_time + year / U256::from(2), true, false).unwrap();
+        assert_eq!(apr, U256::from(22000));
+
+        // Halfway through the window the multiplier has already decayed to 75%.
+        vm.set_block_timestamp(1000 + SECONDS_PER_YEAR / 2);
+        let apr = contract.effective_apr(start_time, start_time + year, false, true).unwrap();
+        assert_eq!(apr, U256::from(12500));
+
+        let invalid = contract.effective_apr(start_time, start_time, false, false);
+        assert!(matches!(invalid, Err(CommonError::InvalidTimeWindow(_))));
+    }
+
+    #[test]
+    fn test_effective_apy() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let year = U256::from(SECONDS_PER_YEAR);
+        let start_time = U256::from(1000);
+
+        // 100% per half-year window compounds twice: 2 * 2 - 1 = 300%.
+        let apy = contract.effective_apy(start_time, start_time + year / U256::from(2), false, false).unwrap();
+        assert_eq!(apy, U256::from(30000));
+
+        // Windows of a year or more fall back to the APR.
+        let apy = contract.effective_apy(start_time, start_time + year * U256::from(2), false, false).unwrap();
+        let apr = contract.effective_apr(start_time, start_time + year * U256::from(2), false, false).unwrap();
+        assert_eq!(apy, apr);
+        assert_eq!(apy, U256::from(5000));
+    }
+
+    #[test]
+    fn test_donation_preferences() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let beneficiary = Address::new([0x0d; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        vm.set_sender(user);
+        assert!(matches!(contract.set_donation(beneficiary, U256::from(500)), Err(CommonError::InvalidDonation(_))));
+
+        vm.set_sender(owner);
+        contract.set_donation_beneficiary(beneficiary, true).unwrap();
+        assert!(contract.is_donation_beneficiary(beneficiary));
+
+        vm.set_sender(user);
+        assert!(matches!(contract.set_donation(beneficiary, U256::from(10001)), Err(CommonError::InvalidDonation(_))));
+        contract.set_donation(beneficiary, U256::from(500)).unwrap();
+        assert_eq!(contract.donation_of(user), (beneficiary, U256::from(500)));
+        assert_eq!(contract.donation_split(user, U256::from(1000)).unwrap(), (U256::from(50), U256::from(950)));
+        assert_eq!(contract.donated_total(beneficiary), U256::ZERO);
+
+        contract.set_donation(beneficiary, U256::ZERO).unwrap();
+        assert_eq!(contract.donation_of(user), (Address::ZERO, U256::ZERO));
+        assert_eq!(contract.donation_split(user, U256::from(1000)).unwrap(), (U256::ZERO, U256::from(1000)));
+    }
+
+    #[test]
+    fn test_donation_stops_when_beneficiary_removed() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let beneficiary = Address::new([0x0d; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.set_donation_beneficiary(beneficiary, true).unwrap();
+
+        vm.set_sender(user);
+        contract.set_donation(beneficiary, U256::from(2500)).unwrap();
+        assert!(matches!(contract.set_donation_beneficiary(beneficiary, false), Err(CommonError::Unauthorized(_))));
+
+        vm.set_sender(owner);
+        contract.set_donation_beneficiary(beneficiary, false).unwrap();
+        assert_eq!(contract.donation_split(user, U256::from(1000)).unwrap(), (U256::ZERO, U256::from(1000)));
+    }
+
+    #[test]
+    fn test_aliased_owner_requires_flag() {
+        let owner = Address::new([0x01; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        vm.set_sender(apply_l1_alias(owner));
+        assert!(matches!(contract.update_percentage_bonus(U256::from(2000)), Err(CommonError::Unauthorized(_))));
+
+        vm.set_sender(owner);
+        contract.set_accept_aliased_callers(true).unwrap();
+        assert!(contract.accepts_aliased_callers());
+
+        vm.set_sender(apply_l1_alias(owner));
+        contract.update_percentage_bonus(U256::from(2000)).unwrap();
+
+        // Only the alias of the owner is accepted, not the owner's address shifted the other way.
+        vm.set_sender(undo_l1_alias(owner));
+        assert!(matches!(contract.update_percentage_bonus(U256::from(3000)), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_aliased_operator_can_accrue() {
+        let owner = Address::new([0x01; 20]);
+        let operator = Address::new([0x03; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.set_operator(operator, true).unwrap();
+
+        vm.set_sender(apply_l1_alias(operator));
+        let denied = contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false);
+        assert!(matches!(denied, Err(CommonError::Unauthorized(_))));
+
+        vm.set_sender(owner);
+        contract.set_accept_aliased_callers(true).unwrap();
+
+        vm.set_sender(apply_l1_alias(operator));
+        assert!(contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).is_ok());
+    }
+
+    #[test]
+    fn test_wad_precision_keeps_sub_bps_decay() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        // One third of the way through the exact multiplier is 83.333...%; basis points round the decay down to 83.34%.
+        let amount = U256::from(3_000_000);
+        let (start_time, end_time, now) = (U256::from(0), U256::from(3), U256::from(1));
+
+        let coarse = contract.calculate_reward_at_time(amount, now, start_time, end_time, false, false).unwrap();
+        assert_eq!(coarse, U256::from(2_500_200));
+
+        contract.update_wad_precision(true).unwrap();
+        assert!(contract.wad_precision());
+        let precise = contract.calculate_reward_at_time(amount, now, start_time, end_time, false, false).unwrap();
+        assert_eq!(precise, U256::from(2_500_000));
+
+        let breakdown = contract.calculate_reward_detailed(amount, now, start_time, end_time, false, false).unwrap();
+        assert_eq!(breakdown.time_multiplier, U256::from(8333));
+        assert_eq!(breakdown.decayed, precise);
+    }
+
+    #[test]
+    fn test_wad_precision_matches_at_window_edges() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_wad_precision(true).unwrap();
+
+        let amount = U256::from(1000);
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(500), U256::from(1000), U256::from(2000), false, false).unwrap(), U256::from(1000));
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(1500), U256::from(1000), U256::from(2000), false, false).unwrap(), U256::from(750));
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(2500), U256::from(1000), U256::from(2000), false, false).unwrap(), U256::from(500));
+    }
+
+    #[test]
+    fn test_early_bird_bonus_before_window() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let amount = U256::from(1000);
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(100), start_time, end_time, false, false).unwrap(), U256::from(1000));
+
+        contract.update_early_bird(U256::from(300), U256::from(500)).unwrap();
+        assert_eq!(contract.early_bird(), (U256::from(300), U256::from(500)));
+
+        // Strictly before start_time - window earns the boost, from the cutoff onwards it does not.
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(699), start_time, end_time, false, false).unwrap(), U256::from(1050));
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(700), start_time, end_time, false, false).unwrap(), U256::from(1000));
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(1500), start_time, end_time, false, false).unwrap(), U256::from(750));
+
+        let breakdown = contract.calculate_reward_detailed(amount, U256::from(0), start_time, end_time, true, false).unwrap();
+        assert_eq!(breakdown.bonus, U256::from(150));
+    }
+
+    #[test]
+    fn test_early_bird_window_longer_than_start() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_early_bird(U256::from(5000), U256::from(500)).unwrap();
+
+        let reward = contract.calculate_reward_at_time(U256::from(1000), U256::from(0), U256::from(1000), U256::from(2000), false, false).unwrap();
+        assert_eq!(reward, U256::from(1000));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.update_early_bird(U256::from(1), U256::from(1)), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_pause_for_expires_on_its_own() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.set_guardian(GUARDIAN).unwrap();
+
+        vm.set_sender(user);
+        assert!(matches!(contract.pause_for(U256::from(100)), Err(CommonError::Unauthorized(_))));
+
+        vm.set_sender(GUARDIAN);
+        assert!(matches!(contract.pause_for(U256::ZERO), Err(CommonError::ZeroValue(_))));
+        contract.pause_for(U256::from(100)).unwrap();
+        assert!(contract.is_paused());
+        assert_eq!(contract.paused_until(), U256::from(1100));
+
+        vm.set_sender(owner);
+        let paused = contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false);
+        assert!(matches!(paused, Err(CommonError::ContractPaused(_))));
+        assert!(matches!(contract.record_participation(user), Err(CommonError::ContractPaused(_))));
+
+        vm.set_block_timestamp(1100);
+        assert!(!contract.is_paused());
+        assert!(contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).is_ok());
+    }
+
+    #[test]
+    fn test_try_unpause() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        contract.pause_for(U256::from(100)).unwrap();
+
+        vm.set_sender(user);
+        assert!(matches!(contract.try_unpause(), Err(CommonError::PauseNotExpired(_))));
+        vm.set_block_timestamp(1100);
+        contract.try_unpause().unwrap();
+        assert_eq!(contract.paused_until(), U256::ZERO);
+
+        // The owner can lift a pause early.
+        vm.set_sender(owner);
+        contract.pause_for(U256::from(100)).unwrap();
+        contract.try_unpause().unwrap();
+        assert!(!contract.is_paused());
+    }
+
+    #[test]
+    fn test_grace_period_holds_last_multiplier() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let amount = U256::from(10000);
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+        contract.update_grace_period(U256::from(300)).unwrap();
+        assert_eq!(contract.grace_period(), U256::from(300));
+
+        let last_in_window = contract.calculate_reward_at_time(amount, U256::from(1999), start_time, end_time, false, false).unwrap();
+        assert_eq!(last_in_window, U256::from(5005));
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(2000), start_time, end_time, false, false).unwrap(), last_in_window);
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(2299), start_time, end_time, false, false).unwrap(), last_in_window);
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(2300), start_time, end_time, false, false).unwrap(), U256::from(5000));
+    }
+
+    #[test]
+    fn test_grace_period_with_step_decay() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_decay_mode(DecayMode::Step as u8).unwrap();
+        contract.update_step_interval(U256::from(250)).unwrap();
+        contract.update_grace_period(U256::from(100)).unwrap();
+
+        // The last step before end_time is 62.5%, which the grace period preserves.
+        let amount = U256::from(10000);
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(2050), start_time, end_time, false, false).unwrap(), U256::from(6250));
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(2100), start_time, end_time, false, false).unwrap(), U256::from(5000));
+    }
+
+    #[test]
+    fn test_bonus_policy_flag_combinations() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let amount = U256::from(1000);
+        let (now, start_time, end_time) = (U256::from(1000), U256::from(1000), U256::from(2000));
+        let cases = [
+            (BonusPolicy::Additive, [(false, false, 1000u64), (true, false, 1100), (false, true, 1500), (true, true, 1600)]),
+            (BonusPolicy::StrictOverridesPercentage, [(false, false, 1000), (true, false, 1100), (false, true, 1500), (true, true, 1500)]),
+        ];
+
+        for (policy, combinations) in cases {
+            contract.update_bonus_policy(policy as u8).unwrap();
+            assert_eq!(contract.bonus_policy(), policy as u8);
+            for (has_bonus, has_strict_bonus, expected) in combinations {
+                let reward = contract.calculate_reward_at_time(amount, now, start_time, end_time, has_bonus, has_strict_bonus).unwrap();
+                assert_eq!(reward, U256::from(expected));
+            }
+        }
+    }
+
+    #[test]
+    fn test_update_bonus_policy_rejects_unknown_and_non_owner() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        assert!(matches!(contract.update_bonus_policy(u8::MAX), Err(CommonError::InvalidBonusPolicy(_))));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.update_bonus_policy(1), Err(CommonError::Unauthorized(_))));
+        assert_eq!(contract.bonus_policy(), 0);
+    }
+
+    #[test]
+    fn test_two_segment_decay() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        assert!(matches!(contract.update_decay_mode(DecayMode::TwoSegment as u8), Err(CommonError::InvalidDecayMidpoint(_))));
+
+        // Fast then slow: down to 60% after the first 20% of the window.
+        contract.update_decay_midpoint(U256::from(2000), U256::from(6000)).unwrap();
+        contract.update_decay_mode(DecayMode::TwoSegment as u8).unwrap();
+        assert_eq!(contract.decay_midpoint(), (U256::from(2000), U256::from(6000)));
+
+        let amount = U256::from(10000);
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+        let expected = [(1000u64, 10000u64), (1100, 8000), (1200, 6000), (1600, 5500), (2000, 5000)];
+        for (time, reward) in expected {
+            let actual = contract.calculate_reward_at_time(amount, U256::from(time), start_time, end_time, false, false).unwrap();
+            assert_eq!(actual, U256::from(reward));
+        }
+
+        // Averages 0.2 * 80% + 0.8 * 55% = 60% when streamed.
+        let streamed = contract.calculate_reward_streamed(amount, start_time, end_time, false, false).unwrap();
+        assert_eq!(streamed, U256::from(6000));
+    }
+
+    #[test]
+    fn test_update_decay_midpoint_validation() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        for (fraction, multiplier) in [(0u64, 7000u64), (10000, 7000), (5000, 4999), (5000, 10001)] {
+            let result = contract.update_decay_midpoint(U256::from(fraction), U256::from(multiplier));
+            assert!(matches!(result, Err(CommonError::InvalidDecayMidpoint(_))));
+        }
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.update_decay_midpoint(U256::from(5000), U256::from(9000)), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_growth_mode_inverts_multiplier() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_growth_mode(true).unwrap();
+        assert!(contract.growth_mode());
+
+        let amount = U256::from(1000);
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+        let expected = [(500u64, 500u64), (1000, 500), (1250, 625), (1500, 750), (2000, 1000), (3000, 1000)];
+        for (time, reward) in expected {
+            let actual = contract.calculate_reward_at_time(amount, U256::from(time), start_time, end_time, false, false).unwrap();
+            assert_eq!(actual, U256::from(reward));
+        }
+
+        let streamed = contract.calculate_reward_streamed(U256::from(10000), start_time, end_time, false, false).unwrap();
+        assert_eq!(streamed, U256::from(7500));
+    }
+
+    #[test]
+    fn test_growth_mode_with_step_decay() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_decay_mode(DecayMode::Step as u8).unwrap();
+        contract.update_step_interval(U256::from(250)).unwrap();
+        contract.update_growth_mode(true).unwrap();
+
+        // Steps of 50%, 62.5%, 75% and 87.5% average to 68.75%.
+        let amount = U256::from(10000);
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+        assert_eq!(contract.calculate_reward_at_time(amount, U256::from(1300), start_time, end_time, false, false).unwrap(), U256::from(6250));
+        assert_eq!(contract.calculate_reward_streamed(amount, start_time, end_time, false, false).unwrap(), U256::from(6875));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.update_growth_mode(false), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_sybil_scorer_rejects_low_scores() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let scorer = Address::new([0x5c; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        assert!(matches!(contract.update_sybil_scorer(scorer, U256::from(20), u8::MAX, 0, false), Err(CommonError::InvalidSybilPolicy(_))));
+        contract.update_sybil_scorer(scorer, U256::from(20), SybilPolicy::Reject as u8, 100_000, false).unwrap();
+        assert_eq!(contract.sybil_config(), (scorer, U256::from(20), 0, 100_000, false));
+
+        mock_sybil_score(&vm, scorer, user, 19);
+        let rejected = contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), true, false);
+        assert!(matches!(rejected, Err(CommonError::SybilScoreTooLow(_))));
+
+        mock_sybil_score(&vm, scorer, user, 20);
+        let reward = contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), true, false).unwrap();
+        assert_eq!(reward, U256::from(1100));
+    }
+
+    #[test]
+    fn test_sybil_scorer_strips_bonuses() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let scorer = Address::new([0x5c; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_sybil_scorer(scorer, U256::from(20), SybilPolicy::StripBonuses as u8, 0, false).unwrap();
+
+        mock_sybil_score(&vm, scorer, user, 5);
+        let reward = contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), true, true).unwrap();
+        assert_eq!(reward, U256::from(1000));
+    }
+
+    #[test]
+    fn test_sybil_scorer_outage_fail_open_and_closed() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let scorer = Address::new([0x5c; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let calldata = ISybilScorer::scoreCall { account: user }.abi_encode();
+        vm.mock_static_call(scorer, calldata, Err(Vec::new()));
+
+        contract.update_sybil_scorer(scorer, U256::from(20), SybilPolicy::Reject as u8, 0, false).unwrap();
+        let closed = contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), true, false);
+        assert!(matches!(closed, Err(CommonError::ScorerUnavailable(_))));
+
+        contract.update_sybil_scorer(scorer, U256::from(20), SybilPolicy::Reject as u8, 0, true).unwrap();
+        let open = contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), true, false).unwrap();
+        assert_eq!(open, U256::from(1100));
+    }
+
+    #[test]
+    fn test_sigmoid_decay_reference_values() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_decay_mode(DecayMode::Sigmoid as u8).unwrap();
+
+        // Reference values of 10000 - floor(5000 * (3x² - 2x³)) for x = elapsed / 1000.
+        let amount = U256::from(10000);
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+        let fixtures = [
+            (0u64, 10000u64),
+            (50, 9964),
+            (100, 9860),
+            (250, 9219),
+            (400, 8240),
+            (500, 7500),
+            (600, 6760),
+            (750, 5782),
+            (900, 5140),
+            (999, 5001),
+            (1000, 5000),
+        ];
+        for (elapsed, expected) in fixtures {
+            let reward = contract.calculate_reward_at_time(amount, start_time + U256::from(elapsed), start_time, end_time, false, false).unwrap();
+            assert_eq!(reward, U256::from(expected), "elapsed {elapsed}");
+        }
+
+        let streamed = contract.calculate_reward_streamed(amount, start_time, end_time, false, false).unwrap();
+        assert_eq!(streamed, U256::from(7500));
+    }
+
+    #[test]
+    fn test_campaign_factors() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let (campaign_a, campaign_b) = (U256::from(1), U256::from(2));
+        for _ in 0..2 {
+            let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+            contract.activate_campaign(campaign_id).unwrap();
+        }
+        contract.set_campaign_factor(campaign_a, U256::from(20000)).unwrap();
+        assert_eq!(contract.campaign_factor(campaign_a), U256::from(20000));
+        assert_eq!(contract.campaign_factor(campaign_b), U256::from(5000));
+
+        let amount = U256::from(1000);
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+        assert_eq!(contract.calculate_reward_for_campaign(campaign_a, amount, start_time, end_time, false, true).unwrap(), U256::from(3000));
+        assert_eq!(contract.calculate_reward_for_campaign(campaign_b, amount, start_time, end_time, false, true).unwrap(), U256::from(1500));
+        assert_eq!(contract.calculate_reward(amount, start_time, end_time, false, true).unwrap(), U256::from(1500));
+
+        // Resetting to zero falls back to the global factor.
+        contract.set_campaign_factor(campaign_a, U256::ZERO).unwrap();
+        assert_eq!(contract.calculate_reward_for_campaign(campaign_a, amount, start_time, end_time, false, true).unwrap(), U256::from(1500));
+    }
+
+    #[test]
+    fn test_set_campaign_factor_validation() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let too_large = U256::from(DEFAULT_MAX_MULTIPLY_FACTOR + 1);
+        assert!(matches!(contract.set_campaign_factor(U256::from(1), U256::from(100)), Err(CommonError::CampaignNotFound(_))));
+        contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        assert!(matches!(contract.set_campaign_factor(U256::from(1), too_large), Err(CommonError::InvalidMultiplyFactor(_))));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.set_campaign_factor(U256::from(1), U256::from(100)), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_bonus_expiry() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_bonus_expiry(U256::from(1200), U256::from(1400)).unwrap();
+        assert_eq!(contract.bonus_expiry(), (U256::from(1200), U256::from(1400)));
+
+        let amount = U256::from(1000);
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+        let reward_at = |contract: &RewardProcessor, time: u64| contract.calculate_reward_at_time(amount, U256::from(time), start_time, end_time, true, true).unwrap();
+
+        // Multiplier + 10% + 50% while both are valid, the percentage bonus drops after 1200, the strict bonus after 1400.
+        assert_eq!(reward_at(&contract, 1200), U256::from(900 + 100 + 500));
+        assert_eq!(reward_at(&contract, 1201), U256::from(899 + 500));
+        assert_eq!(reward_at(&contract, 1400), U256::from(800 + 500));
+        assert_eq!(reward_at(&contract, 1401), U256::from(799));
+    }
+
+    #[test]
+    fn test_expired_strict_bonus_no_longer_overrides_percentage() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_bonus_policy(BonusPolicy::StrictOverridesPercentage as u8).unwrap();
+        contract.update_bonus_expiry(U256::ZERO, U256::from(500)).unwrap();
+
+        let reward = contract.calculate_reward_at_time(U256::from(1000), U256::from(1000), U256::from(1000), U256::from(2000), true, true).unwrap();
+        assert_eq!(reward, U256::from(1100));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.update_bonus_expiry(U256::ZERO, U256::ZERO), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_window_status_phases() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+        let cases = [
+            (500u64, (0u64, 1500u64, 10000u64, WindowPhase::PreStart)),
+            (1000, (0, 1000, 10000, WindowPhase::Active)),
+            (1250, (250, 750, 8750, WindowPhase::Active)),
+            (2000, (1000, 0, 5000, WindowPhase::Active)),
+            (2500, (1000, 0, 5000, WindowPhase::Ended)),
+        ];
+        for (now, (elapsed, remaining, multiplier, phase)) in cases {
+            vm.set_block_timestamp(now);
+            let status = contract.window_status(start_time, end_time).unwrap();
+            assert_eq!(status, (U256::from(elapsed), U256::from(remaining), U256::from(multiplier), phase as u8), "at {now}");
+        }
+
+        assert!(matches!(contract.window_status(end_time, start_time), Err(CommonError::InvalidTimeWindow(_))));
+    }
+
+    #[test]
+    fn test_window_status_follows_boundary_policy() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_boundary_policy(BoundaryPolicy::ExclusiveStartExclusiveEnd as u8).unwrap();
+
+        vm.set_block_timestamp(1000);
+        let (_, _, _, phase) = contract.window_status(U256::from(1000), U256::from(2000)).unwrap();
+        assert_eq!(phase, WindowPhase::PreStart as u8);
+
+        vm.set_block_timestamp(2000);
+        let (_, _, _, phase) = contract.window_status(U256::from(1000), U256::from(2000)).unwrap();
+        assert_eq!(phase, WindowPhase::Ended as u8);
+    }
+
+    #[test]
+    fn test_reward_accrued_verbose_by_default() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+
+        let logs = vm.get_emitted_logs();
+        let accrued = RewardAccrued::decode_raw_log(logs[0].0.iter().copied(), &logs[0].1, true).unwrap();
+        assert_eq!(accrued.user, user);
+        assert_eq!(accrued.reward, U256::from(1000));
+        assert_eq!(accrued.seq, U256::from(1));
+    }
+
+    #[test]
+    fn test_reward_accrued_compact_mode() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_compact_events(true).unwrap();
+        assert!(contract.compact_events());
+
+        contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        // Amounts beyond uint128 fall back to the verbose event.
+        contract.accrue(user, U256::from(1) << 130, U256::from(1000), U256::from(2000), false, false).unwrap();
+
+        let logs = vm.get_emitted_logs();
+        assert_eq!(logs.len(), 3);
+
+        let compact = RewardAccruedCompact::decode_raw_log(logs[1].0.iter().copied(), &logs[1].1, true).unwrap();
+        assert_eq!(compact.user, user);
+        assert_eq!(compact.packed_amounts >> 128, U256::from(1000));
+        assert_eq!(compact.packed_amounts & U256::from(u128::MAX), U256::from(1000));
+        assert_eq!(compact.seq, U256::from(2));
+        assert_eq!(logs[1].1.len(), 2 * 32);
+
+        let verbose = RewardAccrued::decode_raw_log(logs[2].0.iter().copied(), &logs[2].1, true).unwrap();
+        assert_eq!(verbose.reward, U256::from(1) << 130);
+        assert_eq!(verbose.seq, U256::from(3));
+        assert_eq!(contract.last_event_seq(), U256::from(3));
+    }
+
+    #[test]
+    fn test_penalty_subtracts_from_reward() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        vm.set_block_timestamp(1500);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_penalty(U256::from(2000)).unwrap();
+        assert_eq!(contract.penalty_bps(), U256::from(2000));
+
+        let amount = U256::from(1000);
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+        assert_eq!(contract.calculate_reward_with_penalty(amount, start_time, end_time, true, false, false).unwrap(), U256::from(850));
+        assert_eq!(contract.calculate_reward_with_penalty(amount, start_time, end_time, true, false, true).unwrap(), U256::from(650));
+    }
+
+    #[test]
+    fn test_penalty_never_goes_below_zero() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        vm.set_block_timestamp(2000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_penalty(U256::from(30000)).unwrap();
+
+        let reward = contract.calculate_reward_with_penalty(U256::from(1000), U256::from(1000), U256::from(2000), true, true, true).unwrap();
+        assert_eq!(reward, U256::ZERO);
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.update_penalty(U256::ZERO), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_lucky_draw_commit_reveal_settle() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let secret = B256::repeat_byte(0x42);
+        // A 100% win chance makes every entrant a winner.
+        contract.commit_lucky_draw(keccak(secret), U256::from(5000), U256::from(10000)).unwrap();
+        assert_eq!(contract.lucky_draw_round(), U256::from(1));
+        assert!(matches!(contract.commit_lucky_draw(keccak(secret), U256::ZERO, U256::ZERO), Err(CommonError::DrawInProgress(_))));
+
+        contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        assert_eq!(contract.lucky_draw_entry(U256::from(1), user), U256::from(1000));
+        assert!(matches!(contract.settle_lucky_draw(U256::from(1), user), Err(CommonError::DrawNotRevealed(_))));
+
+        assert!(matches!(contract.reveal_lucky_draw(B256::repeat_byte(0x43)), Err(CommonError::InvalidReveal(_))));
+        contract.reveal_lucky_draw(secret).unwrap();
+        assert!(contract.is_lucky_winner(U256::from(1), user));
+
+        // Accruals after the reveal no longer enter the round.
+        contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        assert_eq!(contract.lucky_draw_entry(U256::from(1), user), U256::from(1000));
+
+        vm.set_sender(Address::new([0x09; 20]));
+        assert_eq!(contract.settle_lucky_draw(U256::from(1), user).unwrap(), U256::from(500));
+        assert_eq!(contract.compounded_balance_of(user).unwrap(), U256::from(2500));
+        assert!(matches!(contract.settle_lucky_draw(U256::from(1), user), Err(CommonError::DrawAlreadySettled(_))));
+    }
+
+    #[test]
+    fn test_lucky_draw_losers_and_non_entrants() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        vm.set_block_timestamp(1000);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let secret = B256::repeat_byte(0x42);
+        contract.commit_lucky_draw(keccak(secret), U256::from(5000), U256::ZERO).unwrap();
+        contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        contract.reveal_lucky_draw(secret).unwrap();
+        assert!(matches!(contract.reveal_lucky_draw(secret), Err(CommonError::DrawNotOpen(_))));
+
+        assert!(!contract.is_lucky_winner(U256::from(1), user));
+        assert!(!contract.is_lucky_winner(U256::from(1), Address::new([0x03; 20])));
+        assert!(matches!(contract.settle_lucky_draw(U256::from(1), user), Err(CommonError::NotDrawWinner(_))));
+
+        // The next round can be committed once the previous one is revealed.
+        contract.commit_lucky_draw(keccak(secret), U256::from(5000), U256::from(10000)).unwrap();
+        assert_eq!(contract.lucky_draw_round(), U256::from(2));
+    }
+
+    #[test]
+    fn test_sqrt_bonus_scaling() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_sqrt_bonus_scaling(U256::from(10000)).unwrap();
+        assert_eq!(contract.sqrt_bonus_reference(), U256::from(10000));
+
+        let (now, start_time, end_time) = (U256::from(1000), U256::from(1000), U256::from(2000));
+
+        // At the reference amount the bonus is unchanged: 10000 + 10% + 50%.
+        let at_reference = contract.calculate_reward_detailed(U256::from(10000), now, start_time, end_time, true, true).unwrap();
+        assert_eq!((at_reference.bonus, at_reference.strict_bonus), (U256::from(1000), U256::from(5000)));
+
+        // A whale with 100x the amount gets only 10x the bonus, while the decayed part stays linear.
+        let whale = contract.calculate_reward_detailed(U256::from(1_000_000), now, start_time, end_time, true, true).unwrap();
+        assert_eq!(whale.decayed, U256::from(1_000_000));
+        assert_eq!((whale.bonus, whale.strict_bonus), (U256::from(10000), U256::from(50000)));
+
+        // A small position with 1/100 of the amount gets 1/10 of the bonus.
+        let small = contract.calculate_reward_detailed(U256::from(100), now, start_time, end_time, true, false).unwrap();
+        assert_eq!(small.bonus, U256::from(100));
+    }
+
+    #[test]
+    fn test_sqrt_bonus_scaling_disabled_and_owner_only() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        contract.update_sqrt_bonus_scaling(U256::from(10000)).unwrap();
+        contract.update_sqrt_bonus_scaling(U256::ZERO).unwrap();
+        let reward = contract.calculate_reward_at_time(U256::from(1_000_000), U256::from(1000), U256::from(1000), U256::from(2000), true, false).unwrap();
+        assert_eq!(reward, U256::from(1_100_000));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.update_sqrt_bonus_scaling(U256::from(1)), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_reward_decimals_normalization() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        assert_eq!(contract.reward_decimals(), 18);
+        assert_eq!(contract.to_internal(U256::from(123)).unwrap(), U256::from(123));
+
+        contract.set_reward_decimals(6).unwrap();
+        let one_usdc = U256::from(1_000_000);
+        assert_eq!(contract.to_internal(one_usdc).unwrap(), U256::from(10).pow(U256::from(18)));
+        assert_eq!(contract.from_internal(U256::from(10).pow(U256::from(18))), one_usdc);
+        assert_eq!(contract.from_internal(U256::from(999_999_999_999u64)), U256::ZERO);
+
+        contract.set_reward_decimals(24).unwrap();
+        assert_eq!(contract.to_internal(U256::from(1_000_000)).unwrap(), U256::from(1));
+        assert_eq!(contract.from_internal(U256::from(1)), U256::from(1_000_000));
+    }
+
+    #[test]
+    fn test_reward_decimals_keep_results_in_token_units() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let (now, start_time, end_time) = (U256::from(1500), U256::from(1000), U256::from(2000));
+
+        // Rounding each 6-decimal term separately would give 2 + 0 + 1; internally only the total is floored.
+        contract.set_reward_decimals(6).unwrap();
+        let breakdown = contract.calculate_reward_detailed(U256::from(3), now, start_time, end_time, true, true).unwrap();
+        assert_eq!((breakdown.base, breakdown.decayed, breakdown.bonus, breakdown.strict_bonus), (U256::from(3), U256::from(2), U256::ZERO, U256::from(1)));
+        assert_eq!(breakdown.total, U256::from(4));
+        let usdc = contract.calculate_reward_at_time(U256::from(10_000_000), now, start_time, end_time, true, true).unwrap();
+
+        contract.set_reward_decimals(18).unwrap();
+        let wei = contract.calculate_reward_at_time(U256::from(10).pow(U256::from(19)), now, start_time, end_time, true, true).unwrap();
+        assert_eq!(usdc * U256::from(10).pow(U256::from(12)), wei);
+
+        assert!(matches!(contract.set_reward_decimals(MAX_REWARD_DECIMALS + 1), Err(CommonError::InvalidDecimals(_))));
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.set_reward_decimals(6), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_dust_threshold_zeroes_small_rewards() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_dust_threshold(U256::from(100), false).unwrap();
+        assert_eq!(contract.dust_threshold(), (U256::from(100), false));
+
+        let (now, start_time, end_time) = (U256::from(1000), U256::from(1000), U256::from(2000));
+        assert_eq!(contract.calculate_reward_at_time(U256::from(99), now, start_time, end_time, false, false).unwrap(), U256::ZERO);
+        assert_eq!(contract.calculate_reward_at_time(U256::from(100), now, start_time, end_time, false, false).unwrap(), U256::from(100));
+
+        // The breakdown still shows the pieces that were zeroed.
+        let breakdown = contract.calculate_reward_detailed(U256::from(50), now, start_time, end_time, false, false).unwrap();
+        assert_eq!((breakdown.decayed, breakdown.total), (U256::from(50), U256::ZERO));
+    }
+
+    #[test]
+    fn test_dust_threshold_can_revert() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_dust_threshold(U256::from(100), true).unwrap();
+
+        let (now, start_time, end_time) = (U256::from(1000), U256::from(1000), U256::from(2000));
+        assert!(matches!(
+            contract.calculate_reward_at_time(U256::from(99), now, start_time, end_time, false, false),
+            Err(CommonError::ZeroValue(_))
+        ));
+        assert!(matches!(contract.calculate_reward_streamed(U256::from(10), start_time, end_time, false, false), Err(CommonError::ZeroValue(_))));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.update_dust_threshold(U256::ZERO, false), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_calculate_reward_u64_matches_u256() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_block_timestamp(1500);
+
+        let amount = U256::from(1_000_000);
+        let wide = contract.calculate_reward(amount, U256::from(1000), U256::from(2000), true, false).unwrap();
+        assert_eq!(contract.calculate_reward_u64(amount, 1000, 2000, true, false).unwrap(), wide);
+        assert_eq!(contract.calculate_reward_at_time_u64(amount, 1500, 1000, 2000, true, false).unwrap(), wide);
+    }
+
+    #[test]
+    fn test_calculate_reward_u64_rejects_absurd_timestamps() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let amount = U256::from(1_000_000);
+        assert!(contract.calculate_reward_at_time_u64(amount, 1000, 1000, MAX_TIMESTAMP, false, false).is_ok());
+        assert!(matches!(
+            contract.calculate_reward_at_time_u64(amount, 1000, 1000, MAX_TIMESTAMP + 1, false, false),
+            Err(CommonError::InvalidTimestamp(_))
+        ));
+        assert!(matches!(contract.calculate_reward_u64(amount, u64::MAX - 1, u64::MAX, false, false), Err(CommonError::InvalidTimestamp(_))));
+        assert!(matches!(
+            contract.calculate_reward_at_time_u64(amount, u64::MAX, 1000, 2000, false, false),
+            Err(CommonError::InvalidTimestamp(_))
+        ));
+    }
+
+    #[test]
+    fn test_calculate_and_record_bonus_cooldown() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_bonus_cooldown(U256::from(3600)).unwrap();
+        let user = Address::new([0x02; 20]);
+        let amount = U256::from(1_000_000);
+
+        // Windows start at the current timestamp so only the bonus changes between calls.
+        vm.set_block_timestamp(10_000);
+        let with_bonus = contract.calculate_and_record(user, amount, U256::from(10_000), U256::from(20_000), true, false).unwrap();
+        assert_eq!(with_bonus, U256::from(1_100_000));
+        assert_eq!(contract.last_bonus_at(user), U256::from(10_000));
+
+        vm.set_block_timestamp(13_599);
+        let cooling = contract.calculate_and_record(user, amount, U256::from(13_599), U256::from(23_599), true, false).unwrap();
+        assert_eq!(cooling, amount);
+        assert_eq!(contract.last_bonus_at(user), U256::from(10_000));
+
+        // Other users are unaffected by this user's cooldown.
+        let other = contract.calculate_and_record(Address::new([0x03; 20]), amount, U256::from(13_599), U256::from(23_599), true, false).unwrap();
+        assert_eq!(other, U256::from(1_100_000));
+
+        vm.set_block_timestamp(13_600);
+        let again = contract.calculate_and_record(user, amount, U256::from(13_600), U256::from(23_600), true, false).unwrap();
+        assert_eq!(again, U256::from(1_100_000));
+        assert_eq!(contract.last_bonus_at(user), U256::from(13_600));
+    }
+
+    #[test]
+    fn test_calculate_and_record_requires_operator() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let user = Address::new([0x02; 20]);
+
+        vm.set_sender(user);
+        assert!(matches!(
+            contract.calculate_and_record(user, U256::from(1000), U256::from(1000), U256::from(2000), true, false),
+            Err(CommonError::Unauthorized(_))
+        ));
+        assert!(matches!(contract.update_bonus_cooldown(U256::from(1)), Err(CommonError::Unauthorized(_))));
+
+        // Calls without the bonus flag never stamp the cooldown.
+        vm.set_sender(Address::new([0x01; 20]));
+        contract.calculate_and_record(user, U256::from(1000), U256::from(1000), U256::from(2000), false, true).unwrap();
+        assert_eq!(contract.last_bonus_at(user), U256::ZERO);
+    }
+
+    #[test]
+    fn test_max_total_bonus_caps_combined_bonuses() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let (amount, now, start_time, end_time) = (U256::from(10_000), U256::from(1000), U256::from(1000), U256::from(2000));
+
+        // Uncapped: 10% + 50%.
+        let uncapped = contract.calculate_reward_detailed(amount, now, start_time, end_time, true, true).unwrap();
+        assert_eq!((uncapped.bonus, uncapped.strict_bonus), (U256::from(1000), U256::from(5000)));
+
+        // A 55% cap comes out of the strict bonus first.
+        contract.update_max_total_bonus(U256::from(5500)).unwrap();
+        let capped = contract.calculate_reward_detailed(amount, now, start_time, end_time, true, true).unwrap();
+        assert_eq!((capped.bonus, capped.strict_bonus), (U256::from(500), U256::from(5000)));
+        assert_eq!(contract.calculate_reward_at_time(amount, now, start_time, end_time, true, true).unwrap(), U256::from(15_500));
+
+        contract.update_max_total_bonus(U256::from(3000)).unwrap();
+        let capped = contract.calculate_reward_detailed(amount, now, start_time, end_time, true, true).unwrap();
+        assert_eq!((capped.bonus, capped.strict_bonus), (U256::ZERO, U256::from(3000)));
+
+        // Bonuses already under the cap are untouched.
+        let small = contract.calculate_reward_detailed(amount, now, start_time, end_time, true, false).unwrap();
+        assert_eq!(small.bonus, U256::from(1000));
+    }
+
+    #[test]
+    fn test_update_max_total_bonus_requires_owner() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.update_max_total_bonus(U256::from(2000)).unwrap();
+        assert_eq!(contract.max_total_bonus_bps(), U256::from(2000));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.update_max_total_bonus(U256::ZERO), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_sample_reward_curve() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        let curve = contract.sample_reward_curve(U256::from(10_000), U256::from(1000), U256::from(2000), U256::from(5)).unwrap();
+        let expected: Vec<U256> = [10_000u64, 8750, 7500, 6250, 5000].into_iter().map(U256::from).collect();
+        assert_eq!(curve, expected);
+
+        let single = contract.sample_reward_curve(U256::from(10_000), U256::from(1000), U256::from(2000), U256::from(1)).unwrap();
+        assert_eq!(single, vec![U256::from(10_000)]);
+    }
+
+    #[test]
+    fn test_sample_reward_curve_validation() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let amount = U256::from(10_000);
+
+        assert!(matches!(
+            contract.sample_reward_curve(amount, U256::from(1000), U256::from(2000), U256::ZERO),
+            Err(CommonError::InvalidPointCount(_))
+        ));
+        assert!(matches!(
+            contract.sample_reward_curve(amount, U256::from(1000), U256::from(2000), U256::from(MAX_CURVE_POINTS + 1)),
+            Err(CommonError::InvalidPointCount(_))
+        ));
+        assert!(matches!(
+            contract.sample_reward_curve(amount, U256::from(2000), U256::from(2000), U256::from(2)),
+            Err(CommonError::InvalidTimeWindow(_))
+        ));
+        assert_eq!(contract.sample_reward_curve(amount, U256::from(1000), U256::from(2000), U256::from(MAX_CURVE_POINTS)).unwrap().len(), 256);
+    }
+
+    #[test]
+    fn test_halving_schedule_halves_strict_bonus() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(8000)).unwrap();
+        contract.update_halving_schedule(U256::from(1000), U256::from(500)).unwrap();
+        assert_eq!(contract.halving_schedule(), (U256::from(1000), U256::from(500)));
+
+        let amount = U256::from(10_000);
+        let strict_bonus_at = |contract: &RewardProcessor, time: u64| {
+            let (time, end_time) = (U256::from(time), U256::from(time + 1000));
+            contract.calculate_reward_detailed(amount, time, time, end_time, false, true).unwrap().strict_bonus
+        };
+        assert_eq!(strict_bonus_at(&contract, 500), U256::from(8000));
+        assert_eq!(strict_bonus_at(&contract, 1499), U256::from(8000));
+        assert_eq!(strict_bonus_at(&contract, 1500), U256::from(4000));
+        assert_eq!(strict_bonus_at(&contract, 2000), U256::from(2000));
+
+        vm.set_block_timestamp(2100);
+        assert_eq!(contract.halving_epoch(), U256::from(2));
+        assert_eq!(contract.effective_multiply_factor(), U256::from(2000));
+
+        contract.update_halving_schedule(U256::from(1000), U256::ZERO).unwrap();
+        assert_eq!(contract.halving_epoch(), U256::ZERO);
+        assert_eq!(contract.effective_multiply_factor(), U256::from(8000));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.update_halving_schedule(U256::ZERO, U256::from(1)), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_create_campaign_and_calculate() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+
+        // 20% bonus and 1x strict factor out of 1000, decaying over [2000, 3000].
+        let campaign_id = contract.create_campaign(U256::from(1000), U256::from(200), U256::from(1000), U256::from(2000), U256::from(3000)).unwrap();
+        assert_eq!(campaign_id, U256::from(1));
+        assert_eq!(contract.campaign_count(), U256::from(1));
+        contract.activate_campaign(campaign_id).unwrap();
+        assert_eq!(
+            contract.campaign(campaign_id).unwrap(),
+            (U256::from(1000), U256::from(200), U256::from(1000), U256::from(2000), U256::from(3000))
+        );
+
+        let amount = U256::from(10_000);
+        vm.set_block_timestamp(2500);
+        assert_eq!(contract.calculate_campaign_reward(campaign_id, amount, true, true).unwrap(), U256::from(7500 + 2000 + 10_000));
+        // The global settings are untouched: 10% and 0.5x over the same window.
+        assert_eq!(contract.calculate_reward(amount, U256::from(2000), U256::from(3000), true, true).unwrap(), U256::from(7500 + 1000 + 5000));
+
+        // A campaign with every field unset prices exactly like the global settings.
+        let defaults = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(2000), U256::from(3000)).unwrap();
+        assert_eq!(defaults, U256::from(2));
+        contract.activate_campaign(defaults).unwrap();
+        assert_eq!(contract.calculate_campaign_reward(defaults, amount, true, true).unwrap(), U256::from(7500 + 1000 + 5000));
+    }
+
+    #[test]
+    fn test_create_campaign_validation() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let (start_time, end_time) = (U256::from(1000), U256::from(2000));
+
+        assert!(matches!(
+            contract.create_campaign(U256::from(DEFAULT_MAX_MULTIPLY_FACTOR + 1), U256::ZERO, U256::ZERO, start_time, end_time),
+            Err(CommonError::InvalidMultiplyFactor(_))
+        ));
+        assert!(matches!(
+            contract.create_campaign(U256::ZERO, U256::from(2000), U256::from(1000), start_time, end_time),
+            Err(CommonError::InvalidDenominator(_))
+        ));
+        assert!(matches!(contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, end_time, start_time), Err(CommonError::InvalidTimeWindow(_))));
+        assert!(matches!(contract.calculate_campaign_reward(U256::from(1), U256::from(1), false, false), Err(CommonError::CampaignNotFound(_))));
+        assert!(matches!(contract.campaign(U256::from(1)), Err(CommonError::CampaignNotFound(_))));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, start_time, end_time), Err(CommonError::Unauthorized(_))));
+        assert_eq!(contract.campaign_count(), U256::ZERO);
+    }
+
+    #[test]
+    fn test_campaign_lifecycle() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        let amount = U256::from(1000);
+
+        // Drafts do not price rewards and cannot be finalized.
+        vm.set_block_timestamp(1500);
+        assert_eq!(contract.campaign_state(campaign_id).unwrap(), CampaignState::Draft as u8);
+        assert!(matches!(contract.calculate_campaign_reward(campaign_id, amount, false, false), Err(CommonError::InvalidCampaignState(_))));
+
+        contract.activate_campaign(campaign_id).unwrap();
+        assert_eq!(contract.campaign_state(campaign_id).unwrap(), CampaignState::Active as u8);
+        assert!(contract.calculate_campaign_reward(campaign_id, amount, false, false).is_ok());
+        assert!(matches!(contract.activate_campaign(campaign_id), Err(CommonError::InvalidCampaignState(_))));
+        assert!(matches!(contract.finalize_campaign(campaign_id), Err(CommonError::CampaignNotEnded(_))));
+
+        vm.set_block_timestamp(2001);
+        contract.finalize_campaign(campaign_id).unwrap();
+        assert_eq!(contract.campaign_state(campaign_id).unwrap(), CampaignState::Ended as u8);
+        assert!(matches!(contract.calculate_campaign_reward(campaign_id, amount, false, false), Err(CommonError::InvalidCampaignState(_))));
+        assert!(matches!(contract.cancel_campaign(campaign_id), Err(CommonError::InvalidCampaignState(_))));
+
+        let events = vm.get_emitted_logs();
+        let topics: Vec<_> = events.iter().map(|(topics, _)| topics[0]).collect();
+        assert!(topics.contains(&CampaignActivated::SIGNATURE_HASH));
+        assert!(topics.contains(&CampaignFinalized::SIGNATURE_HASH));
+    }
+
+    #[test]
+    fn test_cancel_campaign() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let draft = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        let active = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.activate_campaign(active).unwrap();
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.cancel_campaign(active), Err(CommonError::Unauthorized(_))));
+        vm.set_sender(Address::new([0x01; 20]));
+
+        contract.cancel_campaign(draft).unwrap();
+        contract.cancel_campaign(active).unwrap();
+        assert_eq!(contract.campaign_state(active).unwrap(), CampaignState::Cancelled as u8);
+        assert!(matches!(contract.activate_campaign(draft), Err(CommonError::InvalidCampaignState(_))));
+        assert!(matches!(
+            contract.calculate_reward_for_campaign(active, U256::from(1000), U256::from(1000), U256::from(2000), false, false),
+            Err(CommonError::InvalidCampaignState(_))
+        ));
+        assert!(matches!(contract.cancel_campaign(U256::from(3)), Err(CommonError::CampaignNotFound(_))));
+    }
+
+    #[test]
+    fn test_campaign_budget_enforced_on_accrual() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(2500)).unwrap();
+        vm.set_block_timestamp(1000);
+
+        let user = Address::new([0x02; 20]);
+        assert_eq!(contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false).unwrap(), U256::from(1000));
+        assert_eq!(contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false).unwrap(), U256::from(1000));
+        assert_eq!(contract.remaining_budget(campaign_id).unwrap(), U256::from(500));
+        assert_eq!(contract.compounded_balance_of(user).unwrap(), U256::from(2000));
+
+        assert!(matches!(
+            contract.accrue_for_campaign(campaign_id, user, U256::from(501), false, false),
+            Err(CommonError::BudgetExceeded(_))
+        ));
+        contract.accrue_for_campaign(campaign_id, user, U256::from(500), false, false).unwrap();
+        assert_eq!(contract.campaign_budget(campaign_id).unwrap(), (U256::from(2500), U256::from(2500)));
+        assert_eq!(contract.remaining_budget(campaign_id).unwrap(), U256::ZERO);
+    }
+
+    #[test]
+    fn test_set_campaign_budget_validation() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        vm.set_block_timestamp(1000);
+
+        // No budget means nothing can be issued, and drafts cannot accrue at all.
+        let user = Address::new([0x02; 20]);
+        assert!(matches!(contract.accrue_for_campaign(campaign_id, user, U256::from(1), false, false), Err(CommonError::InvalidCampaignState(_))));
+        contract.activate_campaign(campaign_id).unwrap();
+        assert!(matches!(contract.accrue_for_campaign(campaign_id, user, U256::from(1), false, false), Err(CommonError::BudgetExceeded(_))));
+
+        contract.set_campaign_budget(campaign_id, U256::from(100)).unwrap();
+        contract.accrue_for_campaign(campaign_id, user, U256::from(60), false, false).unwrap();
+        assert!(matches!(contract.set_campaign_budget(campaign_id, U256::from(59)), Err(CommonError::BudgetExceeded(_))));
+        assert!(matches!(contract.set_campaign_budget(U256::from(9), U256::from(1)), Err(CommonError::CampaignNotFound(_))));
+
+        vm.set_sender(user);
+        assert!(matches!(contract.set_campaign_budget(campaign_id, U256::from(1000)), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_campaign_operators_are_scoped_to_their_campaign() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let ours = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        let theirs = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.activate_campaign(theirs).unwrap();
+        contract.set_campaign_budget(theirs, U256::from(1_000_000)).unwrap();
+
+        let partner = Address::new([0x02; 20]);
+        contract.set_campaign_operator(theirs, partner, true).unwrap();
+        assert!(contract.is_campaign_operator(theirs, partner));
+        assert!(!contract.is_campaign_operator(ours, partner));
+
+        vm.set_sender(partner);
+        contract.set_campaign_factor(theirs, U256::from(20000)).unwrap();
+        contract.set_campaign_bonus(theirs, U256::from(2000)).unwrap();
+        assert_eq!(contract.campaign(theirs).unwrap().1, U256::from(2000));
+        vm.set_block_timestamp(1000);
+        assert_eq!(contract.accrue_for_campaign(theirs, partner, U256::from(1000), true, true).unwrap(), U256::from(1000 + 200 + 2000));
+
+        // No rights over other campaigns or global settings.
+        assert!(matches!(contract.set_campaign_factor(ours, U256::from(20000)), Err(CommonError::Unauthorized(_))));
+        assert!(matches!(contract.set_campaign_bonus(ours, U256::from(2000)), Err(CommonError::Unauthorized(_))));
+        assert!(matches!(contract.update_percentage_bonus(U256::from(2000)), Err(CommonError::Unauthorized(_))));
+        assert!(matches!(contract.set_campaign_operator(theirs, partner, false), Err(CommonError::Unauthorized(_))));
+
+        vm.set_sender(Address::new([0x01; 20]));
+        contract.set_campaign_operator(theirs, partner, false).unwrap();
+        assert!(!contract.is_campaign_operator(theirs, partner));
+        assert_ne!(contract.member_index_of(partner), U256::ZERO);
+        vm.set_sender(partner);
+        assert!(matches!(contract.set_campaign_bonus(theirs, U256::from(1)), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_set_campaign_bonus_validation() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::from(100), U256::from(1000), U256::from(2000)).unwrap();
+
+        contract.set_campaign_bonus(campaign_id, U256::from(100)).unwrap();
+        assert!(matches!(contract.set_campaign_bonus(campaign_id, U256::from(101)), Err(CommonError::InvalidDenominator(_))));
+        assert!(matches!(contract.set_campaign_bonus(U256::from(5), U256::ZERO), Err(CommonError::CampaignNotFound(_))));
+    }
+
+    #[test]
+    fn test_campaign_metadata() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        assert_eq!(contract.campaign_metadata(campaign_id).unwrap(), (B256::ZERO, B256::ZERO));
+
+        let name = B256::right_padding_from(b"Spring Drop");
+        let uri_hash = keccak(b"ipfs://bafybeigdyrzt5sfp7udm7hu76uh7y26nf3efuylqabf3oclgtqy55fbzdi");
+        contract.set_campaign_metadata(campaign_id, name, uri_hash).unwrap();
+        assert_eq!(contract.campaign_metadata(campaign_id).unwrap(), (name, uri_hash));
+
+        let logs = vm.get_emitted_logs();
+        assert_eq!(logs.last().unwrap().0[0], CampaignMetadataUpdated::SIGNATURE_HASH);
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.set_campaign_metadata(campaign_id, B256::ZERO, B256::ZERO), Err(CommonError::Unauthorized(_))));
+        assert!(matches!(contract.campaign_metadata(U256::from(9)), Err(CommonError::CampaignNotFound(_))));
+    }
+
+    #[test]
+    fn test_epoch_checkpoints_pin_bonus_settings() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_block_timestamp(1000);
+
+        let first = contract.roll_epoch().unwrap();
+        assert_eq!(first, U256::from(1));
+        let before = contract.calculate_reward(U256::from(1000), U256::from(1000), U256::from(2000), true, true).unwrap();
+        assert_eq!(before, U256::from(1000 + 100 + 500));
+
+        contract.update_percentage_bonus(U256::from(3000)).unwrap();
+        contract.update_multiply_factor(U256::from(20000)).unwrap();
+        let second = contract.roll_epoch().unwrap();
+        assert_eq!(contract.epoch_checkpoint_count(), U256::from(2));
+
+        assert_eq!(contract.calculate_reward_for_epoch(first, U256::from(1000), U256::from(1000), U256::from(2000), true, true).unwrap(), before);
+        assert_eq!(
+            contract.calculate_reward_for_epoch(second, U256::from(1000), U256::from(1000), U256::from(2000), true, true).unwrap(),
+            contract.calculate_reward(U256::from(1000), U256::from(1000), U256::from(2000), true, true).unwrap(),
+        );
+        assert_eq!(
+            contract.epoch_checkpoint(first).unwrap(),
+            (U256::from(1000), U256::from(10000), U256::from(5000), U256::from(10000), U256::from(1000)),
+        );
+
+        assert!(matches!(contract.epoch_checkpoint(U256::ZERO), Err(CommonError::EpochNotFound(_))));
+        assert!(matches!(
+            contract.calculate_reward_for_epoch(U256::from(3), U256::from(1000), U256::from(1000), U256::from(2000), true, true),
+            Err(CommonError::EpochNotFound(_))
+        ));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.roll_epoch(), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_reward_at_time_uses_settings_in_effect_then() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        vm.set_block_timestamp(100);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let amount = U256::from(1000);
+        let (start, end) = (U256::from(100), U256::from(10_000));
+
+        vm.set_block_timestamp(1000);
+        contract.update_percentage_bonus(U256::from(2000)).unwrap();
+        vm.set_block_timestamp(2000);
+        contract.update_percentage_bonus(U256::from(3000)).unwrap();
+        // A second change in the same block replaces the entry instead of adding one.
+        contract.update_multiply_factor(U256::from(10000)).unwrap();
+
+        let reward_at = |time: u64| contract.calculate_reward_at_time(amount, U256::from(time), start, end, true, false).unwrap();
+        let base_at = |time: u64| contract.calculate_reward_at_time(amount, U256::from(time), start, end, false, false).unwrap();
+        assert_eq!(reward_at(100) - base_at(100), U256::from(100));
+        assert_eq!(reward_at(999) - base_at(999), U256::from(100));
+        assert_eq!(reward_at(1000) - base_at(1000), U256::from(200));
+        assert_eq!(reward_at(1999) - base_at(1999), U256::from(200));
+        assert_eq!(reward_at(2000) - base_at(2000), U256::from(300));
+        // Before deployment, the deployment settings apply.
+        assert_eq!(reward_at(0) - base_at(0), U256::from(100));
+
+        let strict_at = |time: u64| contract.calculate_reward_at_time(amount, U256::from(time), start, end, false, true).unwrap() - base_at(time);
+        assert_eq!(strict_at(1500), U256::from(500));
+        assert_eq!(strict_at(2500), U256::from(1000));
+    }
+
+    #[test]
+    fn test_get_params_at() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        vm.set_block_timestamp(100);
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let deployed = (U256::from(5000), U256::from(1000), U256::from(10000), U256::from(10000));
+        assert_eq!(contract.param_history_count(), U256::from(1));
+
+        vm.set_block_timestamp(500);
+        contract.update_percentage_denominator(U256::from(100_000)).unwrap();
+        contract.update_percentage_bonus(U256::from(2500)).unwrap();
+        vm.set_block_timestamp(900);
+        contract.update_multiply_factor(U256::from(7000)).unwrap();
+        assert_eq!(contract.param_history_count(), U256::from(3));
+
+        assert_eq!(contract.get_params_at(U256::ZERO), deployed);
+        assert_eq!(contract.get_params_at(U256::from(499)), deployed);
+        assert_eq!(contract.get_params_at(U256::from(500)), (U256::from(5000), U256::from(2500), U256::from(100_000), U256::from(10000)));
+        assert_eq!(contract.get_params_at(U256::from(900)), (U256::from(7000), U256::from(2500), U256::from(100_000), U256::from(10000)));
+        assert_eq!(contract.get_params_at(U256::MAX), contract.get_params_at(U256::from(900)));
+    }
+
+    #[test]
+    fn test_issuance_totals() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_block_timestamp(1000);
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(1_000_000)).unwrap();
+
+        let user = Address::new([0x02; 20]);
+        let global = contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), true, false).unwrap();
+        let in_campaign = contract.accrue_for_campaign(campaign_id, user, U256::from(500), false, false).unwrap();
+
+        assert_eq!(contract.total_calculated(), global + in_campaign);
+        assert_eq!(contract.total_claimed(), U256::ZERO);
+        assert_eq!(contract.campaign_totals(campaign_id).unwrap(), (in_campaign, U256::ZERO));
+        // Pure calculations leave the totals alone.
+        contract.calculate_reward(U256::from(1000), U256::from(1000), U256::from(2000), true, true).unwrap();
+        assert_eq!(contract.total_calculated(), global + in_campaign);
+        assert!(matches!(contract.campaign_totals(U256::from(7)), Err(CommonError::CampaignNotFound(_))));
+    }
+
+    #[test]
+    fn test_credit_updates_ledger() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let week = 7 * 24 * 60 * 60;
+        let user = Address::new([0x02; 20]);
+        assert_eq!(contract.ledger_of(user), (U256::ZERO, U256::ZERO, U256::ZERO));
+
+        vm.set_block_timestamp(week);
+        let first = contract.credit(user, U256::from(1000), U256::from(week), U256::from(2 * week), false, false).unwrap();
+        assert_eq!(contract.ledger_of(user), (first, U256::ZERO, U256::from(week)));
+        assert_eq!(contract.balance_of(user).unwrap(), first);
+
+        // Compounding growth is realized into `accrued` on the next credit.
+        contract.update_compound_rate(U256::from(1000)).unwrap();
+        vm.set_block_timestamp(2 * week);
+        let grown = contract.balance_of(user).unwrap();
+        assert_eq!(grown, first + first / U256::from(10));
+        let second = contract.credit(user, U256::from(1000), U256::from(2 * week), U256::from(3 * week), false, false).unwrap();
+        assert_eq!(contract.ledger_of(user), (grown + second, U256::ZERO, U256::from(2 * week)));
+        assert_eq!(contract.balance_of(user).unwrap(), grown + second);
+
+        vm.set_sender(user);
+        assert!(matches!(
+            contract.credit(user, U256::from(1000), U256::from(2 * week), U256::from(3 * week), false, false),
+            Err(CommonError::Unauthorized(_))
+        ));
+    }
+
+    #[test]
+    fn test_accrue_then_claim() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        vm.set_block_timestamp(1000);
+
+        let reward = contract.accrue_reward(user, U256::from(1000), U256::from(1000), U256::from(2000), true, false).unwrap();
+        // Later settings changes do not touch the frozen reward.
+        contract.update_percentage_bonus(U256::from(5000)).unwrap();
+        assert_eq!(contract.balance_of(user).unwrap(), reward);
+
+        vm.set_sender(user);
+        assert_eq!(contract.claim().unwrap(), reward);
+        assert_eq!(vm.balance(user), reward);
+        assert_eq!(contract.balance_of(user).unwrap(), U256::ZERO);
+        assert_eq!(contract.ledger_of(user), (reward, reward, U256::from(1000)));
+        assert_eq!(contract.total_claimed(), reward);
+        assert!(matches!(contract.claim(), Err(CommonError::ZeroValue(_))));
+
+        let logs = vm.get_emitted_logs();
+        let claimed = RewardClaimed::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((claimed.user, claimed.amount, claimed.donation), (user, reward, U256::ZERO));
+    }
+
+    #[test]
+    fn test_claim_routes_donation() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let beneficiary = Address::new([0x0d; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        vm.set_block_timestamp(1000);
+        contract.set_donation_beneficiary(beneficiary, true).unwrap();
+        contract.update_compact_events(true).unwrap();
+        let reward = contract.accrue_reward(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        assert_eq!(reward, U256::from(1000));
+
+        vm.set_sender(user);
+        contract.set_donation(beneficiary, U256::from(500)).unwrap();
+        assert_eq!(contract.claim().unwrap(), reward);
+        assert_eq!(vm.balance(user), U256::from(950));
+        assert_eq!(vm.balance(beneficiary), U256::from(50));
+        assert_eq!(contract.donated_total(beneficiary), U256::from(50));
+
+        let logs = vm.get_emitted_logs();
+        let routed = DonationRouted::decode_raw_log(logs[logs.len() - 2].0.iter().copied(), &logs[logs.len() - 2].1, true).unwrap();
+        assert_eq!((routed.user, routed.beneficiary, routed.amount), (user, beneficiary, U256::from(50)));
+        let compact = RewardClaimedCompact::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!(compact.packed_amounts, (U256::from(1000) << 128) | U256::from(50));
+    }
+
+    #[test]
+    fn test_campaign_vesting_with_cliff() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        assert!(matches!(contract.set_campaign_vesting(campaign_id, U256::from(2000), U256::from(1000)), Err(CommonError::InvalidTimeWindow(_))));
+        contract.set_campaign_vesting(campaign_id, U256::from(100), U256::from(1000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        assert!(matches!(contract.set_campaign_vesting(campaign_id, U256::ZERO, U256::ZERO), Err(CommonError::InvalidCampaignState(_))));
+
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false).unwrap();
+        assert_eq!(reward, U256::from(1000));
+        assert_eq!(contract.balance_of(user).unwrap(), U256::ZERO);
+        assert_eq!(contract.vesting_of(campaign_id, user), (reward, U256::ZERO));
+        assert_eq!(contract.total_calculated(), reward);
+
+        vm.set_sender(user);
+        vm.set_block_timestamp(1099);
+        assert_eq!(contract.vested_of(user).unwrap(), U256::ZERO);
+        assert!(matches!(contract.claim_vested(), Err(CommonError::ZeroValue(_))));
+
+        vm.set_block_timestamp(1400);
+        assert_eq!(contract.vested_of(user).unwrap(), U256::from(400));
+        assert_eq!(contract.claim_vested().unwrap(), U256::from(400));
+        assert_eq!(vm.balance(user), U256::from(400));
+        assert_eq!(contract.vested_of(user).unwrap(), U256::ZERO);
+
+        vm.set_block_timestamp(5000);
+        assert_eq!(contract.claim_vested().unwrap(), U256::from(600));
+        assert_eq!(contract.vesting_of(campaign_id, user), (reward, reward));
+        assert_eq!(contract.campaign_totals(campaign_id).unwrap(), (reward, reward));
+        assert_eq!(contract.total_claimed(), reward);
+    }
+
+    #[test]
+    fn test_campaign_claim_window() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        assert!(matches!(contract.set_campaign_claim_window(campaign_id, U256::from(3000), U256::from(3000)), Err(CommonError::InvalidTimeWindow(_))));
+        contract.set_campaign_claim_window(campaign_id, U256::from(2000), U256::from(3000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        assert_eq!(contract.campaign_claim_window(campaign_id).unwrap(), (U256::from(2000), U256::from(3000)));
+
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false).unwrap();
+        assert_eq!(contract.vested_of(user).unwrap(), reward);
+
+        vm.set_sender(user);
+        assert!(matches!(contract.claim_vested(), Err(CommonError::ClaimWindowClosed(_))));
+        vm.set_block_timestamp(3001);
+        assert!(matches!(contract.claim_vested(), Err(CommonError::ClaimWindowClosed(_))));
+
+        vm.set_sender(owner);
+        assert!(matches!(contract.extend_claim_deadline(campaign_id, U256::from(3000)), Err(CommonError::InvalidTimeWindow(_))));
+        contract.extend_claim_deadline(campaign_id, U256::from(4000)).unwrap();
+        assert!(matches!(contract.extend_claim_deadline(campaign_id, U256::from(5000)), Err(CommonError::DeadlineAlreadyExtended(_))));
+        let logs = vm.get_emitted_logs();
+        assert_eq!(logs.last().unwrap().0[0], ClaimDeadlineExtended::SIGNATURE_HASH);
+
+        vm.set_sender(user);
+        assert_eq!(contract.claim_vested().unwrap(), reward);
+        assert_eq!(vm.balance(user), reward);
+    }
+
+    #[test]
+    fn test_sweep_expired_rewards() {
+        let owner = Address::new([0x01; 20]);
+        let early = Address::new([0x02; 20]);
+        let late = Address::new([0x03; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_claim_window(campaign_id, U256::from(1000), U256::from(3000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+
+        vm.set_block_timestamp(1000);
+        let early_reward = contract.accrue_for_campaign(campaign_id, early, U256::from(1000), false, false).unwrap();
+        let late_reward = contract.accrue_for_campaign(campaign_id, late, U256::from(2000), false, false).unwrap();
+        vm.set_sender(early);
+        contract.claim_vested().unwrap();
+
+        vm.set_sender(owner);
+        assert!(matches!(contract.sweep_expired(campaign_id), Err(CommonError::ClaimWindowOpen(_))));
+        vm.set_block_timestamp(3001);
+        vm.set_sender(late);
+        assert!(matches!(contract.sweep_expired(campaign_id), Err(CommonError::Unauthorized(_))));
+
+        vm.set_sender(owner);
+        assert_eq!(contract.sweep_expired(campaign_id).unwrap(), late_reward);
+        assert_eq!(contract.expired_rewards(campaign_id), late_reward);
+        assert_eq!(contract.campaign_totals(campaign_id).unwrap(), (early_reward + late_reward, early_reward));
+        assert!(matches!(contract.sweep_expired(campaign_id), Err(CommonError::InvalidCampaignState(_))));
+        let logs = vm.get_emitted_logs();
+        let expired = RewardsExpired::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((expired.campaign_id, expired.amount), (campaign_id, late_reward));
+
+        // Swept grants cannot be revived by extending the deadline.
+        assert!(matches!(contract.extend_claim_deadline(campaign_id, U256::from(5000)), Err(CommonError::InvalidCampaignState(_))));
+        assert_eq!(contract.vested_of(late).unwrap(), U256::ZERO);
+        vm.set_sender(late);
+        assert!(matches!(contract.claim_vested(), Err(CommonError::ZeroValue(_))));
+    }
+
+    #[test]
+    fn test_get_campaigns_pages() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        for week in 1..=5u64 {
+            contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(week * 1000), U256::from(week * 1000 + 500)).unwrap();
+        }
+        contract.activate_campaign(U256::from(2)).unwrap();
+
+        let page = contract.get_campaigns(U256::from(1), U256::from(2));
+        assert_eq!(page.len(), 2);
+        assert_eq!(page[0], (U256::from(2), CampaignState::Active as u8, B256::ZERO, U256::from(2000), U256::from(2500), U256::ZERO, U256::ZERO));
+        assert_eq!(page[1].0, U256::from(3));
+
+        let ids = |page: Vec<(U256, u8, B256, U256, U256, U256, U256)>| page.into_iter().map(|campaign| campaign.0.to::<u64>()).collect::<Vec<_>>();
+        assert_eq!(ids(contract.get_campaigns(U256::from(3), U256::from(10))), [4, 5]);
+        assert_eq!(ids(contract.get_campaigns(U256::ZERO, U256::MAX)), [1, 2, 3, 4, 5]);
+        assert!(contract.get_campaigns(U256::from(5), U256::from(1)).is_empty());
+        assert!(contract.get_campaigns(U256::ZERO, U256::ZERO).is_empty());
+    }
+
+    #[test]
+    fn test_clone_campaign() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let week = 7 * 24 * 60 * 60;
+        let source = contract.create_campaign(U256::from(20000), U256::from(300), U256::from(1000), U256::from(week), U256::from(2 * week)).unwrap();
+        contract.set_campaign_budget(source, U256::from(50_000)).unwrap();
+        contract.set_campaign_vesting(source, U256::from(100), U256::from(1000)).unwrap();
+        contract.set_campaign_claim_window(source, U256::from(2 * week), U256::from(3 * week)).unwrap();
+        let name = B256::right_padding_from(b"Weekly");
+        contract.set_campaign_metadata(source, name, B256::repeat_byte(0xab)).unwrap();
+        contract.activate_campaign(source).unwrap();
+
+        let clone = contract.clone_campaign(source, U256::from(2 * week), U256::from(3 * week)).unwrap();
+        assert_eq!(clone, U256::from(2));
+        assert_eq!(
+            contract.campaign(clone).unwrap(),
+            (U256::from(20000), U256::from(300), U256::from(1000), U256::from(2 * week), U256::from(3 * week))
+        );
+        assert_eq!(contract.campaign_state(clone).unwrap(), CampaignState::Draft as u8);
+        assert_eq!(contract.campaign_budget(clone).unwrap(), (U256::from(50_000), U256::ZERO));
+        assert_eq!(contract.campaign_vesting(clone).unwrap(), (U256::from(100), U256::from(1000)));
+        assert_eq!(contract.campaign_claim_window(clone).unwrap(), (U256::from(3 * week), U256::from(4 * week)));
+        assert_eq!(contract.campaign_metadata(clone).unwrap(), (name, B256::repeat_byte(0xab)));
+
+        assert!(matches!(contract.clone_campaign(U256::from(9), U256::ZERO, U256::from(1)), Err(CommonError::CampaignNotFound(_))));
+        assert!(matches!(contract.clone_campaign(source, U256::from(5), U256::from(5)), Err(CommonError::InvalidTimeWindow(_))));
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.clone_campaign(source, U256::from(2 * week), U256::from(3 * week)), Err(CommonError::Unauthorized(_))));
+
+        // Operators may clone settings, but not a budget only the owner could grant.
+        let operator = Address::new([0x03; 20]);
+        vm.set_sender(Address::new([0x01; 20]));
+        contract.set_operator(operator, true).unwrap();
+        contract.update_max_operator_campaigns(U256::from(5)).unwrap();
+        let unbudgeted = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(week), U256::from(2 * week)).unwrap();
+        vm.set_sender(operator);
+        assert!(matches!(contract.clone_campaign(source, U256::from(2 * week), U256::from(3 * week)), Err(CommonError::Unauthorized(_))));
+        let clone = contract.clone_campaign(unbudgeted, U256::from(2 * week), U256::from(3 * week)).unwrap();
+        assert_eq!(contract.campaign_budget(clone).unwrap(), (U256::ZERO, U256::ZERO));
+        assert_eq!(contract.open_campaigns_of(operator), U256::from(1));
+    }
+
+    #[test]
+    fn test_pause_single_campaign() {
+        let owner = Address::new([0x01; 20]);
+        let partner = Address::new([0x02; 20]);
+        let user = Address::new([0x03; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        let mut ids = Vec::new();
+        for _ in 0..2 {
+            let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+            contract.set_campaign_claim_window(campaign_id, U256::from(1000), U256::ZERO).unwrap();
+            contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+            contract.activate_campaign(campaign_id).unwrap();
+            ids.push(campaign_id);
+        }
+        let (paused, live) = (ids[0], ids[1]);
+        contract.set_campaign_operator(paused, partner, true).unwrap();
+        vm.set_block_timestamp(1000);
+        contract.accrue_for_campaign(paused, user, U256::from(1000), false, false).unwrap();
+
+        vm.set_sender(partner);
+        assert!(matches!(contract.pause_campaign(live), Err(CommonError::Unauthorized(_))));
+        contract.pause_campaign(paused).unwrap();
+        assert!(contract.is_campaign_paused(paused));
+        assert!(!contract.is_paused());
+
+        vm.set_sender(owner);
+        assert!(matches!(contract.accrue_for_campaign(paused, user, U256::from(1000), false, false), Err(CommonError::CampaignPaused(_))));
+        assert!(matches!(contract.calculate_campaign_reward(paused, U256::from(1000), false, false), Err(CommonError::CampaignPaused(_))));
+        let live_reward = contract.accrue_for_campaign(live, user, U256::from(1000), false, false).unwrap();
+
+        vm.set_sender(user);
+        assert_eq!(contract.claim_vested().unwrap(), live_reward);
+        assert!(matches!(contract.claim_vested(), Err(CommonError::CampaignPaused(_))));
+
+        vm.set_sender(partner);
+        contract.unpause_campaign(paused).unwrap();
+        vm.set_sender(user);
+        assert_eq!(contract.claim_vested().unwrap(), U256::from(1000));
+    }
+
+    #[test]
+    fn test_token_funded_campaign_pool() {
+        let owner = Address::new([0x01; 20]);
+        let sponsor = Address::new([0x02; 20]);
+        let user = Address::new([0x03; 20]);
+        let token = Address::new([0x70; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+
+        vm.set_sender(sponsor);
+        assert!(matches!(contract.fund_campaign(campaign_id, token, U256::from(1500)), Err(CommonError::TokenNotApproved(_))));
+        vm.set_sender(owner);
+        mock_token_metadata(&vm, token, 18, "RWD");
+        contract.approve_token(token).unwrap();
+
+        vm.set_sender(sponsor);
+        let pull = IERC20::transferFromCall { from: sponsor, to: vm.contract_address(), amount: U256::from(1500) }.abi_encode();
+        vm.mock_call(token, pull.clone(), Ok(IERC20::transferFromCall::abi_encode_returns(&(false,))));
+        assert!(matches!(contract.fund_campaign(campaign_id, token, U256::from(1500)), Err(CommonError::TransferFailed(_))));
+        vm.mock_call(token, pull, Ok(IERC20::transferFromCall::abi_encode_returns(&(true,))));
+        contract.fund_campaign(campaign_id, token, U256::from(1500)).unwrap();
+        assert_eq!(contract.campaign_pool(campaign_id), (token, U256::from(1500)));
+        assert!(matches!(contract.fund_campaign(campaign_id, Address::new([0x71; 20]), U256::from(1)), Err(CommonError::TokenNotApproved(_))));
+
+        vm.set_sender(owner);
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false).unwrap();
+        assert!(matches!(contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false), Err(CommonError::BudgetNotFunded(_))));
+        assert_eq!(contract.balance_of(user).unwrap(), U256::ZERO);
+
+        vm.set_sender(user);
+        vm.mock_call(token, IERC20::transferCall { to: user, amount: reward }.abi_encode(), Ok(IERC20::transferCall::abi_encode_returns(&(true,))));
+        assert_eq!(contract.claim_vested().unwrap(), reward);
+        assert_eq!(contract.campaign_pool(campaign_id), (token, U256::from(500)));
+        assert_eq!(contract.pooled_balance(token), U256::from(500));
+        assert_eq!(vm.balance(user), U256::ZERO);
+        let logs = vm.get_emitted_logs();
+        let claimed = CampaignRewardClaimed::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((claimed.campaign_id, claimed.user, claimed.token, claimed.amount), (campaign_id, user, token, reward));
+    }
+
+    #[test]
+    fn test_extend_campaign() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1500);
+
+        assert!(matches!(contract.extend_campaign(campaign_id, U256::from(2000)), Err(CommonError::InvalidTimeWindow(_))));
+        contract.extend_campaign(campaign_id, U256::from(2500)).unwrap();
+        assert_eq!(contract.campaign(campaign_id).unwrap().4, U256::from(2500));
+        let logs = vm.get_emitted_logs();
+        let extended = CampaignExtended::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((extended.old_end_time, extended.new_end_time), (U256::from(2000), U256::from(2500)));
+
+        contract.extend_campaign(campaign_id, U256::from(3000)).unwrap();
+        contract.extend_campaign(campaign_id, U256::from(3500)).unwrap();
+        assert_eq!(contract.campaign_extensions(campaign_id), MAX_CAMPAIGN_EXTENSIONS);
+        assert!(matches!(contract.extend_campaign(campaign_id, U256::from(4000)), Err(CommonError::ExtensionLimitReached(_))));
+
+        let ended = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(1200)).unwrap();
+        assert!(matches!(contract.extend_campaign(ended, U256::from(4000)), Err(CommonError::InvalidCampaignState(_))));
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.extend_campaign(campaign_id, U256::from(4000)), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_campaign_participant_allowlist() {
+        let owner = Address::new([0x01; 20]);
+        let member = Address::new([0x02; 20]);
+        let outsider = Address::new([0x03; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_claim_window(campaign_id, U256::from(1000), U256::ZERO).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        assert!(contract.is_campaign_participant(campaign_id, outsider));
+
+        contract.set_campaign_restricted(campaign_id, true).unwrap();
+        contract.set_campaign_participants(campaign_id, vec![member], true).unwrap();
+        assert!(contract.is_campaign_participant(campaign_id, member));
+        assert!(!contract.is_campaign_participant(campaign_id, outsider));
+
+        vm.set_block_timestamp(1000);
+        assert!(matches!(contract.accrue_for_campaign(campaign_id, outsider, U256::from(1000), false, false), Err(CommonError::NotEligible(_))));
+        let reward = contract.accrue_for_campaign(campaign_id, member, U256::from(1000), false, false).unwrap();
+
+        contract.set_campaign_participants(campaign_id, vec![member], false).unwrap();
+        vm.set_sender(member);
+        assert!(matches!(contract.claim_vested(), Err(CommonError::NotEligible(_))));
+
+        vm.set_sender(owner);
+        contract.set_campaign_restricted(campaign_id, false).unwrap();
+        vm.set_sender(member);
+        assert_eq!(contract.claim_vested().unwrap(), reward);
+
+        assert!(matches!(contract.set_campaign_participants(campaign_id, vec![outsider], true), Err(CommonError::Unauthorized(_))));
+    }
+
+    #[test]
+    fn test_archive_campaign() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_claim_window(campaign_id, U256::from(1000), U256::ZERO).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false).unwrap();
+
+        assert!(matches!(contract.archive_campaign(campaign_id), Err(CommonError::InvalidCampaignState(_))));
+        vm.set_block_timestamp(2001);
+        contract.finalize_campaign(campaign_id).unwrap();
+        assert!(matches!(contract.archive_campaign(campaign_id), Err(CommonError::OutstandingRewards(_))));
+
+        vm.set_sender(user);
+        contract.claim_vested().unwrap();
+        vm.set_sender(owner);
+        let expected_hash = contract.campaign_params_hash(campaign_id);
+        assert_eq!(contract.archive_campaign(campaign_id).unwrap(), expected_hash);
+        assert_eq!(contract.campaign_summary(campaign_id).unwrap(), (true, expected_hash, reward, reward, U256::ZERO));
+        assert_eq!(contract.campaign(campaign_id).unwrap(), (U256::ZERO, U256::ZERO, U256::ZERO, U256::ZERO, U256::ZERO));
+        assert!(matches!(contract.archive_campaign(campaign_id), Err(CommonError::InvalidCampaignState(_))));
+
+        assert_eq!(contract.prune_vesting(user).unwrap(), U256::from(1));
+        assert_eq!(contract.vesting_of(campaign_id, user), (U256::ZERO, U256::ZERO));
+        assert_eq!(contract.prune_vesting(user).unwrap(), U256::ZERO);
+        vm.set_sender(user);
+        assert!(matches!(contract.claim_vested(), Err(CommonError::ZeroValue(_))));
+    }
+
+    #[test]
+    fn test_archive_ledger_credited_campaign() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false).unwrap();
+        assert_eq!(contract.balance_of(user).unwrap(), reward);
+
+        vm.set_block_timestamp(2001);
+        contract.finalize_campaign(campaign_id).unwrap();
+        let expected_hash = contract.campaign_params_hash(campaign_id);
+        assert_eq!(contract.archive_campaign(campaign_id).unwrap(), expected_hash);
+        assert_eq!(contract.campaign_summary(campaign_id).unwrap(), (true, expected_hash, reward, U256::ZERO, U256::ZERO));
+        assert_eq!(contract.balance_of(user).unwrap(), reward);
+    }
+
+    #[test]
+    fn test_accrual_and_claim_events_carry_campaign_id() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1000);
+
+        contract.update_percentage_bonus(U256::from(2000)).unwrap();
+        let logs = vm.get_emitted_logs();
+        assert_eq!(logs.last().unwrap().0[1], B256::ZERO);
+
+        contract.accrue(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        let logs = vm.get_emitted_logs();
+        assert_eq!(logs.last().unwrap().0[0], RewardAccrued::SIGNATURE_HASH);
+        assert_eq!(logs.last().unwrap().0[1], B256::ZERO);
+
+        contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false).unwrap();
+        let logs = vm.get_emitted_logs();
+        let accrued = RewardAccrued::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((accrued.campaign_id, accrued.user), (campaign_id, user));
+        assert_eq!(logs.last().unwrap().0[1], B256::from(campaign_id));
+
+        vm.set_sender(user);
+        contract.claim().unwrap();
+        let logs = vm.get_emitted_logs();
+        let claimed = RewardClaimed::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((claimed.campaign_id, claimed.user), (U256::ZERO, user));
+    }
+
+
+    #[test]
+    fn test_get_campaign_stats() {
+        let owner = Address::new([0x01; 20]);
+        let alice = Address::new([0x02; 20]);
+        let bob = Address::new([0x03; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_claim_window(campaign_id, U256::from(1000), U256::ZERO).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
 
-use alloy_sol_types::sol;
+        let stats = contract.get_campaign_stats(campaign_id).unwrap();
+        assert_eq!(stats.participants, U256::ZERO);
+        assert_eq!(stats.remaining_budget, U256::from(10_000));
+        assert_eq!(stats.state, CampaignState::Draft as u8);
 
-use stylus_sdk::{alloy_primitives::U256, alloy_primitives::Address, prelude::*, crypto::keccak};
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1000);
+        let first = contract.accrue_for_campaign(campaign_id, alice, U256::from(1000), false, false).unwrap();
+        let second = contract.accrue_for_campaign(campaign_id, alice, U256::from(1000), false, false).unwrap();
+        let third = contract.accrue_for_campaign(campaign_id, bob, U256::from(1000), false, false).unwrap();
+        vm.set_sender(alice);
+        let claimed = contract.claim_vested().unwrap();
 
-sol! {
-    event MultiplyFactorUpdated(address indexed sender, uint256 multiply_factor);
-    event PercentageBonusUpdated(address indexed sender, uint256 percentage_bonus);
-    event OwnershipTransferred(address indexed previous_owner, address indexed new_owner);
-}
+        let stats = contract.get_campaign_stats(campaign_id).unwrap();
+        assert_eq!(stats.participants, U256::from(2));
+        assert_eq!((contract.member_index_of(alice), contract.member_index_of(bob)), (U256::from(1), U256::from(2)));
+        assert_eq!(stats.total_accrued, first + second + third);
+        assert_eq!(stats.total_claimed, claimed);
+        assert_eq!(stats.remaining_budget, U256::from(10_000) - first - second - third);
+        assert_eq!(stats.state, CampaignState::Active as u8);
+        assert!(matches!(contract.get_campaign_stats(U256::from(9)), Err(CommonError::CampaignNotFound(_))));
+    }
 
-sol! {
-    #[derive(Debug)]
-    error InvalidMultiplyFactor();
 
-    #[derive(Debug)]
-    error Unauthorized();
+    #[test]
+    fn test_operator_campaign_limit() {
+        let owner = Address::new([0x01; 20]);
+        let operator = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.set_operator(operator, true).unwrap();
+        let template_id = contract.create_template(B256::ZERO, U256::ZERO, U256::ZERO, U256::ZERO, U256::ZERO, U256::ZERO).unwrap();
 
-    #[derive(Debug)]
-    error ZeroValue();
-}
+        vm.set_sender(operator);
+        let create = |contract: &mut RewardProcessor| contract.create_campaign_from_template(template_id, U256::from(1000), U256::from(2000));
+        assert!(matches!(create(&mut contract), Err(CommonError::CampaignLimitReached(_))));
+        assert!(matches!(contract.update_max_operator_campaigns(U256::from(2)), Err(CommonError::Unauthorized(_))));
 
-sol_storage! {
-    #[entrypoint]
-    pub struct RewardProcessor {
-        uint256 multiply_factor;
-        address owner;
-        uint256 percentage_denominator;
-        uint256 percentage_bonus;
-    }
-}
+        vm.set_sender(owner);
+        contract.update_max_operator_campaigns(U256::from(2)).unwrap();
+        assert_eq!(contract.max_operator_campaigns(), U256::from(2));
 
-#[derive(SolidityError, Debug)]
-pub enum ConstructorError {
-    InvalidMultiplyFactor(InvalidMultiplyFactor),
-}
+        vm.set_sender(operator);
+        let first = create(&mut contract).unwrap();
+        create(&mut contract).unwrap();
+        assert_eq!(contract.open_campaigns_of(operator), U256::from(2));
+        assert!(matches!(create(&mut contract), Err(CommonError::CampaignLimitReached(_))));
 
-#[derive(SolidityError, Debug)]
-pub enum CommonError {
-    Unauthorized(Unauthorized),
-    ZeroValue(ZeroValue),
-    InvalidMultiplyFactor(InvalidMultiplyFactor),
-}
+        vm.set_sender(owner);
+        contract.cancel_campaign(first).unwrap();
+        vm.set_sender(operator);
+        assert!(matches!(
+            contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)),
+            Err(CommonError::Unauthorized(_))
+        ));
 
-#[public]
-impl RewardProcessor {
-    #[constructor]
-    #[payable]
-    pub fn constructor(
-        &mut self,
-        multiply_factor_: U256,
-    ) -> Result<(), ConstructorError> {
-        if multiply_factor_ == U256::ZERO {
-            return Err(ConstructorError::InvalidMultiplyFactor(InvalidMultiplyFactor {}));
+        vm.set_sender(owner);
+        contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        for _ in 0..2 {
+            create(&mut contract).unwrap();
         }
+        assert_eq!(contract.open_campaigns_of(owner), U256::ZERO);
 
-        self.multiply_factor.set(multiply_factor_);
-        self.owner.set(self.vm().tx_origin());
-        self.percentage_denominator.set(U256::from(10000));
-        self.percentage_bonus.set(U256::from(1000));
+        vm.set_sender(operator);
+        create(&mut contract).unwrap();
+        assert_eq!(contract.open_campaigns_of(operator), U256::from(2));
+        vm.set_sender(Address::new([0x03; 20]));
+        assert!(matches!(create(&mut contract), Err(CommonError::Unauthorized(_))));
+    }
 
-        Ok(())
+
+    #[test]
+    fn test_create_campaign_from_template() {
+        let owner = Address::new([0x01; 20]);
+        let operator = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        contract.set_operator(operator, true).unwrap();
+        contract.update_max_operator_campaigns(U256::from(5)).unwrap();
+        let name = B256::right_padding_from(b"Partner");
+
+        assert!(matches!(
+            contract.create_template(name, U256::ZERO, U256::from(2000), U256::from(1000), U256::ZERO, U256::ZERO),
+            Err(CommonError::InvalidDenominator(_))
+        ));
+        let template_id = contract.create_template(name, U256::from(20000), U256::from(300), U256::from(1000), U256::from(50_000), U256::from(1000)).unwrap();
+        assert_eq!(template_id, U256::from(1));
+        assert_eq!(
+            contract.template(template_id).unwrap(),
+            (name, U256::from(20000), U256::from(300), U256::from(1000), U256::from(50_000), U256::from(1000))
+        );
+
+        vm.set_sender(operator);
+        assert!(matches!(contract.create_template(name, U256::ZERO, U256::ZERO, U256::ZERO, U256::ZERO, U256::ZERO), Err(CommonError::Unauthorized(_))));
+        assert!(matches!(contract.create_campaign_from_template(U256::from(9), U256::from(100), U256::from(200)), Err(CommonError::TemplateNotFound(_))));
+        assert!(matches!(
+            contract.create_campaign_from_template(template_id, U256::from(100), U256::from(1101)),
+            Err(CommonError::InvalidTimeWindow(_))
+        ));
+
+        let campaign_id = contract.create_campaign_from_template(template_id, U256::from(100), U256::from(1100)).unwrap();
+        assert_eq!(
+            contract.campaign(campaign_id).unwrap(),
+            (U256::from(20000), U256::from(300), U256::from(1000), U256::from(100), U256::from(1100))
+        );
+        assert_eq!(contract.campaign_budget(campaign_id).unwrap(), (U256::from(50_000), U256::ZERO));
+        assert_eq!(contract.campaign_metadata(campaign_id).unwrap().0, name);
+        assert_eq!(contract.campaign_state(campaign_id).unwrap(), CampaignState::Draft as u8);
+        assert_eq!(contract.open_campaigns_of(operator), U256::from(1));
+
+        vm.set_sender(Address::new([0x03; 20]));
+        assert!(matches!(contract.create_campaign_from_template(template_id, U256::from(100), U256::from(200)), Err(CommonError::Unauthorized(_))));
     }
 
-    pub fn calculate_reward(&self, amount: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> U256 {
-        let current_time = U256::from(self.vm().block_timestamp());
-        self.calculate_reward_at_time(amount, current_time, start_time, end_time, has_bonus, has_strict_bonus)
+
+    #[test]
+    fn test_migrate_campaign_v1_to_v2() {
+        let vm = TestVMBuilder::new().sender(Address::new([0x01; 20])).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        assert_eq!(contract.campaign_version(campaign_id).unwrap(), CAMPAIGN_VERSION);
+        assert!(matches!(contract.migrate_campaign_v1_to_v2(campaign_id), Err(CommonError::InvalidCampaignState(_))));
+
+        // Campaigns written before versioning carry no version.
+        contract.campaigns.setter(campaign_id).version.set(U8::ZERO);
+        assert_eq!(contract.campaign_version(campaign_id).unwrap(), 1);
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.migrate_campaign_v1_to_v2(campaign_id), Err(CommonError::Unauthorized(_))));
+        vm.set_sender(Address::new([0x01; 20]));
+        contract.migrate_campaign_v1_to_v2(campaign_id).unwrap();
+        assert_eq!(contract.campaign_version(campaign_id).unwrap(), 2);
+        assert!(matches!(contract.migrate_campaign_v1_to_v2(U256::from(9)), Err(CommonError::CampaignNotFound(_))));
     }
 
-    pub fn calculate_reward_at_time(&self, amount: U256, current_time: U256, start_time: U256, end_time: U256, has_bonus: bool, has_strict_bonus: bool) -> U256 {
-        let mut reward = amount;
-        
-        let time_decay_multiplier = if current_time <= start_time {
-            self.percentage_denominator.get()
 
-        } else if current_time >= end_time {
+    #[test]
+    fn test_merge_campaigns() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        let into = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        let from = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_claim_window(from, U256::from(1000), U256::ZERO).unwrap();
+        contract.set_campaign_budget(into, U256::from(1000)).unwrap();
+        contract.set_campaign_budget(from, U256::from(5000)).unwrap();
+        contract.activate_campaign(from).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_for_campaign(from, user, U256::from(1000), false, false).unwrap();
 
-            self.percentage_denominator.get() / U256::from(2)
-        } else {
-            let total_duration = end_time - start_time;
-            let elapsed_time = current_time - start_time;
-            
-            let max_multiplier = self.percentage_denominator.get();
-            let min_multiplier = self.percentage_denominator.get() / U256::from(2); // 50%
-            let decay_range = max_multiplier - min_multiplier;
-            
-            let decay_amount = decay_range * elapsed_time / total_duration;
-            max_multiplier - decay_amount
-        };
-        
-        reward = reward * time_decay_multiplier / self.percentage_denominator.get();
-        
-        if has_bonus {
-            reward += amount * self.percentage_bonus.get() / self.percentage_denominator.get();
-        }
+        assert!(matches!(contract.merge_campaigns(into, into), Err(CommonError::InvalidCampaignState(_))));
+        vm.set_sender(user);
+        assert!(matches!(contract.merge_campaigns(into, from), Err(CommonError::Unauthorized(_))));
+        vm.set_sender(owner);
+        assert_eq!(contract.merge_campaigns(into, from).unwrap(), U256::from(5000) - reward);
 
-        if has_strict_bonus {
-            reward += amount * self.multiply_factor.get() / self.percentage_denominator.get();
-        }
+        assert_eq!(contract.campaign_budget(into).unwrap(), (U256::from(6000) - reward, U256::ZERO));
+        assert_eq!(contract.campaign_budget(from).unwrap(), (reward, reward));
+        assert_eq!(contract.campaign_state(from).unwrap(), CampaignState::Cancelled as u8);
+        let logs = vm.get_emitted_logs();
+        let event = CampaignReconciled::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((event.from_id, event.to_id, event.budget, event.pool), (from, into, U256::from(5000) - reward, U256::ZERO));
 
-        if self.get_pseudo_random() {
-            reward = reward * U256::from(2);
-        }
+        // Rewards issued before the merge stay claimable in the source campaign.
+        vm.set_sender(user);
+        assert_eq!(contract.claim_vested().unwrap(), reward);
+        vm.set_sender(owner);
+        assert!(matches!(contract.merge_campaigns(from, into), Err(CommonError::InvalidCampaignState(_))));
+    }
 
-        reward
+    #[test]
+    fn test_split_campaign() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let token = Address::new([0x70; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        mock_token_metadata(&vm, token, 18, "RWD");
+        contract.approve_token(token).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+        contract.fund_campaign(campaign_id, token, U256::from(3000)).unwrap();
+        let allowance = IERC20::allowanceCall { owner, spender: vm.contract_address() }.abi_encode();
+        vm.mock_static_call(token, allowance, Ok(IERC20::allowanceCall::abi_encode_returns(&(U256::from(7000),))));
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false).unwrap();
+
+        assert!(matches!(contract.split_campaign(campaign_id, U256::ZERO, U256::from(3000), U256::from(4000)), Err(CommonError::ZeroValue(_))));
+        assert!(matches!(
+            contract.split_campaign(campaign_id, U256::from(10_000), U256::from(3000), U256::from(4000)),
+            Err(CommonError::BudgetExceeded(_))
+        ));
+
+        let new_id = contract.split_campaign(campaign_id, U256::from(4000), U256::from(3000), U256::from(4000)).unwrap();
+        assert_eq!(contract.campaign_budget(campaign_id).unwrap(), (U256::from(6000), reward));
+        assert_eq!(contract.campaign_budget(new_id).unwrap(), (U256::from(4000), U256::ZERO));
+        assert_eq!(contract.campaign_pool(campaign_id), (token, reward));
+        assert_eq!(contract.campaign_pool(new_id), (token, U256::from(3000) - reward));
+        assert_eq!(contract.campaign(new_id).unwrap().3, U256::from(3000));
+        assert_eq!(contract.campaign_state(new_id).unwrap(), CampaignState::Draft as u8);
     }
 
-    pub fn get_pseudo_random(&self) -> bool {
-        let mut data = Vec::new();
-        data.extend_from_slice(&self.vm().block_timestamp().to_be_bytes());
-        data.extend_from_slice(&self.vm().block_number().to_be_bytes());
-        data.extend_from_slice(self.vm().tx_origin().as_slice());
-        data.extend_from_slice(&self.vm().tx_gas_price().to_be_bytes::<32>());
-        data.extend_from_slice(self.vm().msg_sender().as_slice());
-        
-        let random_hash = keccak(data);
-        
-        let mut bytes = [0u8; 8];
-        bytes.copy_from_slice(&random_hash.as_slice()[0..8]);
-        let result = u64::from_be_bytes(bytes) % 100;
 
-        result == 0
+    #[test]
+    fn test_campaign_bonus_oracle() {
+        let owner = Address::new([0x01; 20]);
+        let oracle = Address::new([0x80; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::from(1000), U256::from(10_000), U256::from(1000), U256::from(2000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1000);
+        let amount = U256::from(1_000_000);
+
+        assert!(matches!(
+            contract.set_campaign_bonus_oracle(campaign_id, oracle, WAD, U256::from(600), U256::from(500)),
+            Err(CommonError::InvalidDenominator(_))
+        ));
+        assert!(matches!(
+            contract.set_campaign_bonus_oracle(campaign_id, oracle, WAD, U256::ZERO, U256::from(10_001)),
+            Err(CommonError::InvalidDenominator(_))
+        ));
+        // Half a bonus point per unit of oracle value, between 1% and 30%.
+        contract.set_campaign_bonus_oracle(campaign_id, oracle, WAD / U256::from(2), U256::from(100), U256::from(3000)).unwrap();
+        assert_eq!(contract.campaign_bonus_oracle(campaign_id), (oracle, WAD / U256::from(2), U256::from(100), U256::from(3000)));
+        assert!(matches!(contract.calculate_campaign_reward(campaign_id, amount, true, false), Err(CommonError::OracleUnavailable(_))));
+
+        let reward_at = |value: u64| {
+            let returned = IValueOracle::latestValueCall::abi_encode_returns(&(U256::from(value),));
+            vm.mock_static_call(oracle, IValueOracle::latestValueCall {}.abi_encode(), Ok(returned));
+            contract.calculate_campaign_reward(campaign_id, amount, true, false).unwrap()
+                - contract.calculate_campaign_reward(campaign_id, amount, false, false).unwrap()
+        };
+        assert_eq!(reward_at(4000), U256::from(200_000));
+        assert_eq!(reward_at(10), U256::from(10_000));
+        assert_eq!(reward_at(1_000_000), U256::from(300_000));
+
+        vm.set_sender(Address::new([0x02; 20]));
+        assert!(matches!(contract.set_campaign_bonus_oracle(campaign_id, Address::ZERO, U256::ZERO, U256::ZERO, U256::ZERO), Err(CommonError::Unauthorized(_))));
+        vm.set_sender(owner);
+        contract.set_campaign_bonus_oracle(campaign_id, Address::ZERO, U256::ZERO, U256::ZERO, U256::ZERO).unwrap();
+        let bonus = contract.calculate_campaign_reward(campaign_id, amount, true, false).unwrap() - contract.calculate_campaign_reward(campaign_id, amount, false, false).unwrap();
+        assert_eq!(bonus, U256::from(100_000));
     }
 
-    pub fn update_multiply_factor(&mut self, new_factor: U256) -> Result<(), CommonError> {
-        self.assert_owner()?;
-        
-        if new_factor == U256::ZERO {
-            return Err(CommonError::InvalidMultiplyFactor(InvalidMultiplyFactor {}));
-        }
-        
-        self.multiply_factor.set(new_factor);
 
-        log(self.vm(), MultiplyFactorUpdated {
-            sender: self.vm().tx_origin(),
-            multiply_factor: new_factor,
-        });
+    #[test]
+    fn test_campaign_referrals() {
+        let owner = Address::new([0x01; 20]);
+        let referee = Address::new([0x02; 20]);
+        let referrer = Address::new([0x03; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(1_000_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1000);
 
-        Ok(())
+        assert!(matches!(contract.register_referral(referee, referee), Err(CommonError::InvalidReferral(_))));
+        assert!(matches!(contract.register_referral(referee, Address::ZERO), Err(CommonError::InvalidReferral(_))));
+        contract.register_referral(referee, referrer).unwrap();
+        assert_eq!(contract.referrer_of(referee), referrer);
+        assert!(matches!(contract.register_referral(referee, owner), Err(CommonError::InvalidReferral(_))));
+        vm.set_sender(referee);
+        assert!(matches!(contract.register_referral(referrer, referee), Err(CommonError::Unauthorized(_))));
+        assert!(matches!(contract.set_campaign_referral_rate(campaign_id, U256::from(500)), Err(CommonError::Unauthorized(_))));
+
+        // Without a rate the referrer earns nothing.
+        vm.set_sender(owner);
+        let first = contract.accrue_for_campaign(campaign_id, referee, U256::from(10_000), false, false).unwrap();
+        assert_eq!(contract.referral_earnings_of(referrer), U256::ZERO);
+
+        assert!(matches!(contract.set_campaign_referral_rate(campaign_id, U256::from(10_001)), Err(CommonError::InvalidReferral(_))));
+        contract.set_campaign_referral_rate(campaign_id, U256::from(500)).unwrap();
+        assert_eq!(contract.campaign_referral_rate(campaign_id), U256::from(500));
+        let second = contract.accrue_for_campaign(campaign_id, referee, U256::from(10_000), false, false).unwrap();
+        let referral = second * U256::from(500) / U256::from(10_000);
+        assert!(referral > U256::ZERO);
+        assert_eq!(contract.referral_earnings_of(referrer), referral);
+        assert_eq!(contract.balance_of(referrer).unwrap(), referral);
+        assert_eq!(contract.balance_of(referee).unwrap(), first + second);
+        assert_eq!(contract.campaign_budget(campaign_id).unwrap().1, first + second + referral);
+
+        let logs = vm.get_emitted_logs();
+        let event = ReferralCredited::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((event.campaign_id, event.referrer, event.referee, event.amount), (campaign_id, referrer, referee, referral));
+
+        // A referrer outside a restricted campaign's allowlist earns nothing in it.
+        contract.set_campaign_restricted(campaign_id, true).unwrap();
+        contract.set_campaign_participants(campaign_id, vec![referee], true).unwrap();
+        contract.accrue_for_campaign(campaign_id, referee, U256::from(10_000), false, false).unwrap();
+        assert_eq!(contract.referral_earnings_of(referrer), referral);
     }
 
-    pub fn update_percentage_bonus(&mut self, new_bonus: U256) -> Result<(), CommonError> {
-        self.assert_owner()?;
-        
-        if new_bonus == U256::ZERO {
-            return Err(CommonError::ZeroValue(ZeroValue {}));
-        }
-        
-        self.percentage_bonus.set(new_bonus);
 
-        log(self.vm(), PercentageBonusUpdated {
-            sender: self.vm().tx_origin(),
-            percentage_bonus: new_bonus,
-        });
+    #[test]
+    fn test_withdraw_surplus() {
+        let owner = Address::new([0x01; 20]);
+        let sponsor = Address::new([0x02; 20]);
+        let user = Address::new([0x03; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_block_timestamp(1000);
 
-        Ok(())
+        vm.set_sender(sponsor);
+        vm.set_value(U256::from(3000));
+        contract.fund().unwrap();
+        vm.set_value(U256::ZERO);
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        vm.set_sender(owner);
+        let reward = contract.accrue_reward(user, U256::from(1000), U256::from(1000), U256::from(2000), true, false).unwrap();
+
+        assert_eq!(contract.contract_balance(), U256::from(10_000));
+        assert_eq!(contract.reserved_balance(), U256::from(3000) + reward);
+        assert_eq!(contract.surplus(), U256::from(7000) - reward);
+
+        vm.set_sender(user);
+        assert!(matches!(contract.withdraw_surplus(), Err(CommonError::Unauthorized(_))));
+        vm.set_sender(owner);
+        assert_eq!(contract.withdraw_surplus().unwrap(), U256::from(7000) - reward);
+        assert_eq!(vm.balance(owner), U256::from(7000) - reward);
+        assert_eq!(contract.surplus(), U256::ZERO);
+        assert!(matches!(contract.withdraw_surplus(), Err(CommonError::ZeroValue(_))));
+
+        // The reserved rewards are still there to be claimed.
+        vm.set_sender(user);
+        assert_eq!(contract.claim().unwrap(), reward);
+        assert_eq!(contract.contract_balance(), U256::from(3000));
+        assert_eq!(contract.reserved_balance(), U256::from(3000));
     }
 
-    pub fn assert_owner(&self) -> Result<(), CommonError> {
-        if self.vm().tx_origin() != self.owner.get() {
-            return Err(CommonError::Unauthorized(Unauthorized {}));
-        }
-        Ok(())
+
+    #[test]
+    fn test_claim_erc20() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let token = Address::new([0x70; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(user, U256::from(1000), U256::from(1000), U256::from(2000), true, false).unwrap();
+
+        vm.set_sender(user);
+        assert!(matches!(contract.claim_erc20(), Err(CommonError::TokenNotApproved(_))));
+        vm.set_sender(owner);
+        assert!(matches!(contract.set_payout_token(token), Err(CommonError::TokenNotApproved(_))));
+        mock_token_metadata(&vm, token, 18, "RWD");
+        contract.approve_token(token).unwrap();
+        contract.set_payout_token(token).unwrap();
+        assert_eq!(contract.payout_token(), token);
+
+        vm.set_sender(user);
+        let transfer = IERC20::transferCall { to: user, amount: reward }.abi_encode();
+        vm.mock_call(token, transfer.clone(), Ok(IERC20::transferCall::abi_encode_returns(&(false,))));
+        let error = contract.claim_erc20().unwrap_err();
+        assert_eq!(Vec::<u8>::from(error), <TransferFailed as alloy_sol_types::SolError>::SELECTOR.to_vec());
+
+        // The test VM keeps the failed attempt's writes, so start over from a fresh balance.
+        vm.set_sender(owner);
+        let reward = contract.accrue_reward(user, U256::from(1000), U256::from(1000), U256::from(2000), true, false).unwrap();
+        vm.set_sender(user);
+        let transfer = IERC20::transferCall { to: user, amount: reward }.abi_encode();
+        vm.mock_call(token, transfer, Ok(IERC20::transferCall::abi_encode_returns(&(true,))));
+        assert_eq!(contract.claim_erc20().unwrap(), reward);
+        assert_eq!(contract.balance_of(user).unwrap(), U256::ZERO);
+        assert_eq!(vm.balance(user), U256::ZERO);
+        let logs = vm.get_emitted_logs();
+        let claimed = CampaignRewardClaimed::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((claimed.campaign_id, claimed.user, claimed.token, claimed.amount), (U256::ZERO, user, token, reward));
+        assert!(matches!(contract.claim_erc20(), Err(CommonError::ZeroValue(_))));
     }
 
-    pub fn transfer_ownership(&mut self, new_owner: Address) -> Result<(), CommonError> {
-        self.assert_owner()?;
-        self.owner.set(new_owner);
 
-        log(self.vm(), OwnershipTransferred {
-            previous_owner: self.vm().tx_origin(),
-            new_owner,
-        });
-        
-        Ok(())
+    #[test]
+    fn test_claim_with_proof() {
+        let owner = Address::new([0x01; 20]);
+        let alice = Address::new([0x02; 20]);
+        let bob = Address::new([0x03; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(100_000));
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(100_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1000);
+
+        let leaf = |index: u64, account: Address, amount: u64| keccak(keccak((U256::from(index), account, U256::from(amount), true, false).abi_encode()));
+        let (alice_leaf, bob_leaf) = (leaf(0, alice, 1000), leaf(1, bob, 2000));
+        let root = merkle::hash_pair(alice_leaf, bob_leaf);
+
+        vm.set_sender(alice);
+        assert!(matches!(
+            contract.claim_with_proof(campaign_id, U256::ZERO, U256::from(1000), true, false, vec![bob_leaf]),
+            Err(CommonError::InvalidProof(_))
+        ));
+        vm.set_sender(owner);
+        contract.set_campaign_merkle_root(campaign_id, root).unwrap();
+        assert_eq!(contract.campaign_merkle_root(campaign_id), root);
+
+        vm.set_sender(alice);
+        assert!(matches!(
+            contract.claim_with_proof(campaign_id, U256::ZERO, U256::from(2000), true, false, vec![bob_leaf]),
+            Err(CommonError::InvalidProof(_))
+        ));
+        assert!(matches!(
+            contract.claim_with_proof(campaign_id, U256::from(1), U256::from(2000), true, false, vec![alice_leaf]),
+            Err(CommonError::InvalidProof(_))
+        ));
+        let expected = contract.calculate_campaign_reward(campaign_id, U256::from(1000), true, false).unwrap();
+        // A lucky block doubles the quote, never the committed payout.
+        hit_lucky_draw(&vm, &contract);
+        assert_eq!(contract.calculate_campaign_reward(campaign_id, U256::from(1000), true, false).unwrap(), expected * U256::from(2));
+        assert_eq!(contract.claim_with_proof(campaign_id, U256::ZERO, U256::from(1000), true, false, vec![bob_leaf]).unwrap(), expected);
+        assert_eq!(vm.balance(alice), expected);
+        assert!(contract.is_merkle_claimed(campaign_id, U256::ZERO));
+        assert!(!contract.is_merkle_claimed(campaign_id, U256::from(1)));
+        assert_eq!(contract.campaign_totals(campaign_id).unwrap(), (expected, expected));
+        assert!(matches!(
+            contract.claim_with_proof(campaign_id, U256::ZERO, U256::from(1000), true, false, vec![bob_leaf]),
+            Err(CommonError::AlreadyClaimed(_))
+        ));
+
+        vm.set_sender(bob);
+        let reward = contract.claim_with_proof(campaign_id, U256::from(1), U256::from(2000), true, false, vec![alice_leaf]).unwrap();
+        assert_eq!(vm.balance(bob), reward);
+        assert_eq!(contract.get_campaign_stats(campaign_id).unwrap().participants, U256::from(2));
     }
-}
 
-#[cfg(test)]
-mod test {
-    use alloy_primitives::Address;
-    use stylus_sdk::testing::TestVMBuilder;
 
-    use super::*;
+    #[test]
+    fn test_claim_with_signature() {
+        use ethers::signers::{LocalWallet, Signer};
+
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(100_000));
+        vm.set_block_timestamp(1000);
+
+        let wallet = LocalWallet::from_bytes(&[0x42; 32]).unwrap();
+        let signer = Address::from(wallet.address().0);
+        let (amount, start_time, end_time, deadline) = (U256::from(1000), U256::from(1000), U256::from(2000), U256::from(1500));
+        let message = RewardClaim { account: user, amount, start_time, end_time, flags: 1, deadline, nonce: U256::ZERO };
+        let digest = message.eip712_signing_hash(&contract.eip712_domain());
+        let signature = wallet.sign_hash(ethers::types::H256(digest.0)).unwrap();
+        let signature_bytes = Bytes::from(signature.to_vec());
+        // The test VM has no precompiles, so answer the ecrecover call for this exact input.
+        let recover_input = [digest.as_slice(), U256::from(signature.v).to_be_bytes::<32>().as_slice(), &signature.to_vec()[..64]].concat();
+        vm.mock_static_call(Address::with_last_byte(1), recover_input, Ok(B256::left_padding_from(signer.as_slice()).to_vec()));
+
+        vm.set_sender(user);
+        let claim = |contract: &mut RewardProcessor, amount: U256, signature: Bytes| contract.claim_with_signature(amount, start_time, end_time, 1, deadline, signature);
+        assert!(matches!(claim(&mut contract, amount, signature_bytes.clone()), Err(CommonError::InvalidSignature(_))));
+        vm.set_sender(owner);
+        contract.set_claim_signer(signer).unwrap();
+        assert_eq!(contract.claim_signer(), signer);
+
+        vm.set_sender(user);
+        assert!(matches!(claim(&mut contract, amount + U256::from(1), signature_bytes.clone()), Err(CommonError::InvalidSignature(_))));
+        assert!(matches!(claim(&mut contract, amount, Bytes::from(vec![0u8; 64])), Err(CommonError::InvalidSignature(_))));
+        let expected = contract.calculate_reward(amount, start_time, end_time, true, false).unwrap();
+        // A lucky block doubles the quote, never the signed payout.
+        hit_lucky_draw(&vm, &contract);
+        assert_eq!(contract.calculate_reward(amount, start_time, end_time, true, false).unwrap(), expected * U256::from(2));
+        assert_eq!(claim(&mut contract, amount, signature_bytes.clone()).unwrap(), expected);
+        assert_eq!(vm.balance(user), expected);
+        assert_eq!(contract.total_claimed(), expected);
+        assert_eq!(contract.nonce_of(user), U256::from(1));
+        // Replaying the signature fails, as it was made for the consumed nonce.
+        assert!(matches!(claim(&mut contract, amount, signature_bytes.clone()), Err(CommonError::InvalidSignature(_))));
+
+        vm.set_block_timestamp(1501);
+        assert!(matches!(claim(&mut contract, amount, signature_bytes), Err(CommonError::SignatureExpired(_))));
+        assert_eq!(contract.domain_separator(), contract.eip712_domain().separator());
+    }
+
 
     #[test]
-    fn test_assert_owner() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
+    fn test_claim_on_behalf() {
+        let owner = Address::new([0x01; 20]);
+        let cold = Address::new([0x02; 20]);
+        let hot = Address::new([0x03; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(100_000));
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_claim_window(campaign_id, U256::from(1000), U256::ZERO).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(100_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(cold, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        let grant = contract.accrue_for_campaign(campaign_id, cold, U256::from(1000), false, false).unwrap();
 
+        vm.set_sender(hot);
+        assert!(matches!(contract.claim_for(cold), Err(CommonError::Unauthorized(_))));
+        assert!(matches!(contract.claim_vested_for(cold), Err(CommonError::Unauthorized(_))));
+
+        vm.set_sender(cold);
+        contract.approve_claimer(hot).unwrap();
+        assert!(contract.is_claimer(cold, hot));
+        let logs = vm.get_emitted_logs();
+        let event = ClaimerUpdated::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((event.account, event.claimer, event.approved), (cold, hot, true));
+
+        vm.set_sender(hot);
+        assert_eq!(contract.claim_for(cold).unwrap(), reward);
+        assert_eq!(contract.claim_vested_for(cold).unwrap(), grant);
+        assert_eq!(vm.balance(cold), reward + grant);
+        assert_eq!(vm.balance(hot), U256::ZERO);
+
+        vm.set_sender(cold);
+        contract.revoke_claimer(hot).unwrap();
+        assert!(!contract.is_claimer(cold, hot));
+        vm.set_sender(hot);
+        assert!(matches!(contract.claim_for(cold), Err(CommonError::Unauthorized(_))));
+    }
+
+
+    #[test]
+    fn test_partial_claim() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(100_000));
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        let part = reward / U256::from(3);
 
-        let result = contract.constructor(U256::from(1000000));
+        vm.set_sender(user);
+        assert!(matches!(contract.claim_amount(U256::ZERO), Err(CommonError::ZeroValue(_))));
+        assert!(matches!(contract.claim_amount(reward + U256::from(1)), Err(CommonError::AmountExceedsBalance(_))));
+        assert_eq!(contract.claim_amount(part).unwrap(), part);
+        assert_eq!(vm.balance(user), part);
+        assert_eq!(contract.balance_of(user).unwrap(), reward - part);
+        assert_eq!(contract.ledger_of(user), (reward, part, U256::from(1000)));
 
-        assert!(result.is_ok());
-        
-        let owner_check = contract.assert_owner();
-        assert!(owner_check.is_ok());
-        
-        let vm2 = TestVMBuilder::new()
-            .sender(Address::from([0x02; 20]))
-            .build();
-        
-        let contract2 = RewardProcessor::from(&vm2);
-        
-        let unauthorized_check = contract2.assert_owner();
-        assert!(unauthorized_check.is_err());
-        assert!(matches!(
-            unauthorized_check.unwrap_err(),
-            CommonError::Unauthorized(_)
-        ));
+        assert_eq!(contract.claim().unwrap(), reward - part);
+        assert_eq!(vm.balance(user), reward);
+        assert_eq!(contract.total_claimed(), reward);
     }
 
     #[test]
-    fn test_update_multiply_factor() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
+    fn test_partial_claim_keeps_remainder_compounding() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(1_000_000));
+        contract.update_compound_rate(U256::from(1000)).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(user, U256::from(10_000), U256::from(1000), U256::from(2000), false, false).unwrap();
+
+        vm.set_sender(user);
+        let part = reward / U256::from(2);
+        contract.claim_amount(part).unwrap();
+        vm.set_block_timestamp(1000 + DEFAULT_EPOCH_LENGTH);
+        assert_eq!(contract.balance_of(user).unwrap(), (reward - part) * U256::from(11) / U256::from(10));
+    }
 
+
+    #[test]
+    fn test_claim_all() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(100_000));
+        let mut campaign_ids = Vec::new();
+        for _ in 0..3 {
+            let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+            contract.set_campaign_claim_window(campaign_id, U256::from(1000), U256::ZERO).unwrap();
+            contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+            contract.activate_campaign(campaign_id).unwrap();
+            campaign_ids.push(campaign_id);
+        }
+        vm.set_block_timestamp(1000);
+        let mut total = U256::ZERO;
+        for campaign_id in &campaign_ids[..2] {
+            total += contract.accrue_for_campaign(*campaign_id, user, U256::from(1000), false, false).unwrap();
+        }
 
-        let result = contract.constructor(U256::from(1000000));
+        vm.set_sender(user);
+        assert!(matches!(contract.claim_all(vec![campaign_ids[2]]), Err(CommonError::ZeroValue(_))));
+        vm.set_sender(owner);
+        contract.pause_campaign(campaign_ids[0]).unwrap();
+        vm.set_sender(user);
+        assert!(matches!(contract.claim_all(campaign_ids.clone()), Err(CommonError::CampaignPaused(_))));
+        vm.set_sender(owner);
+        contract.unpause_campaign(campaign_ids[0]).unwrap();
 
-        assert!(result.is_ok());
-        
-        let owner_check = contract.assert_owner();
-        assert!(owner_check.is_ok());
-        
-        let vm2 = TestVMBuilder::new()
-            .sender(Address::from([0x02; 20]))
-            .build();
-        
-        let mut contract2 = RewardProcessor::from(&vm2);
-        
-        let unauthorized_check = contract2.update_multiply_factor(U256::from(2000000));
-        assert!(unauthorized_check.is_err());
-        assert!(matches!(
-            unauthorized_check.unwrap_err(),
-            CommonError::Unauthorized(_)
-        ));
+        vm.set_sender(user);
+        let logs_before = vm.get_emitted_logs().len();
+        assert_eq!(contract.claim_all(campaign_ids.clone()).unwrap(), total);
+        assert_eq!(vm.balance(user), total);
+        assert_eq!(contract.total_claimed(), total);
+        let logs = vm.get_emitted_logs();
+        assert_eq!(logs.len(), logs_before + 2);
+        let event = BatchRewardClaimed::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((event.user, event.token, event.amount, event.campaign_count), (user, Address::ZERO, total, U256::from(2)));
+        assert!(matches!(contract.claim_all(campaign_ids), Err(CommonError::ZeroValue(_))));
+    }
 
-        let multiply_factor_check = contract.multiply_factor.get();
-        assert_eq!(multiply_factor_check, U256::from(1000000));
+    #[test]
+    fn test_claim_receipt_splits_components() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(1_000_000));
+        vm.set_block_timestamp(1000);
+        let breakdown = contract.calculate_reward_detailed(U256::from(10_000), U256::from(1000), U256::from(1000), U256::from(2000), true, true).unwrap();
+        assert!(breakdown.bonus > U256::ZERO && breakdown.strict_bonus > U256::ZERO);
+        let reward = contract.accrue_reward(user, U256::from(10_000), U256::from(1000), U256::from(2000), true, true).unwrap();
+        assert_eq!(reward, breakdown.total);
+
+        vm.set_sender(user);
+        let part = reward / U256::from(4);
+        contract.claim_amount(part).unwrap();
+        let logs = vm.get_emitted_logs();
+        let receipt = &logs[logs.len() - 2];
+        let event = ClaimReceipt::decode_raw_log(receipt.0.iter().copied(), &receipt.1, true).unwrap();
+        let (bonus, strict_bonus) = (breakdown.bonus * part / reward, breakdown.strict_bonus * part / reward);
+        assert_eq!((event.user, event.campaign_id, event.total), (user, U256::ZERO, part));
+        assert_eq!((event.bonus, event.strict_bonus, event.base), (bonus, strict_bonus, part - bonus - strict_bonus));
+
+        contract.claim().unwrap();
+        let logs = vm.get_emitted_logs();
+        let receipt = &logs[logs.len() - 2];
+        let event = ClaimReceipt::decode_raw_log(receipt.0.iter().copied(), &receipt.1, true).unwrap();
+        assert_eq!(event.total, reward - part);
+        assert_eq!((event.bonus, event.strict_bonus), (breakdown.bonus - bonus, breakdown.strict_bonus - strict_bonus));
+    }
+
+
+    #[test]
+    fn test_reentrant_payout_reverts() {
+        let owner = Address::new([0x01; 20]);
+        let users = [Address::new([0x02; 20]), Address::new([0x03; 20]), Address::new([0x04; 20])];
+        let token = Address::new([0x05; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(1_000_000));
+        mock_token_metadata(&vm, token, 18, "RWD");
+        contract.approve_token(token).unwrap();
+        contract.set_payout_token(token).unwrap();
+        vm.set_block_timestamp(1000);
+        for user in users {
+            contract.accrue_reward(user, U256::from(10_000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        }
 
-        let update_result = contract.update_multiply_factor(U256::from(2000000));
-        assert!(update_result.is_ok());
+        // Claims made from inside a transfer, as a receiving contract's callback would.
+        vm.set_sender(users[0]);
+        assert!(matches!(contract.non_reentrant(|this| this.claim()), Err(CommonError::ReentrantCall(_))));
+        vm.set_sender(users[1]);
+        assert!(matches!(contract.non_reentrant(|this| this.claim_erc20()), Err(CommonError::ReentrantCall(_))));
+        assert_eq!(vm.balance(users[0]), U256::ZERO);
 
-        let multiply_factor_check2 = contract.multiply_factor.get();
-        assert_eq!(multiply_factor_check2, U256::from(2000000));
+        // The lock is released once the outer call returns.
+        vm.set_sender(users[2]);
+        let reward = contract.claim().unwrap();
+        assert_eq!(vm.balance(users[2]), reward);
     }
 
-    #[test]
-    fn test_transfer_ownership_success() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
 
+    #[test]
+    fn test_treasury_deposits_and_withdrawal() {
+        let owner = Address::new([0x01; 20]);
+        let funder = Address::new([0x02; 20]);
+        let user = Address::new([0x03; 20]);
+        let token = Address::new([0x70; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
 
-        let result = contract.constructor(U256::from(1000000));
-        assert!(result.is_ok());
+        vm.set_sender(funder);
+        assert!(matches!(contract.deposit(), Err(CommonError::ZeroValue(_))));
+        vm.set_value(U256::from(10_000));
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        contract.deposit().unwrap();
+        vm.set_value(U256::ZERO);
+        assert_eq!(contract.treasury_balance(), U256::from(10_000));
+        let logs = vm.get_emitted_logs();
+        let event = TreasuryDeposited::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((event.depositor, event.token, event.amount), (funder, Address::ZERO, U256::from(10_000)));
 
-        let initial_owner = contract.owner.get();
-        assert_eq!(initial_owner, Address::from([0x01; 20]));
+        assert!(matches!(contract.deposit_token(token, U256::from(500)), Err(CommonError::TokenNotApproved(_))));
+        vm.set_sender(owner);
+        mock_token_metadata(&vm, token, 18, "RWD");
+        contract.approve_token(token).unwrap();
+        vm.set_sender(funder);
+        let pull = IERC20::transferFromCall { from: funder, to: vm.contract_address(), amount: U256::from(500) }.abi_encode();
+        vm.mock_call(token, pull, Ok(IERC20::transferFromCall::abi_encode_returns(&(true,))));
+        contract.deposit_token(token, U256::from(500)).unwrap();
+        assert_eq!(contract.treasury_token_balance(token), U256::from(500));
 
-        let new_owner_address = Address::from([0x03; 20]);
-        let transfer_result = contract.transfer_ownership(new_owner_address);
-        
-        assert!(transfer_result.is_ok());
-        
-        let updated_owner = contract.owner.get();
-        assert_eq!(updated_owner, new_owner_address);
+        // Rewards owed stay behind.
+        vm.set_sender(owner);
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        assert!(matches!(contract.withdraw(owner, U256::from(10_000)), Err(CommonError::AmountExceedsBalance(_))));
+        vm.set_sender(funder);
+        assert!(matches!(contract.withdraw(funder, U256::from(1000)), Err(CommonError::Unauthorized(_))));
+        vm.set_sender(owner);
+        let amount = U256::from(10_000) - reward;
+        contract.withdraw(funder, amount).unwrap();
+        assert_eq!(vm.balance(funder), amount);
+        assert_eq!(contract.treasury_balance(), reward);
+        let logs = vm.get_emitted_logs();
+        let event = TreasuryWithdrawn::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((event.recipient, event.amount), (funder, amount));
     }
 
     #[test]
-    fn test_transfer_ownership_unauthorized() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
-
+    fn test_treasury_follows_native_payouts() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let other_user = Address::new([0x03; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_value(U256::from(10_000));
+        vm.set_balance(vm.contract_address(), U256::from(10_000));
+        contract.deposit().unwrap();
+        vm.set_value(U256::ZERO);
 
-        let result = contract.constructor(U256::from(1000000));
-        assert!(result.is_ok());
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        let other_reward = contract.accrue_reward(other_user, U256::from(2000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        vm.set_sender(user);
+        assert_eq!(contract.claim().unwrap(), reward);
+        assert_eq!(contract.treasury_balance(), contract.contract_balance());
+        assert_eq!(contract.treasury_balance(), U256::from(10_000) - reward);
 
-        let vm2 = TestVMBuilder::new()
-            .sender(Address::from([0x02; 20]))
-            .build();
-        
-        let mut contract2 = RewardProcessor::from(&vm2);
-        
-        let new_owner_address = Address::from([0x03; 20]);
-        let transfer_result = contract2.transfer_ownership(new_owner_address);
-        
-        assert!(transfer_result.is_err());
-        assert!(matches!(
-            transfer_result.unwrap_err(),
-            CommonError::Unauthorized(_)
-        ));
-        
-        let owner_check = contract.owner.get();
-        assert_eq!(owner_check, Address::from([0x01; 20]));
+        // The surplus leaves only what the unclaimed reward needs.
+        vm.set_sender(owner);
+        assert_eq!(contract.withdraw_surplus().unwrap(), U256::from(10_000) - reward - other_reward);
+        assert_eq!(contract.treasury_balance(), contract.contract_balance());
+        assert_eq!(contract.treasury_balance(), other_reward);
+        assert!(matches!(contract.withdraw(owner, U256::from(1)), Err(CommonError::AmountExceedsBalance(_))));
     }
 
-    #[test]
-    fn test_time_based_reward_decay() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
 
+    #[test]
+    fn test_claim_fee() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let collector = Address::new([0x03; 20]);
+        let token = Address::new([0x70; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
-        let result = contract.constructor(U256::from(5000));
-        assert!(result.is_ok());
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(1_000_000));
 
-        let amount = U256::from(1000);
-        let start_time = U256::from(1000);
-        let end_time = U256::from(2000);
-
-        let reward_at_start = contract.calculate_reward_at_time(amount, U256::from(1000), start_time, end_time, false, false);
-        assert_eq!(reward_at_start, amount);
+        assert!(matches!(contract.set_claim_fee_bps(U256::from(MAX_CLAIM_FEE_BPS + 1)), Err(CommonError::FeeTooHigh(_))));
+        contract.set_claim_fee_bps(U256::from(250)).unwrap();
+        contract.set_fee_recipient(collector).unwrap();
+        assert_eq!((contract.claim_fee_bps(), contract.fee_recipient()), (U256::from(250), collector));
+        vm.set_sender(user);
+        assert!(matches!(contract.set_claim_fee_bps(U256::ZERO), Err(CommonError::Unauthorized(_))));
+        assert!(matches!(contract.set_fee_recipient(user), Err(CommonError::Unauthorized(_))));
 
-        let reward_at_middle = contract.calculate_reward_at_time(amount, U256::from(1500), start_time, end_time, false, false);
-        assert_eq!(reward_at_middle, U256::from(750));
+        vm.set_sender(owner);
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(user, U256::from(10_000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        vm.set_sender(user);
+        assert_eq!(contract.claim().unwrap(), reward);
+        let fee = reward * U256::from(250) / U256::from(BPS_DENOMINATOR);
+        assert!(fee > U256::ZERO);
+        assert_eq!(vm.balance(collector), fee);
+        assert_eq!(vm.balance(user), reward - fee);
+        let logs = vm.get_emitted_logs();
+        let fee_log = logs.iter().rev().find_map(|log| FeeCollected::decode_raw_log(log.0.iter().copied(), &log.1, true).ok()).unwrap();
+        assert_eq!((fee_log.user, fee_log.token, fee_log.recipient, fee_log.amount), (user, Address::ZERO, collector, fee));
 
-        let reward_at_end = contract.calculate_reward_at_time(amount, U256::from(2000), start_time, end_time, false, false);
-        assert_eq!(reward_at_end, U256::from(500));
+        // Token claims pay the fee in the token.
+        vm.set_sender(owner);
+        mock_token_metadata(&vm, token, 18, "RWD");
+        contract.approve_token(token).unwrap();
+        contract.set_payout_token(token).unwrap();
+        let reward = contract.accrue_reward(user, U256::from(10_000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        let fee = reward * U256::from(250) / U256::from(BPS_DENOMINATOR);
+        let to_user = IERC20::transferCall { to: user, amount: reward - fee }.abi_encode();
+        let to_collector = IERC20::transferCall { to: collector, amount: fee }.abi_encode();
+        vm.mock_call(token, to_collector, Ok(IERC20::transferCall::abi_encode_returns(&(true,))));
+        vm.mock_call(token, to_user.clone(), Ok(IERC20::transferCall::abi_encode_returns(&(false,))));
+        vm.set_sender(user);
+        assert!(matches!(contract.claim_erc20(), Err(CommonError::TransferFailed(_))));
     }
 
-    #[test]
-    fn test_time_based_reward_with_bonuses() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
 
+    #[test]
+    fn test_rescue_assets() {
+        let owner = Address::new([0x01; 20]);
+        let sponsor = Address::new([0x02; 20]);
+        let user = Address::new([0x03; 20]);
+        let token = Address::new([0x70; 20]);
+        let stray = Address::new([0x71; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
-        let result = contract.constructor(U256::from(5000));
-        assert!(result.is_ok());
-
-        let amount = U256::from(1000);
-        let start_time = U256::from(1000);
-        let end_time = U256::from(2000);
+        contract.constructor(U256::from(5000)).unwrap();
+        mock_token_metadata(&vm, token, 18, "RWD");
+        contract.approve_token(token).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        let pull = IERC20::transferFromCall { from: sponsor, to: vm.contract_address(), amount: U256::from(1500) }.abi_encode();
+        vm.mock_call(token, pull, Ok(IERC20::transferFromCall::abi_encode_returns(&(true,))));
+        vm.set_sender(sponsor);
+        contract.fund_campaign(campaign_id, token, U256::from(1500)).unwrap();
+        assert_eq!(contract.pooled_balance(token), U256::from(1500));
 
-        let reward_with_bonuses = contract.calculate_reward_at_time(amount, U256::from(1000), start_time, end_time, true, true);
-        
-        let expected = U256::from(1000) + U256::from(100) + U256::from(500);
-        assert_eq!(reward_with_bonuses, expected);
+        // Only what exceeds the pool can be rescued.
+        let balance_of = |account| IERC20::balanceOfCall { account }.abi_encode();
+        vm.mock_static_call(token, balance_of(vm.contract_address()), Ok(IERC20::balanceOfCall::abi_encode_returns(&(U256::from(1600),))));
+        vm.mock_static_call(stray, balance_of(vm.contract_address()), Ok(IERC20::balanceOfCall::abi_encode_returns(&(U256::from(40),))));
+        assert!(matches!(contract.rescue_token(stray, sponsor, U256::from(40)), Err(CommonError::Unauthorized(_))));
+        vm.set_sender(owner);
+        assert!(matches!(contract.rescue_token(token, sponsor, U256::from(101)), Err(CommonError::AmountExceedsBalance(_))));
+        contract.rescue_token(token, sponsor, U256::from(100)).unwrap();
+        contract.rescue_token(stray, sponsor, U256::from(40)).unwrap();
+        let logs = vm.get_emitted_logs();
+        let event = AssetsRescued::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((event.token, event.recipient, event.amount), (stray, sponsor, U256::from(40)));
 
-        let reward_middle_with_bonuses = contract.calculate_reward_at_time(amount, U256::from(1500), start_time, end_time, true, true);
-        
-        let expected_middle = U256::from(750) + U256::from(100) + U256::from(500);
-        assert_eq!(reward_middle_with_bonuses, expected_middle);
+        // Native currency owed as rewards or deposited in the treasury stays.
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(user, U256::from(1000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        vm.set_value(U256::from(2000));
+        contract.deposit().unwrap();
+        vm.set_value(U256::ZERO);
+        vm.set_balance(vm.contract_address(), U256::from(2500) + reward);
+        assert!(matches!(contract.rescue_eth(owner, U256::from(501)), Err(CommonError::AmountExceedsBalance(_))));
+        contract.rescue_eth(owner, U256::from(500)).unwrap();
+        assert_eq!(vm.balance(owner), U256::from(500));
     }
 
+
     #[test]
-    fn test_time_based_reward_edge_cases() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
+    fn test_fund_with_permit() {
+        let owner = Address::new([0x01; 20]);
+        let funder = Address::new([0x02; 20]);
+        let token = Address::new([0x70; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        mock_token_metadata(&vm, token, 18, "RWD");
+        contract.approve_token(token).unwrap();
+
+        let (deadline, v, r, s) = (U256::from(5000), 27u8, B256::repeat_byte(0x11), B256::repeat_byte(0x22));
+        let amount = U256::from(800);
+        let permit = IERC20Permit::permitCall { owner: funder, spender: vm.contract_address(), value: amount, deadline, v, r, s }.abi_encode();
+        let pull = IERC20::transferFromCall { from: funder, to: vm.contract_address(), amount }.abi_encode();
+        vm.mock_call(token, permit, Ok(Vec::new()));
+        vm.mock_call(token, pull.clone(), Ok(IERC20::transferFromCall::abi_encode_returns(&(false,))));
 
+        vm.set_sender(funder);
+        assert!(matches!(contract.fund_with_permit(token, U256::ZERO, deadline, v, r, s), Err(CommonError::ZeroValue(_))));
+        assert!(matches!(contract.fund_with_permit(token, amount, deadline, v, r, s), Err(CommonError::TransferFailed(_))));
+        vm.mock_call(token, pull, Ok(IERC20::transferFromCall::abi_encode_returns(&(true,))));
+        contract.fund_with_permit(token, amount, deadline, v, r, s).unwrap();
+        assert_eq!(contract.treasury_token_balance(token), amount);
+        let logs = vm.get_emitted_logs();
+        let event = TreasuryDeposited::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((event.depositor, event.token, event.amount), (funder, token, amount));
+    }
+
+
+    #[test]
+    fn test_campaign_payout_legs() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let token = Address::new([0x70; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
-        let result = contract.constructor(U256::from(5000));
-        assert!(result.is_ok());
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(100_000));
+        mock_token_metadata(&vm, token, 18, "RWD");
+        contract.approve_token(token).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_claim_window(campaign_id, U256::from(1000), U256::ZERO).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
 
-        let amount = U256::from(1000);
-        let start_time = U256::from(1000);
-        let end_time = U256::from(2000);
+        let (tokens, weights) = (vec![token, Address::ZERO], vec![U256::from(7000), U256::from(3000)]);
+        assert!(matches!(contract.set_campaign_payout_legs(campaign_id, tokens.clone(), vec![U256::from(7000)]), Err(CommonError::LengthMismatch(_))));
+        assert!(matches!(contract.set_campaign_payout_legs(campaign_id, tokens.clone(), vec![U256::from(7000), U256::from(2000)]), Err(CommonError::InvalidPayoutLegs(_))));
+        contract.set_campaign_payout_legs(campaign_id, tokens.clone(), weights.clone()).unwrap();
+        assert_eq!(contract.campaign_payout_legs(campaign_id), (tokens, weights));
+        contract.activate_campaign(campaign_id).unwrap();
 
-        let reward_before_start = contract.calculate_reward_at_time(amount, U256::from(500), start_time, end_time, false, false);
-        assert_eq!(reward_before_start, amount);
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false).unwrap();
+        let token_share = reward * U256::from(7000) / U256::from(BPS_DENOMINATOR);
+        let transfer = IERC20::transferCall { to: user, amount: token_share }.abi_encode();
+        vm.mock_call(token, transfer, Ok(IERC20::transferCall::abi_encode_returns(&(true,))));
 
-        let reward_after_end = contract.calculate_reward_at_time(amount, U256::from(3000), start_time, end_time, false, false);
-        assert_eq!(reward_after_end, U256::from(500));
+        // Token legs are paid from the treasury.
+        let pull = IERC20::transferFromCall { from: owner, to: vm.contract_address(), amount: U256::from(5000) }.abi_encode();
+        vm.mock_call(token, pull, Ok(IERC20::transferFromCall::abi_encode_returns(&(true,))));
+        contract.deposit_token(token, U256::from(5000)).unwrap();
+
+        vm.set_sender(user);
+        assert_eq!(contract.claim_vested().unwrap(), reward);
+        assert_eq!(vm.balance(user), reward - token_share);
+        assert_eq!(contract.treasury_token_balance(token), U256::from(5000) - token_share);
     }
 
-    #[test]
-    fn test_constructor_invalid_multiply_factor() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
 
+    #[test]
+    fn test_weth_payout() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let weth = Address::new([0x77; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
-        
-        let result = contract.constructor(U256::ZERO);
-        assert!(result.is_err());
-        assert!(matches!(
-            result.unwrap_err(),
-            ConstructorError::InvalidMultiplyFactor(_)
-        ));
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(1_000_000));
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(user, U256::from(10_000), U256::from(1000), U256::from(2000), false, false).unwrap();
+
+        vm.set_sender(user);
+        assert!(matches!(contract.set_weth(weth), Err(CommonError::Unauthorized(_))));
+        contract.set_receive_weth(true).unwrap();
+        assert!(contract.receives_weth(user));
+        vm.set_sender(owner);
+        contract.set_weth(weth).unwrap();
+        assert_eq!(contract.weth(), weth);
+
+        vm.mock_call(weth, IWETH::depositCall {}.abi_encode(), Ok(Vec::new()));
+        let transfer = IERC20::transferCall { to: user, amount: reward }.abi_encode();
+        vm.mock_call(weth, transfer, Ok(IERC20::transferCall::abi_encode_returns(&(true,))));
+        vm.set_sender(user);
+        assert_eq!(contract.claim().unwrap(), reward);
+        assert_eq!(vm.balance(user), U256::ZERO);
     }
 
-    #[test]
-    fn test_constructor_valid_initialization() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
 
+    #[test]
+    fn test_withdrawal_queue() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let guardian = Address::new([0x03; 20]);
+        let keeper = Address::new([0x04; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
-        
-        let multiply_factor = U256::from(12000);
-        let result = contract.constructor(multiply_factor);
-        assert!(result.is_ok());
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(1_000_000));
+        contract.set_guardian(guardian).unwrap();
+        contract.set_withdrawal_delay(U256::from(3600)).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(user, U256::from(10_000), U256::from(1000), U256::from(2000), false, false).unwrap();
 
-        // Check initial values
-        assert_eq!(contract.multiply_factor.get(), multiply_factor);
-        assert_eq!(contract.owner.get(), Address::from([0x01; 20]));
-        assert_eq!(contract.percentage_denominator.get(), U256::from(10000));
-        assert_eq!(contract.percentage_bonus.get(), U256::from(1000));
+        vm.set_sender(user);
+        assert_eq!(contract.claim().unwrap(), reward);
+        assert_eq!(vm.balance(user), U256::ZERO);
+        assert_eq!(contract.withdrawal_count(), U256::from(1));
+        let withdrawal_id = U256::from(1);
+        assert_eq!(contract.withdrawal(withdrawal_id), (user, U256::ZERO, reward, U256::from(4600), false, false));
+        assert_eq!(contract.reserved_balance(), reward);
+
+        vm.set_sender(keeper);
+        assert!(matches!(contract.execute_withdrawal(U256::from(2)), Err(CommonError::WithdrawalNotFound(_))));
+        assert!(matches!(contract.execute_withdrawal(withdrawal_id), Err(CommonError::WithdrawalNotReady(_))));
+        assert!(matches!(contract.freeze_withdrawal(withdrawal_id, true), Err(CommonError::Unauthorized(_))));
+        vm.set_sender(guardian);
+        contract.freeze_withdrawal(withdrawal_id, true).unwrap();
+        vm.set_block_timestamp(4600);
+        vm.set_sender(keeper);
+        assert!(matches!(contract.execute_withdrawal(withdrawal_id), Err(CommonError::WithdrawalLocked(_))));
+
+        vm.set_sender(guardian);
+        contract.freeze_withdrawal(withdrawal_id, false).unwrap();
+        vm.set_sender(keeper);
+        assert_eq!(contract.execute_withdrawal(withdrawal_id).unwrap(), reward);
+        assert_eq!(vm.balance(user), reward);
+        assert_eq!(contract.reserved_balance(), U256::ZERO);
+        let logs = vm.get_emitted_logs();
+        let event = WithdrawalExecuted::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((event.withdrawal_id, event.user, event.amount), (withdrawal_id, user, reward));
+        assert!(matches!(contract.execute_withdrawal(withdrawal_id), Err(CommonError::AlreadyClaimed(_))));
     }
 
-    #[test]
-    fn test_update_percentage_bonus_success() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
 
+    #[test]
+    fn test_queued_withdrawal_survives_shutdown() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
-        let result = contract.constructor(U256::from(5000));
-        assert!(result.is_ok());
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(1_000_000));
+        contract.set_withdrawal_delay(U256::from(3600)).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_reward(user, U256::from(10_000), U256::from(1000), U256::from(2000), false, false).unwrap();
+        vm.set_sender(user);
+        contract.claim().unwrap();
 
-        assert_eq!(contract.percentage_bonus.get(), U256::from(1000));
+        vm.set_sender(owner);
+        contract.schedule_emergency_shutdown().unwrap();
+        vm.set_block_timestamp(1000 + SHUTDOWN_TIMELOCK);
+        confirm_by_owner_and_guardian(&vm, &mut contract, owner, ACTION_EMERGENCY_SHUTDOWN);
+        contract.emergency_shutdown().unwrap();
 
-        let update_result = contract.update_percentage_bonus(U256::from(2000));
-        assert!(update_result.is_ok());
-        assert_eq!(contract.percentage_bonus.get(), U256::from(2000));
+        vm.set_sender(user);
+        assert_eq!(contract.execute_withdrawal(U256::from(1)).unwrap(), reward);
+        assert_eq!(vm.balance(user), reward);
+        assert_eq!(contract.reserved_balance(), U256::ZERO);
     }
 
     #[test]
-    fn test_update_percentage_bonus_unauthorized() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
-
+    fn test_rollover_budget() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
-        let result = contract.constructor(U256::from(5000));
-        assert!(result.is_ok());
+        contract.constructor(U256::from(5000)).unwrap();
+        let from_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_claim_window(from_id, U256::from(1000), U256::from(3000)).unwrap();
+        contract.set_campaign_budget(from_id, U256::from(10_000)).unwrap();
+        contract.activate_campaign(from_id).unwrap();
+        let to_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(4000), U256::from(5000)).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_for_campaign(from_id, user, U256::from(1000), false, false).unwrap();
 
-        let vm2 = TestVMBuilder::new()
-            .sender(Address::from([0x02; 20]))
-            .build();
-        
-        let mut contract2 = RewardProcessor::from(&vm2);
-        
-        let update_result = contract2.update_percentage_bonus(U256::from(2000));
-        assert!(update_result.is_err());
-        assert!(matches!(
-            update_result.unwrap_err(),
-            CommonError::Unauthorized(_)
-        ));
+        assert!(matches!(contract.rollover_budget(from_id, to_id), Err(CommonError::ClaimWindowOpen(_))));
+        vm.set_block_timestamp(3001);
+        assert!(matches!(contract.rollover_budget(from_id, from_id), Err(CommonError::InvalidCampaignState(_))));
+        vm.set_sender(user);
+        assert!(matches!(contract.rollover_budget(from_id, to_id), Err(CommonError::Unauthorized(_))));
+        vm.set_sender(owner);
+        assert_eq!(contract.rollover_budget(from_id, to_id).unwrap(), U256::from(10_000) - reward);
+        assert_eq!(contract.campaign_budget(from_id).unwrap(), (reward, reward));
+        assert_eq!(contract.expired_rewards(from_id), reward);
+        assert_eq!(contract.campaign_budget(to_id).unwrap().0, U256::from(10_000) - reward);
+        let logs = vm.get_emitted_logs();
+        let event = BudgetRolledOver::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((event.from_id, event.to_id, event.budget), (from_id, to_id, U256::from(10_000) - reward));
     }
 
-    #[test]
-    fn test_update_percentage_bonus_zero_value() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
 
+    #[test]
+    fn test_campaign_report_on_finalize() {
+        let owner = Address::new([0x01; 20]);
+        let users = [Address::new([0x02; 20]), Address::new([0x03; 20])];
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
-        let result = contract.constructor(U256::from(5000));
-        assert!(result.is_ok());
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(100_000));
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_claim_window(campaign_id, U256::from(1000), U256::ZERO).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10_000)).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
 
-        let update_result = contract.update_percentage_bonus(U256::ZERO);
-        assert!(update_result.is_err());
-        assert!(matches!(
-            update_result.unwrap_err(),
-            CommonError::ZeroValue(_)
-        ));
+        vm.set_block_timestamp(1000);
+        let mut issued = U256::ZERO;
+        for user in users {
+            issued += contract.accrue_for_campaign(campaign_id, user, U256::from(1000), false, false).unwrap();
+        }
+        vm.set_block_timestamp(1500);
+        issued += contract.accrue_for_campaign(campaign_id, users[0], U256::from(1000), false, false).unwrap();
+        let multipliers = [1000, 1000, 1500].map(|time| contract.time_multiplier(U256::from(time), U256::from(1000), U256::from(2000)).unwrap());
+        let mut paid = U256::ZERO;
+        for user in users {
+            vm.set_sender(user);
+            paid += contract.claim_vested().unwrap();
+        }
+
+        vm.set_sender(owner);
+        vm.set_block_timestamp(2001);
+        contract.finalize_campaign(campaign_id).unwrap();
+        let logs = vm.get_emitted_logs();
+        let report = CampaignReport::decode_raw_log(logs.last().unwrap().0.iter().copied(), &logs.last().unwrap().1, true).unwrap();
+        assert_eq!((report.campaign_id, report.claim_count, report.claimant_count, report.total_paid), (campaign_id, U256::from(2), U256::from(2), paid));
+        assert_eq!(report.average_multiplier, multipliers.iter().fold(U256::ZERO, |sum, multiplier| sum + multiplier) / U256::from(3));
+        assert_eq!(report.budget_utilization_bps, issued * U256::from(BPS_DENOMINATOR) / U256::from(10_000));
     }
 
     #[test]
-    fn test_update_multiply_factor_zero_value() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
-
+    fn test_lucky_draw_only_doubles_quotes() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
-        let result = contract.constructor(U256::from(5000));
-        assert!(result.is_ok());
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_block_timestamp(1000);
+        hit_lucky_draw(&vm, &contract);
 
-        let update_result = contract.update_multiply_factor(U256::ZERO);
-        assert!(update_result.is_err());
-        assert!(matches!(
-            update_result.unwrap_err(),
-            CommonError::InvalidMultiplyFactor(_)
-        ));
+        let (amount, start_time, end_time) = (U256::from(10_000), U256::from(1000), U256::from(2000));
+        let quote = contract.calculate_reward(amount, start_time, end_time, true, true).unwrap();
+        let breakdown = contract.calculate_reward_detailed(amount, U256::from(1000), start_time, end_time, true, true).unwrap();
+        assert_eq!(breakdown.total, quote);
+        let reward = contract.accrue_reward(user, amount, start_time, end_time, true, true).unwrap();
+        assert_eq!(quote, reward * U256::from(2));
+        assert_eq!(reward, breakdown.decayed + breakdown.bonus + breakdown.strict_bonus);
+        assert_eq!(contract.balance_of(user).unwrap(), reward);
     }
 
+
     #[test]
-    fn test_get_pseudo_random_deterministic() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
+    fn test_format_units() {
+        assert_eq!(format_units(U256::from(12_500_000_000_000_000_000u128), 18), "12.5");
+        assert_eq!(format_units(U256::from(1_234_567), 6), "1.2345");
+        assert_eq!(format_units(U256::from(3_000_000), 6), "3");
+        assert_eq!(format_units(U256::from(42), 0), "42");
+        assert_eq!(format_units(U256::from(5), 2), "0.05");
+    }
 
+    #[test]
+    fn test_claim_summary() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let outsider = Address::new([0x03; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
-        let result = contract.constructor(U256::from(5000));
-        assert!(result.is_ok());
+        contract.constructor(U256::from(5000)).unwrap();
+        assert!(matches!(contract.claim_summary(user, U256::from(1)), Err(CommonError::CampaignNotFound(_))));
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_claim_window(campaign_id, U256::from(1000), U256::from(20_000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(10).pow(U256::from(21))).unwrap();
+        contract.set_campaign_restricted(campaign_id, true).unwrap();
+        contract.set_campaign_participants(campaign_id, vec![user], true).unwrap();
+        contract.activate_campaign(campaign_id).unwrap();
+        vm.set_block_timestamp(1000);
+        let reward = contract.accrue_for_campaign(campaign_id, user, U256::from(10).pow(U256::from(18)), false, false).unwrap();
+        assert_eq!(reward, U256::from(10).pow(U256::from(18)));
 
-        let random1 = contract.get_pseudo_random();
-        let random2 = contract.get_pseudo_random();
-        assert_eq!(random1, random2);
+        vm.set_block_timestamp(9000);
+        assert_eq!(contract.claim_summary(user, campaign_id).unwrap(), "Eligible: yes, est. reward: 1 ETH, window ends in 4 hours");
+        assert_eq!(contract.claim_summary(outsider, campaign_id).unwrap(), "Eligible: no, est. reward: 0 ETH, window ends in 4 hours");
+        vm.set_block_timestamp(20_001);
+        assert_eq!(contract.claim_summary(user, campaign_id).unwrap(), "Eligible: no, est. reward: 1 ETH, window closed");
     }
 
+
     #[test]
-    fn test_get_pseudo_random_different_states() {
-        let vm1 = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
+    fn test_integrator_fee_tiers() {
+        let owner = Address::new([0x01; 20]);
+        let user = Address::new([0x02; 20]);
+        let collector = Address::new([0x03; 20]);
+        let integrator = Address::new([0x04; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
+        let mut contract = RewardProcessor::from(&vm);
+        contract.constructor(U256::from(5000)).unwrap();
+        vm.set_balance(vm.contract_address(), U256::from(10).pow(U256::from(20)));
+        contract.set_claim_fee_bps(U256::from(300)).unwrap();
+        contract.set_fee_recipient(collector).unwrap();
 
-        let vm2 = TestVMBuilder::new()
-            .sender(Address::from([0x02; 20]))
-            .build();
+        let ether = U256::from(10).pow(U256::from(18));
+        let tiers = |thresholds: &[u64], fee_bps: &[u64]| (thresholds.iter().map(|t| U256::from(*t) * ether).collect::<Vec<_>>(), fee_bps.iter().map(|b| U256::from(*b)).collect::<Vec<_>>());
+        let (thresholds, fee_bps) = tiers(&[2, 1], &[200, 100]);
+        assert!(matches!(contract.set_fee_tiers(thresholds, fee_bps), Err(CommonError::InvalidFeeTiers(_))));
+        let (thresholds, fee_bps) = tiers(&[1, 2], &[100, 200]);
+        assert!(matches!(contract.set_fee_tiers(thresholds, fee_bps), Err(CommonError::InvalidFeeTiers(_))));
+        let (thresholds, fee_bps) = tiers(&[1, 2], &[200]);
+        assert!(matches!(contract.set_fee_tiers(thresholds, fee_bps), Err(CommonError::LengthMismatch(_))));
+        let (thresholds, fee_bps) = tiers(&[1, 3], &[200, 100]);
+        contract.set_fee_tiers(thresholds.clone(), fee_bps.clone()).unwrap();
+        assert_eq!(contract.fee_tiers(), (thresholds, fee_bps));
+        assert_eq!(contract.integrator_tier(integrator), (U256::ZERO, U256::from(300)));
+        assert_eq!(contract.integrator_next_threshold(integrator), ether);
 
-        let mut contract1 = RewardProcessor::from(&vm1);
-        let mut contract2 = RewardProcessor::from(&vm2);
-        
-        let result1 = contract1.constructor(U256::from(5000));
-        let result2 = contract2.constructor(U256::from(5000));
-        assert!(result1.is_ok());
-        assert!(result2.is_ok());
+        vm.set_sender(user);
+        contract.approve_claimer(integrator).unwrap();
+        let mut paid = U256::ZERO;
+        let mut claim_through_integrator = |contract: &mut RewardProcessor, fee_bps: u64| {
+            vm.set_sender(owner);
+            vm.set_block_timestamp(1000);
+            let reward = contract.accrue_reward(user, U256::from(2) * ether, U256::from(1000), U256::from(2000), false, false).unwrap();
+            vm.set_sender(integrator);
+            contract.claim_for(user).unwrap();
+            paid += reward - reward * U256::from(fee_bps) / U256::from(BPS_DENOMINATOR);
+            assert_eq!(vm.balance(user), paid);
+            reward
+        };
 
-        let random1 = contract1.get_pseudo_random();
-        let random2 = contract2.get_pseudo_random();
-        
-        assert!(random1 == true || random1 == false);
-        assert!(random2 == true || random2 == false);
-    }
+        // Until the owner registers them, routing claims builds no volume.
+        claim_through_integrator(&mut contract, 300);
+        assert_eq!(contract.integrator_volume(integrator), U256::ZERO);
+        vm.set_sender(integrator);
+        assert!(matches!(contract.set_integrator(integrator, true), Err(CommonError::Unauthorized(_))));
+        vm.set_sender(owner);
+        contract.set_integrator(integrator, true).unwrap();
+        assert!(contract.is_integrator(integrator));
 
-    #[test]
-    fn test_calculate_reward_current_time() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
+        // The first claim pays the base fee and lifts the integrator into tier 1.
+        let reward = claim_through_integrator(&mut contract, 300);
+        assert!(reward >= ether && reward < U256::from(3) * ether);
+        assert_eq!(contract.integrator_volume(integrator), reward);
+        assert_eq!(contract.integrator_tier(integrator), (U256::from(1), U256::from(200)));
+        assert_eq!(contract.integrator_next_threshold(integrator), U256::from(3) * ether);
+        let logs = vm.get_emitted_logs();
+        let change = logs.iter().rev().find_map(|log| IntegratorTierChanged::decode_raw_log(log.0.iter().copied(), &log.1, true).ok()).unwrap();
+        assert_eq!((change.integrator, change.previous_tier, change.tier, change.fee_bps), (integrator, U256::ZERO, U256::from(1), U256::from(200)));
 
-        let mut contract = RewardProcessor::from(&vm);
-        let result = contract.constructor(U256::from(5000));
-        assert!(result.is_ok());
+        claim_through_integrator(&mut contract, 200);
+        assert_eq!(contract.integrator_tier(integrator), (U256::from(2), U256::from(100)));
+        assert_eq!(contract.integrator_next_threshold(integrator), U256::ZERO);
+        claim_through_integrator(&mut contract, 100);
 
-        let amount = U256::from(1000);
-        let start_time = U256::from(1000);
-        let end_time = U256::from(2000);
+        // Claiming directly builds no volume, and tiers never charge more than the base fee.
+        assert_eq!(contract.integrator_volume(user), U256::ZERO);
+        vm.set_sender(owner);
+        contract.set_claim_fee_bps(U256::from(50)).unwrap();
+        assert_eq!(contract.integrator_tier(integrator), (U256::from(2), U256::from(50)));
 
-        let reward = contract.calculate_reward(amount, start_time, end_time, false, false);
-        
-        assert!(reward >= U256::from(500));
-        assert!(reward <= U256::from(2000));
+        // Executing someone's queued withdrawal is not routing their claim.
+        contract.set_withdrawal_delay(U256::from(3600)).unwrap();
+        let reward = contract.accrue_reward(user, U256::from(2) * ether, U256::from(1000), U256::from(2000), false, false).unwrap();
+        vm.set_sender(user);
+        contract.claim().unwrap();
+        let volume = contract.integrator_volume(integrator);
+        vm.set_block_timestamp(4600);
+        vm.set_sender(integrator);
+        contract.execute_withdrawal(contract.withdrawal_count()).unwrap();
+        assert_eq!(contract.integrator_volume(integrator), volume);
+        assert_eq!(vm.balance(user), paid + reward - reward * U256::from(50) / U256::from(BPS_DENOMINATOR));
     }
 
-    #[test]
-    fn test_reward_with_all_bonuses() {
-        let vm = TestVMBuilder::new()
-            .sender(Address::from([0x01; 20]))
-            .build();
 
+    #[test]
+    fn test_activation_requires_funded_budget() {
+        let owner = Address::new([0x01; 20]);
+        let sponsor = Address::new([0x02; 20]);
+        let token = Address::new([0x70; 20]);
+        let vm = TestVMBuilder::new().sender(owner).build();
         let mut contract = RewardProcessor::from(&vm);
-        let result = contract.constructor(U256::from(5000));
-        assert!(result.is_ok());
+        contract.constructor(U256::from(5000)).unwrap();
+        mock_token_metadata(&vm, token, 18, "RWD");
+        contract.approve_token(token).unwrap();
+        let campaign_id = contract.create_campaign(U256::ZERO, U256::ZERO, U256::ZERO, U256::from(1000), U256::from(2000)).unwrap();
+        contract.set_campaign_budget(campaign_id, U256::from(1000)).unwrap();
+        let pull = IERC20::transferFromCall { from: sponsor, to: vm.contract_address(), amount: U256::from(400) }.abi_encode();
+        vm.mock_call(token, pull, Ok(IERC20::transferFromCall::abi_encode_returns(&(true,))));
+        vm.set_sender(sponsor);
+        contract.fund_campaign(campaign_id, token, U256::from(400)).unwrap();
 
-        let amount = U256::from(1000);
-        let start_time = U256::from(1000);
-        let end_time = U256::from(2000);
+        // The pool covers 400 and the contract holds nothing else: 600 short.
+        let balance_of = IERC20::balanceOfCall { account: vm.contract_address() }.abi_encode();
+        let allowance = IERC20::allowanceCall { owner, spender: vm.contract_address() }.abi_encode();
+        let mock_backing = |balance: u64, allowed: u64| {
+            vm.mock_static_call(token, balance_of.clone(), Ok(IERC20::balanceOfCall::abi_encode_returns(&(U256::from(balance),))));
+            vm.mock_static_call(token, allowance.clone(), Ok(IERC20::allowanceCall::abi_encode_returns(&(U256::from(allowed),))));
+        };
+        mock_backing(400, 0);
+        vm.set_sender(owner);
+        assert!(matches!(contract.activate_campaign(campaign_id), Err(CommonError::BudgetNotFunded(_))));
+        mock_backing(650, 349);
+        assert!(matches!(contract.activate_campaign(campaign_id), Err(CommonError::BudgetNotFunded(_))));
+        assert_eq!(contract.campaign_state(campaign_id).unwrap(), CampaignState::Draft as u8);
 
-        let reward = contract.calculate_reward_at_time(amount, U256::from(1000), start_time, end_time, true, true);
-        
-        assert!(reward >= U256::from(1600));
-        assert!(reward <= U256::from(3200));
+        // Uncommitted balance and the owner's allowance together cover the rest.
+        mock_backing(650, 350);
+        contract.activate_campaign(campaign_id).unwrap();
+
+        // Raising the budget of an active campaign needs the same backing.
+        assert!(matches!(contract.set_campaign_budget(campaign_id, U256::from(1001)), Err(CommonError::BudgetNotFunded(_))));
+        mock_backing(651, 350);
+        contract.set_campaign_budget(campaign_id, U256::from(1001)).unwrap();
     }
 }
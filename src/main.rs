@@ -6,5 +6,9 @@ pub extern "C" fn main() {}
 
 #[cfg(feature = "export-abi")]
 fn main() {
-    stylus_hello_world::print_from_args();
+    if std::env::args().nth(1).as_deref() == Some("schemas") {
+        stylus_hello_world::schema::print_schemas();
+    } else {
+        stylus_hello_world::print_from_args();
+    }
 }
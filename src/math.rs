@@ -0,0 +1,122 @@
+//! WAD (1e18) and RAY (1e27) fixed-point helpers.
+//!
+//! Values are unsigned integers scaled by the unit, so `WAD` itself is 1.0.
+//! Products and quotients go through `mul_div` and round down.
+
+use stylus_sdk::alloy_primitives::U256;
+
+use crate::mul_div::mul_div;
+
+/// 1.0 with 18 decimals.
+pub const WAD: U256 = U256::from_limbs([1_000_000_000_000_000_000, 0, 0, 0]);
+
+/// 1.0 with 27 decimals.
+pub const RAY: U256 = U256::from_limbs([0x9fd0803ce8000000, 0x33b2e3c, 0, 0]);
+
+/// `a * b` for WAD values.
+pub fn wmul(a: U256, b: U256) -> Option<U256> {
+    mul_div(a, b, WAD)
+}
+
+/// `a / b` for WAD values; `None` when `b` is zero.
+pub fn wdiv(a: U256, b: U256) -> Option<U256> {
+    mul_div(a, WAD, b)
+}
+
+/// `a * b` for RAY values.
+pub fn rmul(a: U256, b: U256) -> Option<U256> {
+    mul_div(a, b, RAY)
+}
+
+/// `a / b` for RAY values; `None` when `b` is zero.
+pub fn rdiv(a: U256, b: U256) -> Option<U256> {
+    mul_div(a, RAY, b)
+}
+
+/// `x` raised to the integer power `n`, where `x` and the result are scaled by `unit`.
+///
+/// Uses square-and-multiply, so the cost is logarithmic in `n`.
+pub fn rpow(x: U256, n: U256, unit: U256) -> Option<U256> {
+    let mut result = unit;
+    let mut base = x;
+    let mut remaining = n;
+
+    while remaining > U256::ZERO {
+        if remaining & U256::from(1) == U256::from(1) {
+            result = mul_div(result, base, unit)?;
+        }
+        remaining >>= 1;
+        if remaining > U256::ZERO {
+            base = mul_div(base, base, unit)?;
+        }
+    }
+
+    Some(result)
+}
+
+/// Integer square root, rounded down.
+pub fn isqrt(x: U256) -> U256 {
+    if x < U256::from(2) {
+        return x;
+    }
+
+    // Newton's method from a power of two above the root; the sequence decreases until it settles.
+    let mut current = U256::from(1) << x.bit_len().div_ceil(2);
+    loop {
+        let next = (current + x / current) >> 1;
+        if next >= current {
+            return current;
+        }
+        current = next;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_units() {
+        assert_eq!(WAD, U256::from(10).pow(U256::from(18)));
+        assert_eq!(RAY, U256::from(10).pow(U256::from(27)));
+    }
+
+    #[test]
+    fn test_wmul_wdiv() {
+        let half = WAD / U256::from(2);
+        assert_eq!(wmul(U256::from(3) * WAD, half), Some(U256::from(3) * half));
+        assert_eq!(wdiv(U256::from(3) * WAD, half), Some(U256::from(6) * WAD));
+        assert_eq!(wdiv(WAD, U256::ZERO), None);
+        assert_eq!(wmul(U256::MAX, U256::from(2) * WAD), None);
+    }
+
+    #[test]
+    fn test_rmul_rdiv() {
+        let third = rdiv(RAY, U256::from(3) * RAY).unwrap();
+        assert_eq!(third, U256::from(333_333_333_333_333_333_333_333_333u128));
+        assert_eq!(rmul(third, U256::from(3) * RAY), Some(RAY - U256::from(1)));
+    }
+
+    #[test]
+    fn test_rpow() {
+        let two = U256::from(2) * WAD;
+        assert_eq!(rpow(two, U256::ZERO, WAD), Some(WAD));
+        assert_eq!(rpow(two, U256::from(10), WAD), Some(U256::from(1024) * WAD));
+
+        // 1.1^2 = 1.21 in basis points.
+        assert_eq!(rpow(U256::from(11000), U256::from(2), U256::from(10000)), Some(U256::from(12100)));
+        assert_eq!(rpow(two, U256::from(256), WAD), None);
+    }
+
+    #[test]
+    fn test_isqrt() {
+        let cases = [(0u64, 0u64), (1, 1), (2, 1), (3, 1), (4, 2), (15, 3), (16, 4), (17, 4), (1_000_000, 1000), (999_999, 999)];
+        for (x, root) in cases {
+            assert_eq!(isqrt(U256::from(x)), U256::from(root), "isqrt({x})");
+        }
+
+        let root = isqrt(U256::MAX);
+        assert_eq!(root, U256::from(u128::MAX));
+        assert!(root * root <= U256::MAX);
+    }
+}
@@ -0,0 +1,43 @@
+//! Merkle proofs over keccak256 with sorted pairs.
+//!
+//! Matches the trees built by OpenZeppelin's `StandardMerkleTree` and the
+//! `MerkleProof` library: each parent is the hash of its two children in
+//! ascending order, so proofs carry no left/right flags.
+
+use stylus_sdk::{alloy_primitives::B256, crypto::keccak};
+
+/// Whether `proof` links `leaf` to `root`.
+pub fn verify(proof: &[B256], root: B256, leaf: B256) -> bool {
+    proof.iter().fold(leaf, |node, sibling| hash_pair(node, *sibling)) == root
+}
+
+/// Parent of `a` and `b`.
+pub fn hash_pair(a: B256, b: B256) -> B256 {
+    let (low, high) = if a <= b { (a, b) } else { (b, a) };
+    keccak([low.as_slice(), high.as_slice()].concat())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_verify_four_leaf_tree() {
+        let leaves: [B256; 4] = core::array::from_fn(|i| keccak([i as u8]));
+        let left = hash_pair(leaves[0], leaves[1]);
+        let right = hash_pair(leaves[2], leaves[3]);
+        let root = hash_pair(left, right);
+
+        assert!(verify(&[leaves[1], right], root, leaves[0]));
+        assert!(verify(&[leaves[2], left], root, leaves[3]));
+        assert!(!verify(&[leaves[1], right], root, leaves[2]));
+        assert!(!verify(&[right, leaves[1]], root, leaves[0]));
+    }
+
+    #[test]
+    fn test_single_leaf_tree_needs_no_proof() {
+        let leaf = keccak(b"only");
+        assert!(verify(&[], leaf, leaf));
+        assert!(!verify(&[], leaf, keccak(b"other")));
+    }
+}
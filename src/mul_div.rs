@@ -0,0 +1,104 @@
+//! Full-precision `a * b / denominator` with a 512-bit intermediate product.
+//!
+//! Every multiply-then-divide in the reward math goes through here so that
+//! large amounts never lose their high bits before the division.
+
+use stylus_sdk::alloy_primitives::{ruint::UintTryFrom, U256, U512};
+
+/// How the remainder of a division is resolved.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Rounding {
+    Floor = 0,
+    Ceil = 1,
+    HalfUp = 2,
+}
+
+impl Rounding {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Floor),
+            1 => Some(Self::Ceil),
+            2 => Some(Self::HalfUp),
+            _ => None,
+        }
+    }
+}
+
+/// Computes `floor(a * b / denominator)`.
+///
+/// Returns `None` when `denominator` is zero or the quotient does not fit in 256 bits.
+pub fn mul_div(a: U256, b: U256, denominator: U256) -> Option<U256> {
+    mul_div_rounding(a, b, denominator, Rounding::Floor)
+}
+
+/// Computes `a * b / denominator` rounded according to `rounding`.
+pub fn mul_div_rounding(a: U256, b: U256, denominator: U256, rounding: Rounding) -> Option<U256> {
+    if denominator == U256::ZERO {
+        return None;
+    }
+
+    let product: U512 = a.widening_mul(b);
+    let denominator = U512::from(denominator);
+    let mut quotient = product / denominator;
+    let remainder = product % denominator;
+
+    let round_up = match rounding {
+        Rounding::Floor => false,
+        Rounding::Ceil => remainder != U512::ZERO,
+        Rounding::HalfUp => remainder >= denominator - remainder,
+    };
+    if round_up {
+        quotient += U512::from(1);
+    }
+
+    U256::uint_try_from(quotient).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mul_div_matches_naive_math() {
+        assert_eq!(mul_div(U256::from(1000), U256::from(7500), U256::from(10000)), Some(U256::from(750)));
+        assert_eq!(mul_div(U256::from(7), U256::from(3), U256::from(2)), Some(U256::from(10)));
+        assert_eq!(mul_div(U256::ZERO, U256::from(3), U256::from(2)), Some(U256::ZERO));
+    }
+
+    #[test]
+    fn test_mul_div_keeps_intermediate_precision() {
+        let result = mul_div(U256::MAX, U256::from(10000), U256::from(10000));
+        assert_eq!(result, Some(U256::MAX));
+
+        let result = mul_div(U256::MAX, U256::MAX, U256::MAX);
+        assert_eq!(result, Some(U256::MAX));
+    }
+
+    #[test]
+    fn test_mul_div_rejects_overflow_and_zero_denominator() {
+        assert_eq!(mul_div(U256::MAX, U256::from(2), U256::from(1)), None);
+        assert_eq!(mul_div(U256::from(1), U256::from(1), U256::ZERO), None);
+    }
+
+    #[test]
+    fn test_mul_div_rounding_modes() {
+        let cases = [
+            (U256::from(1001), U256::from(7500), U256::from(10000), [750u64, 751, 751]),
+            (U256::from(1001), U256::from(1000), U256::from(10000), [100, 101, 100]),
+            (U256::from(1001), U256::from(5000), U256::from(10000), [500, 501, 501]),
+            (U256::from(1000), U256::from(7500), U256::from(10000), [750, 750, 750]),
+        ];
+
+        for (a, b, denominator, expected) in cases {
+            assert_eq!(mul_div_rounding(a, b, denominator, Rounding::Floor), Some(U256::from(expected[0])));
+            assert_eq!(mul_div_rounding(a, b, denominator, Rounding::Ceil), Some(U256::from(expected[1])));
+            assert_eq!(mul_div_rounding(a, b, denominator, Rounding::HalfUp), Some(U256::from(expected[2])));
+        }
+    }
+
+    #[test]
+    fn test_mul_div_rounding_overflow() {
+        assert_eq!(mul_div_rounding(U256::MAX, U256::from(3), U256::from(2), Rounding::Ceil), None);
+        assert_eq!(mul_div_rounding(U256::MAX, U256::MAX, U256::MAX, Rounding::Ceil), Some(U256::MAX));
+    }
+}
@@ -0,0 +1,359 @@
+//! Stateless reward math: decay curves, streaming averages, compounding and bonus caps.
+//!
+//! Nothing here touches storage. Callers describe their configuration with a
+//! `DecayCurve` and get `None` back wherever the math would overflow, so other
+//! contracts can reuse the curves with their own parameters and error types.
+
+use stylus_sdk::alloy_primitives::U256;
+
+use crate::mul_div::{mul_div, mul_div_rounding, Rounding};
+
+/// Whether the first and last instant of a `[start, end]` range belong to it.
+///
+/// Applied to campaign accrual windows and claim windows. The shutdown timelock and the refund deadline
+/// are fixed instants and do not take it.
+/// The decay curve does not take it: the multiplier is continuous at both edges of its window, so it
+/// is the same whichever side an edge instant is counted on.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BoundaryPolicy {
+    InclusiveStartInclusiveEnd = 0,
+    InclusiveStartExclusiveEnd = 1,
+    ExclusiveStartInclusiveEnd = 2,
+    ExclusiveStartExclusiveEnd = 3,
+}
+
+impl BoundaryPolicy {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::InclusiveStartInclusiveEnd),
+            1 => Some(Self::InclusiveStartExclusiveEnd),
+            2 => Some(Self::ExclusiveStartInclusiveEnd),
+            3 => Some(Self::ExclusiveStartExclusiveEnd),
+            _ => None,
+        }
+    }
+
+    /// True once `time` is inside the range or past it.
+    pub fn has_started(self, time: U256, start: U256) -> bool {
+        match self {
+            Self::InclusiveStartInclusiveEnd | Self::InclusiveStartExclusiveEnd => time >= start,
+            Self::ExclusiveStartInclusiveEnd | Self::ExclusiveStartExclusiveEnd => time > start,
+        }
+    }
+
+    /// True once `time` is past the range.
+    pub fn has_ended(self, time: U256, end: U256) -> bool {
+        match self {
+            Self::InclusiveStartInclusiveEnd | Self::ExclusiveStartInclusiveEnd => time > end,
+            Self::InclusiveStartExclusiveEnd | Self::ExclusiveStartExclusiveEnd => time >= end,
+        }
+    }
+}
+
+/// Shape of the time multiplier between `start_time` and `end_time`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DecayMode {
+    /// Continuous decay from 100% to 50%.
+    Linear = 0,
+    /// Holds the linear value from the start of each `step_interval` until the next step.
+    Step = 1,
+    /// Two linear segments meeting at the configured decay midpoint.
+    TwoSegment = 2,
+    /// S-curve `3x² - 2x³` (smoothstep): slow at both ends of the window, fastest in the middle.
+    Sigmoid = 3,
+}
+
+impl DecayMode {
+    pub fn from_u8(value: u8) -> Option<Self> {
+        match value {
+            0 => Some(Self::Linear),
+            1 => Some(Self::Step),
+            2 => Some(Self::TwoSegment),
+            3 => Some(Self::Sigmoid),
+            _ => None,
+        }
+    }
+}
+
+/// Everything that shapes the time multiplier.
+#[derive(Clone, Copy, Debug)]
+pub struct DecayCurve {
+    pub mode: DecayMode,
+    pub rounding: Rounding,
+    /// Unit of `midpoint_fraction` and `midpoint_multiplier`, and 100% for streaming averages.
+    pub denominator: U256,
+    /// Plateau length for `DecayMode::Step`.
+    pub step_interval: U256,
+    /// Share of the window covered by the first segment of `DecayMode::TwoSegment`.
+    pub midpoint_fraction: U256,
+    /// Multiplier where the two segments of `DecayMode::TwoSegment` meet.
+    pub midpoint_multiplier: U256,
+    /// How long after `end_time` the last in-window value is held.
+    pub grace_period: U256,
+    /// Mirrors the curve so it ramps from 50% up to 100%.
+    pub growth: bool,
+}
+
+/// Decay from `max_multiplier` at `start_time` to half of it at `end_time`, or the mirrored ramp in growth mode.
+pub fn time_multiplier(curve: &DecayCurve, current_time: U256, start_time: U256, end_time: U256, max_multiplier: U256) -> Option<U256> {
+    let multiplier = decay_multiplier(curve, current_time, start_time, end_time, max_multiplier)?;
+    if curve.growth {
+        return max_multiplier.checked_add(max_multiplier / U256::from(2))?.checked_sub(multiplier);
+    }
+    Some(multiplier)
+}
+
+/// The decay curve itself, before the growth-mode mirror.
+pub fn decay_multiplier(curve: &DecayCurve, current_time: U256, start_time: U256, end_time: U256, max_multiplier: U256) -> Option<U256> {
+    let min_multiplier = max_multiplier / U256::from(2); // 50%
+
+    if current_time <= start_time {
+        return Some(max_multiplier);
+    }
+    if current_time >= end_time {
+        if current_time < end_time.saturating_add(curve.grace_period) {
+            // Hold the last in-window value instead of cliffing to the floor.
+            return decay_multiplier(curve, end_time - U256::from(1), start_time, end_time, max_multiplier);
+        }
+        return Some(min_multiplier);
+    }
+
+    let total_duration = end_time.checked_sub(start_time)?;
+    let mut elapsed_time = current_time.checked_sub(start_time)?;
+    let decay_range = max_multiplier - min_multiplier;
+    match curve.mode {
+        DecayMode::Linear => {}
+        DecayMode::Step => elapsed_time -= elapsed_time % curve.step_interval,
+        DecayMode::TwoSegment => {
+            return two_segment_multiplier(curve, elapsed_time, total_duration, max_multiplier, min_multiplier);
+        }
+        DecayMode::Sigmoid => {
+            // x = elapsed / total, so 3x² - 2x³ = elapsed² · (3·total - 2·elapsed) / total³.
+            let curve_value = elapsed_time
+                .checked_mul(elapsed_time)?
+                .checked_mul(U256::from(3).checked_mul(total_duration)?.checked_sub(U256::from(2).checked_mul(elapsed_time)?)?)?;
+            let cube = total_duration.checked_mul(total_duration)?.checked_mul(total_duration)?;
+            return max_multiplier.checked_sub(mul_div_rounding(decay_range, curve_value, cube, curve.rounding)?);
+        }
+    }
+
+    max_multiplier.checked_sub(mul_div_rounding(decay_range, elapsed_time, total_duration, curve.rounding)?)
+}
+
+/// Linear from `max_multiplier` to the midpoint value over the first `midpoint_fraction`
+/// of the window, then linear from there to `min_multiplier`.
+fn two_segment_multiplier(curve: &DecayCurve, elapsed_time: U256, total_duration: U256, max_multiplier: U256, min_multiplier: U256) -> Option<U256> {
+    let denominator = curve.denominator;
+    let fraction = curve.midpoint_fraction;
+    let midpoint = mul_div(curve.midpoint_multiplier, max_multiplier, denominator)?;
+
+    // Compare `elapsed / total` against `fraction / denominator` without dividing.
+    let elapsed_scaled = elapsed_time.checked_mul(denominator)?;
+    let midpoint_scaled = total_duration.checked_mul(fraction)?;
+
+    if elapsed_scaled < midpoint_scaled {
+        let decay = mul_div_rounding(max_multiplier.checked_sub(midpoint)?, elapsed_scaled, midpoint_scaled, curve.rounding)?;
+        max_multiplier.checked_sub(decay)
+    } else {
+        let remaining_scaled = total_duration.checked_mul(denominator.checked_sub(fraction)?)?;
+        let decay = mul_div_rounding(midpoint.checked_sub(min_multiplier)?, elapsed_scaled - midpoint_scaled, remaining_scaled, curve.rounding)?;
+        midpoint.checked_sub(decay)
+    }
+}
+
+/// Average multiplier over a window of `duration` seconds, as a `(numerator, denominator)` pair
+/// in `curve.denominator` units.
+///
+/// With `T = duration` and `E` the decayed elapsed time, the average is
+/// `max - range * (∫E dt) / T²`. Both sides are doubled to stay in integers:
+/// linear decay has `2∫E dt = T²`; step decay with interval `I` and `n = T / I`
+/// full steps has `2∫E dt = I²·n·(n - 1) + 2·n·I·(T - n·I)`.
+pub fn streamed_multiplier(curve: &DecayCurve, duration: U256) -> Option<(U256, U256)> {
+    let (numerator, denominator) = streamed_decay(curve, duration)?;
+    if curve.growth {
+        let max_multiplier = curve.denominator;
+        let bounds = max_multiplier.checked_add(max_multiplier / U256::from(2))?;
+        return Some((bounds.checked_mul(denominator)?.checked_sub(numerator)?, denominator));
+    }
+    Some((numerator, denominator))
+}
+
+fn streamed_decay(curve: &DecayCurve, duration: U256) -> Option<(U256, U256)> {
+    let max_multiplier = curve.denominator;
+    let min_multiplier = max_multiplier / U256::from(2);
+    let decay_range = max_multiplier - min_multiplier;
+    let duration_squared = duration.checked_mul(duration)?;
+
+    let doubled_integral = match curve.mode {
+        // Smoothstep is symmetric around the middle of the window, so it averages like a straight line.
+        DecayMode::Linear | DecayMode::Sigmoid => duration_squared,
+        DecayMode::TwoSegment => {
+            // The average of each segment is the mean of its end points, so the window length cancels out:
+            // `(f·(max + mid) + (D - f)·(mid + min)) / 2D` with `f` the midpoint fraction.
+            let fraction = curve.midpoint_fraction;
+            let midpoint = curve.midpoint_multiplier;
+            let numerator = fraction
+                .checked_mul(max_multiplier.checked_add(midpoint)?)?
+                .checked_add(max_multiplier.checked_sub(fraction)?.checked_mul(midpoint.checked_add(min_multiplier)?)?)?;
+            return Some((numerator, U256::from(2).checked_mul(max_multiplier)?));
+        }
+        DecayMode::Step => {
+            let interval = curve.step_interval;
+            let steps = duration / interval;
+            let full_steps = interval.checked_mul(interval)?.checked_mul(steps.checked_mul(steps.saturating_sub(U256::from(1)))?)?;
+            let last_step = U256::from(2).checked_mul(steps)?.checked_mul(interval.checked_mul(duration - steps * interval)?)?;
+            full_steps.checked_add(last_step)?
+        }
+    };
+
+    let numerator = U256::from(2)
+        .checked_mul(max_multiplier)?
+        .checked_mul(duration_squared)?
+        .checked_sub(decay_range.checked_mul(doubled_integral)?)?;
+    Some((numerator, U256::from(2).checked_mul(duration_squared)?))
+}
+
+/// Applies `rate` to `balance` for `periods` periods using square-and-multiply, both in `denominator` units.
+pub fn compound(balance: U256, rate: U256, periods: U256, denominator: U256) -> Option<U256> {
+    let mut factor = denominator.checked_add(rate)?;
+    let mut result = balance;
+    let mut remaining = periods;
+
+    while remaining > U256::ZERO {
+        if remaining & U256::from(1) == U256::from(1) {
+            result = mul_div(result, factor, denominator)?;
+        }
+        remaining >>= 1;
+        if remaining > U256::ZERO {
+            factor = mul_div(factor, factor, denominator)?;
+        }
+    }
+
+    Some(result)
+}
+
+/// Number of whole `interval`s elapsed since `genesis` at `time`; zero before genesis or when `interval` is zero.
+pub fn halving_epoch(genesis: U256, interval: U256, time: U256) -> U256 {
+    if interval == U256::ZERO || time < genesis {
+        return U256::ZERO;
+    }
+    (time - genesis) / interval
+}
+
+/// `value` halved once per elapsed halving epoch.
+pub fn halved(value: U256, genesis: U256, interval: U256, time: U256) -> U256 {
+    let epoch = halving_epoch(genesis, interval, time);
+    if epoch >= U256::from(256) {
+        return U256::ZERO;
+    }
+    value >> epoch.to::<usize>()
+}
+
+/// Part of `granted` unlocked at `time` by a schedule starting at `start`: nothing before `start + cliff`,
+/// then linear up to all of it at `start + duration`. A zero `duration` unlocks everything at the cliff.
+pub fn vested(granted: U256, start: U256, cliff: U256, duration: U256, time: U256) -> Option<U256> {
+    if time < start.checked_add(cliff)? {
+        return Some(U256::ZERO);
+    }
+    let elapsed = time - start;
+    if elapsed >= duration {
+        return Some(granted);
+    }
+    mul_div(granted, elapsed, duration)
+}
+
+/// Fits `bonus + strict_bonus` under `cap`, trimming the strict bonus first, then the percentage bonus.
+pub fn cap_bonuses(bonus: U256, strict_bonus: U256, cap: U256) -> (U256, U256) {
+    let strict_bonus = strict_bonus.min(cap);
+    (bonus.min(cap - strict_bonus), strict_bonus)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn linear() -> DecayCurve {
+        DecayCurve {
+            mode: DecayMode::Linear,
+            rounding: Rounding::Floor,
+            denominator: U256::from(10000),
+            step_interval: U256::from(100),
+            midpoint_fraction: U256::ZERO,
+            midpoint_multiplier: U256::ZERO,
+            grace_period: U256::ZERO,
+            growth: false,
+        }
+    }
+
+    fn at(curve: &DecayCurve, time: u64) -> U256 {
+        time_multiplier(curve, U256::from(time), U256::from(1000), U256::from(2000), U256::from(10000)).unwrap()
+    }
+
+    #[test]
+    fn test_time_multiplier_curves() {
+        let mut curve = linear();
+        assert_eq!([at(&curve, 500), at(&curve, 1250), at(&curve, 2000), at(&curve, 9999)], [10000, 8750, 5000, 5000].map(U256::from));
+
+        curve.mode = DecayMode::Step;
+        assert_eq!([at(&curve, 1150), at(&curve, 1199), at(&curve, 1200)], [9500, 9500, 9000].map(U256::from));
+
+        curve.grace_period = U256::from(100);
+        assert_eq!([at(&curve, 2050), at(&curve, 2100)], [5500, 5000].map(U256::from));
+
+        curve.mode = DecayMode::Linear;
+        curve.grace_period = U256::ZERO;
+        curve.growth = true;
+        assert_eq!([at(&curve, 1000), at(&curve, 1250), at(&curve, 2000)], [5000, 6250, 10000].map(U256::from));
+    }
+
+    #[test]
+    fn test_streamed_multiplier_averages() {
+        let mut curve = linear();
+        let (numerator, denominator) = streamed_multiplier(&curve, U256::from(1000)).unwrap();
+        assert_eq!(numerator / denominator, U256::from(7500));
+
+        curve.mode = DecayMode::TwoSegment;
+        curve.midpoint_fraction = U256::from(5000);
+        curve.midpoint_multiplier = U256::from(9000);
+        let (numerator, denominator) = streamed_multiplier(&curve, U256::from(1000)).unwrap();
+        assert_eq!(numerator / denominator, U256::from(8250));
+    }
+
+    #[test]
+    fn test_compound_and_overflow() {
+        let denominator = U256::from(10000);
+        assert_eq!(compound(U256::from(10000), U256::from(1000), U256::from(2), denominator), Some(U256::from(12100)));
+        assert_eq!(compound(U256::from(10000), U256::from(1000), U256::ZERO, denominator), Some(U256::from(10000)));
+        assert_eq!(compound(U256::MAX, U256::from(1000), U256::from(1), denominator), None);
+    }
+
+    #[test]
+    fn test_halving() {
+        let (genesis, interval) = (U256::from(1000), U256::from(100));
+        assert_eq!(halving_epoch(genesis, interval, U256::from(999)), U256::ZERO);
+        assert_eq!(halving_epoch(genesis, interval, U256::from(1199)), U256::from(1));
+        assert_eq!(halving_epoch(genesis, U256::ZERO, U256::from(5000)), U256::ZERO);
+
+        let factor = U256::from(8000);
+        assert_eq!(halved(factor, genesis, interval, U256::from(1000)), factor);
+        assert_eq!(halved(factor, genesis, interval, U256::from(1200)), U256::from(2000));
+        assert_eq!(halved(U256::MAX, genesis, interval, U256::from(1000 + 256 * 100)), U256::ZERO);
+    }
+
+    #[test]
+    fn test_cap_bonuses() {
+        assert_eq!(cap_bonuses(U256::from(100), U256::from(500), U256::from(1000)), (U256::from(100), U256::from(500)));
+        assert_eq!(cap_bonuses(U256::from(100), U256::from(500), U256::from(550)), (U256::from(50), U256::from(500)));
+        assert_eq!(cap_bonuses(U256::from(100), U256::from(500), U256::from(300)), (U256::ZERO, U256::from(300)));
+    }
+
+    #[test]
+    fn test_vested() {
+        let (granted, start, cliff, duration) = (U256::from(1000), U256::from(1000), U256::from(100), U256::from(1000));
+        let at = |time: u64| vested(granted, start, cliff, duration, U256::from(time)).unwrap();
+        assert_eq!([at(0), at(1099), at(1100), at(1500), at(2000), at(9999)], [0, 0, 100, 500, 1000, 1000].map(U256::from));
+
+        assert_eq!(vested(granted, start, cliff, U256::ZERO, U256::from(1100)), Some(granted));
+        assert_eq!(vested(granted, U256::MAX, cliff, duration, U256::MAX), None);
+    }
+}
@@ -0,0 +1,122 @@
+//! TypeScript and JSON Schema definitions for the `sol!` structs of the public ABI.
+//!
+//! Printed by the `export-abi` binary with `schemas` as its first argument so clients can keep
+//! their models in step with the contract. Every definition is derived from the struct's EIP-712
+//! type string, so a field added to a `sol!` struct shows up here without touching this module.
+//! Integers wider than 48 bits are carried as decimal strings because they do not fit a JSON number.
+
+use std::{format, string::String, vec::Vec};
+
+use alloy_sol_types::SolStruct;
+
+use crate::{CampaignStats, RewardBreakdown, RewardClaim};
+
+/// Returns the `(solidity type, field name)` pairs of `T` in declaration order.
+fn fields<T: SolStruct>() -> Vec<(String, String)> {
+    let root = T::eip712_root_type();
+    let body = &root[T::NAME.len() + 1..root.len() - 1];
+    body.split(',')
+        .filter(|field| !field.is_empty())
+        .map(|field| {
+            let (ty, name) = field.split_once(' ').expect("eip712 field is `type name`");
+            (String::from(ty), String::from(name))
+        })
+        .collect()
+}
+
+/// Bit width of a `uintN`/`intN` type, or `None` for any other type.
+fn int_bits(ty: &str) -> Option<u16> {
+    let bits = ty.strip_prefix("uint").or_else(|| ty.strip_prefix("int"))?;
+    Some(if bits.is_empty() { 256 } else { bits.parse().ok()? })
+}
+
+fn typescript_type(ty: &str) -> &'static str {
+    match (ty, int_bits(ty)) {
+        (_, Some(bits)) if bits <= 48 => "number",
+        (_, Some(_)) => "string",
+        ("address", _) => "`0x${string}`",
+        ("bool", _) => "boolean",
+        _ => "string",
+    }
+}
+
+fn json_schema_type(ty: &str) -> String {
+    match (ty, int_bits(ty)) {
+        (_, Some(bits)) if bits <= 48 && ty.starts_with('u') => {
+            format!(r#"{{ "type": "integer", "minimum": 0, "maximum": {} }}"#, (1u64 << bits) - 1)
+        }
+        (_, Some(bits)) if bits <= 48 => format!(r#"{{ "type": "integer", "minimum": -{0}, "maximum": {1} }}"#, 1u64 << (bits - 1), (1u64 << (bits - 1)) - 1),
+        (_, Some(_)) if ty.starts_with('u') => String::from(r#"{ "type": "string", "pattern": "^[0-9]+$" }"#),
+        (_, Some(_)) => String::from(r#"{ "type": "string", "pattern": "^-?[0-9]+$" }"#),
+        ("address", _) => String::from(r#"{ "type": "string", "pattern": "^0x[0-9a-fA-F]{40}$" }"#),
+        ("bool", _) => String::from(r#"{ "type": "boolean" }"#),
+        _ => String::from(r#"{ "type": "string", "pattern": "^0x([0-9a-fA-F]{2})*$" }"#),
+    }
+}
+
+/// TypeScript interface mirroring `T`.
+pub fn typescript<T: SolStruct>() -> String {
+    let mut out = format!("export interface {} {{\n", T::NAME);
+    for (ty, name) in fields::<T>() {
+        out += &format!("  /** {ty} */\n  {name}: {};\n", typescript_type(&ty));
+    }
+    out + "}\n"
+}
+
+/// JSON Schema (draft 2020-12) describing `T` as a JSON object.
+pub fn json_schema<T: SolStruct>() -> String {
+    let fields = fields::<T>();
+    let properties: Vec<String> = fields.iter().map(|(ty, name)| format!(r#"    "{name}": {}"#, json_schema_type(ty))).collect();
+    let required: Vec<String> = fields.iter().map(|(_, name)| format!(r#""{name}""#)).collect();
+    format!(
+        "{{\n  \"$schema\": \"https://json-schema.org/draft/2020-12/schema\",\n  \"title\": \"{}\",\n  \"type\": \"object\",\n  \"properties\": {{\n{}\n  }},\n  \"required\": [{}],\n  \"additionalProperties\": false\n}}\n",
+        T::NAME,
+        properties.join(",\n"),
+        required.join(", "),
+    )
+}
+
+/// TypeScript definitions followed by the JSON schemas of every ABI struct.
+pub fn all_schemas() -> String {
+    let typescript = [typescript::<RewardBreakdown>(), typescript::<CampaignStats>(), typescript::<RewardClaim>()];
+    let schemas = [json_schema::<RewardBreakdown>(), json_schema::<CampaignStats>(), json_schema::<RewardClaim>()];
+    format!("// TypeScript\n\n{}\n// JSON Schema\n\n{}", typescript.join("\n"), schemas.join("\n"))
+}
+
+/// Prints [`all_schemas`] to stdout.
+pub fn print_schemas() {
+    std::print!("{}", all_schemas());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_typescript_follows_struct_fields() {
+        assert_eq!(
+            typescript::<CampaignStats>(),
+            "export interface CampaignStats {\n  /** uint256 */\n  participants: string;\n  /** uint256 */\n  total_accrued: string;\n  /** uint256 */\n  total_claimed: string;\n  /** uint256 */\n  remaining_budget: string;\n  /** uint8 */\n  state: number;\n}\n"
+        );
+        assert!(typescript::<RewardClaim>().contains("  account: `0x${string}`;\n"));
+    }
+
+    #[test]
+    fn test_json_schema_types() {
+        let schema = json_schema::<RewardClaim>();
+        assert!(schema.contains(r#""title": "RewardClaim""#));
+        assert!(schema.contains(r#""account": { "type": "string", "pattern": "^0x[0-9a-fA-F]{40}$" }"#));
+        assert!(schema.contains(r#""amount": { "type": "string", "pattern": "^[0-9]+$" }"#));
+        assert!(schema.contains(r#""flags": { "type": "integer", "minimum": 0, "maximum": 255 }"#));
+        assert!(schema.contains(r#""required": ["account", "amount", "start_time", "end_time", "flags", "deadline", "nonce"]"#));
+    }
+
+    #[test]
+    fn test_all_schemas_cover_every_struct() {
+        let out = all_schemas();
+        for name in ["RewardBreakdown", "CampaignStats", "RewardClaim"] {
+            assert!(out.contains(&format!("export interface {name} {{")));
+            assert!(out.contains(&format!(r#""title": "{name}""#)));
+        }
+    }
+}